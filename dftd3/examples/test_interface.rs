@@ -198,6 +198,30 @@ fn test_pbe0_d3_bj(model: DFTD3Model, #[case] atm: bool, #[case] expected: f64)
     assert_abs_diff_eq!(res.energy, expected, epsilon = 1e-8);
 }
 
+#[cfg(feature = "api-v0_4")]
+#[test]
+fn test_reaction_stoichiometry() {
+    let expected = -0.029489232932494884;
+    let param = DFTD3RationalDampingParam::load_param("pbe0", true);
+
+    // A -> A has no reaction energy
+    let reaction = DFTD3Reaction::new().species("reactant", model::default(), -1.0).species(
+        "product",
+        model::default(),
+        1.0,
+    );
+    let res = reaction.compute(&param);
+    assert_abs_diff_eq!(res.energy, 0.0, epsilon = 1e-12);
+    assert_abs_diff_eq!(res.contributions[0], -expected, epsilon = 1e-8);
+    assert_abs_diff_eq!(res.species_energies[0], expected, epsilon = 1e-8);
+
+    // stoichiometric coefficients scale, and reversing the reaction flips sign
+    let forward = DFTD3Reaction::new().species("A", model::default(), 2.0).compute(&param);
+    let reverse = DFTD3Reaction::new().species("A", model::default(), -2.0).compute(&param);
+    assert_abs_diff_eq!(forward.energy, 2.0 * expected, epsilon = 1e-8);
+    assert_abs_diff_eq!(reverse.energy, -forward.energy, epsilon = 1e-12);
+}
+
 #[rstest]
 fn test_anyway(model: DFTD3Model) {
     // {a1=0.4145, s8=1.2177, a2=4.8593, doi="10.1002/jcc.21759"}
@@ -214,6 +238,44 @@ fn test_anyway(model: DFTD3Model) {
     println!("Energy: {}", res.energy);
 }
 
+#[cfg(feature = "api-v0_4")]
+#[rstest]
+#[case(true, -0.029489232932494884)]
+#[case(false, -0.029589132634178342)]
+fn test_load_param_registered(model: DFTD3Model, #[case] atm: bool, #[case] expected: f64) {
+    // pbe0-d3(bj) parameters registered under a name unknown to s-dftd3
+    let param = DFTD3RationalDampingParamBuilder::default()
+        .a1(0.4145)
+        .s8(1.2177)
+        .a2(4.8593)
+        .build()
+        .unwrap();
+    dftd3_register_method("pbe0-registered", "bj", param, None);
+    dftd3_register_alias("pbe0-alias", "pbe0");
+    for method in ["pbe0-registered", "pbe0-alias"] {
+        let param = dftd3_load_param("d3bj", method, atm);
+        let res = model.get_dispersion(&param, false);
+        assert_abs_diff_eq!(res.energy, expected, epsilon = 1e-8);
+    }
+}
+
+#[cfg(feature = "api-v0_4")]
+#[rstest]
+#[case("PBE0-D3(BJ)-ATM", false, -0.029489232932494884)]
+#[case("PBE0-D3(BJ)", false, -0.029589132634178342)]
+#[case("pbe0-d3bj", true, -0.029489232932494884)]
+fn test_load_param_label(
+    model: DFTD3Model,
+    #[case] label: &str,
+    #[case] atm: bool,
+    #[case] expected: f64,
+) {
+    let param = dftd3_load_param("d3bj", label, atm);
+    let res = model.get_dispersion(&param, false);
+    assert_abs_diff_eq!(res.energy, expected, epsilon = 1e-8);
+    assert!(dftd3_load_param_f("d3zero", label, atm).is_err());
+}
+
 #[cfg(feature = "api-v0_4")]
 #[rstest]
 #[case(true, -0.022714272555175656)]
@@ -7,6 +7,95 @@
 use approx::assert_abs_diff_eq;
 use dftd3::prelude::*;
 
+#[test]
+fn test_method_metadata() {
+    assert_eq!(dftd3_display_name("m062x").as_deref(), Some("M06-2X"));
+    assert_eq!(dftd3_display_name("no-such-method"), None);
+    let meta = dftd3_method_metadata("CAM-B3LYP").unwrap();
+    assert_eq!(meta.family, Some(FunctionalFamily::Hybrid));
+    assert!(meta.range_separated);
+    assert_eq!(meta.family.unwrap().to_string(), "hybrid");
+    // every embedded method is classified
+    let db = ParameterDatabase::embedded();
+    assert_eq!(db.find_methods(None, None), db.methods());
+    assert!(db.methods().iter().all(|m| db.metadata(m).and_then(|m| m.family).is_some()));
+    let hybrids_bj = dftd3_find_methods(Some(FunctionalFamily::Hybrid), Some("bj"));
+    assert!(hybrids_bj.contains(&"pbe0".to_string()));
+    assert!(!hybrids_bj.contains(&"pbe".to_string()));
+}
+
+#[test]
+fn test_export_tables() {
+    let db = ParameterDatabase::embedded();
+    let availability = db.availability_table();
+    assert_eq!(availability.rows.len(), db.methods().len());
+    let b3lyp = availability.rows.iter().find(|r| r.method == "b3lyp").unwrap();
+    assert_eq!(b3lyp.available, ["bj", "zero", "bjm", "zerom", "op", "cso"]);
+    assert!(availability.to_csv().contains("\nb3lyp,B3LYP,hybrid,1,1,1,1,1,1\n"));
+
+    let table = db.parameter_table(Some("bj"));
+    assert_eq!(table.rows.len(), db.methods_for_variant("bj").len());
+    assert_eq!(table.to_csv(), db.parameter_table(Some("d3bj")).to_csv());
+    let markdown = table.to_markdown();
+    assert!(markdown.contains("| `b3lyp` | B3LYP | hybrid | bj | 1.0 | 1.9889 | 1.0 | 0.3981 |"));
+}
+
+#[cfg(feature = "json")]
+#[test]
+fn test_export_tables_json() {
+    let db = ParameterDatabase::embedded();
+    let json: serde_json::Value =
+        serde_json::from_str(&db.parameter_table(Some("zero")).to_json()).unwrap();
+    let pbe = json["rows"].as_array().unwrap().iter().find(|r| r["method"] == "pbe").unwrap();
+    assert_eq!(pbe["family"], "gga");
+    assert_eq!(pbe["params"]["rs6"], 1.217);
+    assert!(db.availability_table().to_json().contains("\"available\": ["));
+}
+
+#[test]
+fn test_diff_param_files() {
+    let dir = std::env::temp_dir();
+    let (old, new) = (dir.join("dftd3_test_diff_old.toml"), dir.join("dftd3_test_diff_new.toml"));
+    let embedded = include_str!("../src/parameters.toml");
+    std::fs::write(&old, embedded).unwrap();
+    let edited = embedded.replace(
+        "d3.bj = {a1=0.3981, s8=1.9889, a2=4.4211, doi=\"10.1002/jcc.21759\"}",
+        "d3.bj = {a1=0.4000, s8=1.9889, a2=4.4211, doi=\"10.1002/jcc.21759\"}",
+    );
+    std::fs::write(&new, edited).unwrap();
+    let diff = dftd3_diff_param_files(&old, &new);
+    let same = dftd3_diff_param_files(&old, &old);
+    std::fs::remove_file(&old).unwrap();
+    std::fs::remove_file(&new).unwrap();
+
+    assert!(same.is_empty());
+    let changes: Vec<(&str, &str, &str)> = diff
+        .changed_values
+        .iter()
+        .map(|c| (c.method.as_str(), c.version.as_str(), c.field.as_str()))
+        .collect();
+    assert!(changes.contains(&("b3lyp", "bj", "a1")));
+    assert!(diff.changed_dois.is_empty() && diff.added_methods.is_empty());
+    assert!(dftd3_diff_param_files_f("does-not-exist.toml", "does-not-exist.toml").is_err());
+}
+
+#[test]
+fn test_validate_param_file() {
+    let path = std::env::temp_dir().join("dftd3_test_validate.toml");
+    let user = "[parameter.my-func]\nd3.bj = {a1=0.4, s8=1.0, a2=5.0, rs6=1.2}\n";
+    std::fs::write(&path, user).unwrap();
+    let report = dftd3_validate_param_file(&path);
+    let merged = ParameterDatabase::embedded().clone().merge_files_f(&[&path]);
+    std::fs::remove_file(&path).unwrap();
+
+    assert_eq!(report.errors().len(), 1);
+    assert_eq!(report.lints[0].location, "parameter.my-func.d3.bj");
+    assert!(merged.unwrap_err().get_message().contains("unknown field 'rs6'"));
+    let embedded = dftd3_validate_param_toml(include_str!("../src/parameters.toml"));
+    assert_eq!(embedded.errors().len(), 1, "{embedded}");
+    assert!(dftd3_validate_param_file_f("does-not-exist.toml").is_err());
+}
+
 #[test]
 fn test_list_methods() {
     let methods = dftd3_list_methods();
@@ -139,6 +228,45 @@ fn test_variant_not_found() {
     assert!(result.is_err());
 }
 
+#[cfg(feature = "api-v0_4")]
+#[test]
+fn test_lookup_suggestions() {
+    let message = dftd3_get_damping_param_f("b3lpy", "bj").unwrap_err().get_message();
+    assert!(message.contains("did you mean b3lyp"), "{message}");
+    let message = dftd3_get_damping_param_f("b97d", "cso").unwrap_err().get_message();
+    assert!(message.ends_with("b97d has bj, zero, bjm, zerom, op"), "{message}");
+    let message =
+        dftd3_parse_damping_param_from_toml_f(r#"{version = "d3bjj"}"#).unwrap_err().get_message();
+    assert!(message.contains("did you mean bj"), "{message}");
+    match dftd3_diagnose_lookup(Some("b97d"), "cso") {
+        Some(ParameterLookupIssue::MissingVariant { available, .. }) => {
+            assert_eq!(available, ["bj", "zero", "bjm", "zerom", "op"])
+        },
+        issue => panic!("Expected MissingVariant, got: {issue:?}"),
+    }
+}
+
+#[cfg(feature = "api-v0_5")]
+#[test]
+fn test_citations() {
+    use DFTD3CitationRole::*;
+    let citations = dftd3_citations(&dftd3_get_damping_param("b97d", "op"));
+    let summary: Vec<(&str, &[DFTD3CitationRole])> =
+        citations.iter().map(|c| (c.reference.key.as_str(), c.roles.as_slice())).collect();
+    assert_eq!(summary, [("grimme2010", &[Method][..]), ("witte2017", &[Damping, Parameters])]);
+
+    let citations = dftd3_citations(&dftd3_get_damping_param("r2scan", "bj"));
+    let last = citations.last().unwrap();
+    assert_eq!(last.roles, [Parameters]);
+    let bibtex = dftd3_citations_to_bibtex(&citations);
+    assert_eq!(bibtex.matches("@article{").count(), 6);
+    assert!(!bibtex.contains("@misc{"));
+    assert_eq!(last.reference.key, "ehlert2021");
+    assert!(bibtex.contains(&format!("@article{{{},", last.reference.key)));
+    let ris = dftd3_citations_to_ris(&citations);
+    assert_eq!(ris.matches("ER  - \n").count(), citations.len());
+}
+
 #[cfg(feature = "api-v0_4")]
 #[test]
 fn test_all_parameters() {
@@ -149,6 +277,65 @@ fn test_all_parameters() {
     assert!(params.len() > 50);
 }
 
+#[cfg(feature = "api-v0_4")]
+#[test]
+fn test_load_param_files() {
+    let path = std::env::temp_dir().join("dftd3_test_load_param_files.toml");
+    std::fs::write(&path, "[parameter.inhouse-b3lyp]\nd3.bj = {a1=0.42, s8=1.5, a2=4.2}\n")
+        .unwrap();
+    let conflicts = dftd3_load_param_files(&[&path]);
+    assert!(conflicts.is_empty());
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(dftd3_list_methods().contains(&"inhouse-b3lyp".to_string()));
+    let param = dftd3_get_damping_param("inhouse_b3lyp", "bj");
+    assert_eq!(param.param.get_field("a1"), Some(0.42));
+    let param = dftd3_parse_damping_param_from_toml(r#"{version="bj", method="inhouse-b3lyp"}"#);
+    assert_eq!(param.param.get_field("a2"), Some(4.2));
+    assert_eq!(param.param.s9(), 1.0);
+    assert!(dftd3_load_param_files_f(&["does-not-exist.toml"]).is_err());
+}
+
+#[cfg(feature = "api-v0_4")]
+#[test]
+fn test_register_method_alias() {
+    let param =
+        DFTD3RationalDampingParamBuilder::default().a1(0.41).s8(1.6).a2(4.3).build().unwrap();
+    assert!(dftd3_register_method("registered-func", "bj", param, None).is_none());
+    dftd3_register_alias("registered-alias", "registered-func");
+    dftd3_register_alias("b3lyp5", "b3lyp");
+
+    let param = dftd3_get_damping_param("registered_alias", "bj");
+    assert_eq!(param.param.get_field("a2"), Some(4.3));
+    let param =
+        dftd3_parse_damping_param_from_toml(r#"{version="bj", method="b3lyp5", atm=false}"#);
+    assert_eq!(param.param.get_field("a1"), Some(0.3981));
+    assert_eq!(param.param.s9(), 0.0);
+    assert!(dftd3_register_alias_f("another-alias", "unknown-func").is_err());
+}
+
+#[cfg(feature = "api-v0_4")]
+#[test]
+fn test_cross_check_params() {
+    // registered methods are checked as well
+    let param =
+        DFTD3RationalDampingParamBuilder::default().a1(0.42).s8(1.5).a2(4.2).build().unwrap();
+    dftd3_register_method("cross-check-func", "bj", param, None);
+
+    let report = dftd3_cross_check_params(None);
+    println!("{report}");
+    assert!(report.checked > 100);
+    assert!(report.mismatches().is_empty());
+    let issue = |method: &str, version: &str| {
+        report.entries.iter().find(|e| e.method == method && e.version == version)
+    };
+    let registered = issue("cross-check-func", "bj").unwrap();
+    assert!(matches!(registered.issue, DFTD3CrossCheckIssue::MissingInLibrary { .. }));
+    // CF22D D3(0) has no s8, reported instead of aborting the check
+    let cf22d = issue("cf22d", "zero").unwrap();
+    assert!(matches!(cf22d.issue, DFTD3CrossCheckIssue::InvalidDatabaseEntry { .. }));
+}
+
 fn main() {
     println!("Run with: cargo test --example test_parameters");
 }
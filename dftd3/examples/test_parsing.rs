@@ -198,7 +198,90 @@ fn test_method_name_with_underscores() {
 
 #[test]
 fn test_missing_version_error() {
-    let result = dftd3_parse_damping_param_from_toml_f(r#"{method = "b3lyp"}"#);
+    let result = dftd3_parse_damping_param_from_toml_f(r#"{a1 = 0.4, s8 = 1.9, a2 = 4.4}"#);
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_method_without_version() {
+    let param = dftd3_parse_damping_param_from_toml(r#"{method = "b3lyp"}"#);
+    assert_eq!(param.version(), "bj");
+    assert_eq!(param.param.get_field("a1"), Some(0.3981));
+    // falls back to zero damping
+    let param = dftd3_parse_damping_param_from_toml(r#"{method = "M06-2X", atm = false}"#);
+    assert_eq!(param.version(), "zero");
+    assert_eq!(param.param.s9(), 0.0);
+    // overrides apply to the chosen variant
+    let param = dftd3_parse_damping_param_from_toml(r#"{method = "b3lyp", a1 = 0.5}"#);
+    assert_eq!(param.param.get_field("a1"), Some(0.5));
+    let result = dftd3_parse_damping_param_from_toml_f(r#"{method = "b3lyp", rs6 = 1.0}"#);
+    assert!(result.is_err());
+}
+
+// --- Method labels ---
+#[cfg(feature = "api-v0_4")]
+#[test]
+fn test_method_label() {
+    let cases = [
+        ("B3LYP-D3(BJ)", "b3lyp", "bj", false),
+        ("wB97X-D3", "wb97x", "zero", false),
+        ("PBE0-D3(0)", "pbe0", "zero", false),
+        ("PBE0-D3ZERO", "pbe0", "zero", false),
+        ("B2PLYP-D3BJ-ATM", "b2plyp", "bj", true),
+        ("B97-D3(BJ)", "b97d", "bj", false),
+        ("PBE-D3M(BJ)", "pbe", "bjm", false),
+    ];
+    for (label, method, version, atm) in cases {
+        let parsed = dftd3_parse_method_label(label);
+        assert_eq!(parsed, DFTD3MethodLabel {
+            method: method.into(),
+            version: version.into(),
+            atm
+        });
+    }
+
+    let param = dftd3_get_damping_param_from_label("B3LYP-D3(BJ)");
+    assert_eq!(param.param.get_field("a1"), Some(0.3981));
+    assert_eq!(param.param.s9(), 0.0);
+    let param = dftd3_get_damping_param_from_label("B3LYP-D3(BJ)-ATM");
+    assert_eq!(param.param.s9(), 1.0);
+
+    let err = dftd3_parse_method_label_f("B3LYP").unwrap_err().to_string();
+    assert!(err.contains("no dispersion part"), "{err}");
+    let err = dftd3_parse_method_label_f("B3LYP-D3(XYZ)").unwrap_err().to_string();
+    assert!(err.contains("Unknown damping variant 'D3(XYZ)'"), "{err}");
+    let err = dftd3_parse_method_label_f("NoSuchFunctional-D3(BJ)").unwrap_err().to_string();
+    assert!(err.contains("Method 'NoSuchFunctional'"), "{err}");
+}
+
+#[cfg(feature = "api-v0_4")]
+#[test]
+fn test_method_label_in_toml() {
+    // version and atm from label
+    let param = dftd3_parse_damping_param_from_toml(r#"{method = "B3LYP-D3(BJ)-ATM"}"#);
+    assert_eq!(param.param.version(), "bj");
+    assert_eq!(param.param.s9(), 1.0);
+    // explicit atm = false conflicts with -ATM
+    let err =
+        dftd3_parse_damping_param_from_toml_f(r#"{method = "B3LYP-D3(BJ)-ATM", atm = false}"#)
+            .unwrap_err()
+            .to_string();
+    assert!(err.contains("conflicts with the -ATM suffix"), "{err}");
+    // explicit atm = true adds the ATM term to a label without -ATM
+    let param = dftd3_parse_damping_param_from_toml(r#"{method = "B3LYP-D3(BJ)", atm = true}"#);
+    assert_eq!(param.param.s9(), 1.0);
+    // no -ATM suffix and no atm flag: s9 = 0, as for the label itself
+    let param = dftd3_parse_damping_param_from_toml(r#"{method = "B3LYP-D3(BJ)"}"#);
+    assert_eq!(param.param.version(), "bj");
+    assert_eq!(param.param.s9(), 0.0);
+    // agreeing version, atm from table
+    let param = dftd3_parse_damping_param_from_toml(
+        r#"{version = "d3bj", method = "B3LYP-D3(BJ)", atm = false}"#,
+    );
+    assert_eq!(param.param.s9(), 0.0);
+    // conflicting version
+    let result =
+        dftd3_parse_damping_param_from_toml_f(r#"{version = "zero", method = "B3LYP-D3(BJ)"}"#);
     assert!(result.is_err());
 }
 
@@ -219,7 +302,7 @@ method = "b3lyp""#;
 #[test]
 fn test_parse_from_json() {
     let input = r#"{"version": "bj", "method": "b3lyp"}"#;
-    let param = dftd3_parse_damping_param_from_json(input).unwrap();
+    let param = dftd3_parse_damping_param_from_json_f(input).unwrap();
     match &param.param {
         DFTD3DampingParamEnum::Rational(data) => assert_abs_diff_eq!(data.a1, 0.3981),
         _ => panic!("Expected Rational variant"),
@@ -230,7 +313,7 @@ fn test_parse_from_json() {
 #[test]
 fn test_parse_from_json_atm() {
     let input = r#"{"version": "bj", "method": "b3lyp", "atm": false}"#;
-    let param = dftd3_parse_damping_param_from_json(input).unwrap();
+    let param = dftd3_parse_damping_param_from_json_f(input).unwrap();
     match &param.param {
         DFTD3DampingParamEnum::Rational(data) => assert_abs_diff_eq!(data.s9, 0.0),
         _ => panic!("Expected Rational variant"),
@@ -279,6 +362,64 @@ fn test_cso_variant() {
     }
 }
 
+// --- Serde: embedded in a config struct, round trip ---
+#[cfg(feature = "api-v0_4")]
+#[test]
+fn test_serde_round_trip() {
+    #[derive(serde::Deserialize, serde::Serialize)]
+    struct Config {
+        dispersion: DFTD3DampingParam,
+    }
+    let input = "[dispersion]\nmethod = \"B3LYP-D3(BJ)\"\na1 = 0.4\natm = false";
+    let config: Config = toml::from_str(input).unwrap();
+    assert_eq!(config.dispersion.param.get_field("a1"), Some(0.4));
+    assert_abs_diff_eq!(config.dispersion.param.s9(), 0.0);
+
+    let written = toml::to_string(&config).unwrap();
+    assert!(written.starts_with("[dispersion]\nversion = \"bj\"\na1 = 0.4\n"));
+    assert!(written.ends_with("doi = \"10.1002/jcc.21759\"\n"));
+    let reread: Config = toml::from_str(&written).unwrap();
+    assert_eq!(toml::to_string(&reread).unwrap(), written);
+
+    let error = toml::from_str::<Config>("[dispersion]\nversion = \"bj\"\nrs6 = 1.2").err();
+    assert!(error.unwrap().to_string().contains("Unknown parameter 'rs6'"));
+}
+
+#[cfg(all(feature = "api-v0_4", feature = "json"))]
+#[test]
+fn test_serde_json() {
+    let param: DFTD3DampingParam =
+        serde_json::from_str(r#"{"version": "zero", "method": "pbe", "doi": "10.0/x"}"#).unwrap();
+    assert_eq!(param.doi.as_deref(), Some("10.0/x"));
+    let json = serde_json::to_value(&param).unwrap();
+    assert_eq!(json["version"], "zero");
+    assert_eq!(json["rs6"], 1.217);
+    let reread: DFTD3DampingParamEnum = serde_json::from_value(json).unwrap();
+    assert_eq!(reread.to_table(), param.param.to_table());
+}
+
+// --- Explain mode: origin of every field ---
+#[cfg(feature = "api-v0_4")]
+#[test]
+fn test_explain_registered_method() {
+    let param = DFTD3RationalDampingParamBuilder::default().a1(0.4).s8(1.2).a2(4.8).build();
+    dftd3_register_method("explained-func", "bj", param.unwrap(), Some("10.0/explained"));
+    let explanation =
+        dftd3_explain_damping_param_from_toml(r#"{method = "explained_func", s8 = 1.3}"#);
+    assert_eq!(explanation.method.as_deref(), Some("explained-func"));
+    assert_eq!(explanation.param.doi.as_deref(), Some("10.0/explained"));
+    let source = |field: &str| {
+        explanation.fields.iter().find(|f| f.field == field).map(|f| f.source.clone()).unwrap()
+    };
+    let registered = Some("runtime registration".to_string());
+    assert_eq!(source("a1"), DFTD3ParamSource::Database { source: registered });
+    assert_eq!(source("s8"), DFTD3ParamSource::UserOverride);
+    assert!(explanation.to_string().contains("  a1 = 0.4 (database, runtime registration)\n"));
+    assert!(
+        dftd3_explain_damping_param_from_toml_f(r#"{method = "explained_func", rs6 = 1}"#).is_err()
+    );
+}
+
 fn main() {
     println!("Run with: cargo test --example test_parsing --features=\"dynamic_loading\"");
 }
@@ -0,0 +1,393 @@
+//! Non-covalent benchmark sets and a runner for D3 interaction energies.
+//!
+//! A benchmark set is a list of complexes with fragment definitions and
+//! optional reference D3 interaction energies. The interaction energy of each
+//! complex is
+//!
+//! ```text
+//! E_int = E(complex) - sum_f E(fragment f)
+//! ```
+//!
+//! evaluated with fragment geometries taken from the complex. Results are
+//! reported per system, together with summary statistics (MD, MAD, RMSD,
+//! maximum) of deviations from the stored reference values. This serves as a
+//! regression and comparison harness for parameter changes and library
+//! upgrades.
+//!
+//! No benchmark sets are embedded in the crate: S22, S66 and L7 geometries,
+//! and reference D3 interaction energies for them, are not shipped. Such
+//! references need geometries from the published sets and energies computed
+//! with s-dftd3, and neither is part of this repository. Sets are loaded from
+//! TOML files of the following format:
+//!
+//! ```toml
+//! name = "S22"
+//! unit = "angstrom"          # optional, "bohr" (default) or "angstrom"
+//!
+//! [[system]]
+//! name = "argon dimer"
+//! numbers = [18, 18]
+//! positions = [0.0, 0.0, 0.0, 0.0, 0.0, 3.76]
+//! fragments = [[0], [1]]
+//! reference = -1.0e-4       # optional, reference D3 interaction energy in Hartree
+//! ```
+//!
+//! # Example
+//!
+//! ```no_run
+//! use dftd3::prelude::*;
+//!
+//! let input = std::fs::read_to_string("s22.toml").unwrap();
+//! let set = DFTD3BenchmarkSet::from_toml(&input);
+//!
+//! let param = dftd3_load_param("d3bj", "pbe0", true);
+//! let res = set.run(&param);
+//! println!("{}", res.to_csv());
+//! println!("MAD: {:.3e}", res.statistics.mad);
+//!
+//! // every method and variant in the parameter database
+//! let results = set.run_database(true);
+//! println!("{}", dftd3_benchmark_database_to_csv(&results.entries));
+//! ```
+
+use crate::elements::*;
+use crate::fitting::*;
+use crate::interface::*;
+use crate::reaction::*;
+use crate::utils::*;
+use serde::Deserialize;
+
+/* #region Benchmark set */
+
+/// A complex of a benchmark set.
+#[derive(Debug, Clone)]
+pub struct DFTD3BenchmarkSystem {
+    /// Name of system.
+    pub name: String,
+    /// Element index of atoms.
+    pub numbers: Vec<usize>,
+    /// Atomic positions in Bohr (natom * 3).
+    pub positions: Vec<f64>,
+    /// Atom indices of fragments; every atom belongs to exactly one fragment.
+    pub fragments: Vec<Vec<usize>>,
+    /// Reference D3 interaction energy in Hartree, if available.
+    pub reference: Option<f64>,
+}
+
+/// A benchmark set of complexes, loaded from TOML (no sets are embedded).
+#[derive(Debug, Clone)]
+pub struct DFTD3BenchmarkSet {
+    /// Name of set.
+    pub name: String,
+    /// Complexes of set.
+    pub systems: Vec<DFTD3BenchmarkSystem>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct BenchmarkSetInput {
+    name: String,
+    #[serde(default)]
+    unit: Option<String>,
+    #[serde(default)]
+    system: Vec<BenchmarkSystemInput>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct BenchmarkSystemInput {
+    name: String,
+    numbers: Vec<usize>,
+    positions: Vec<f64>,
+    fragments: Vec<Vec<usize>>,
+    #[serde(default)]
+    reference: Option<f64>,
+}
+
+/* #endregion */
+
+/* #region Benchmark results */
+
+/// Interaction energy of one system of a benchmark set.
+#[derive(Debug, Clone)]
+pub struct DFTD3BenchmarkEntry {
+    /// Name of system.
+    pub name: String,
+    /// D3 interaction energy in Hartree.
+    pub energy: f64,
+    /// Reference D3 interaction energy in Hartree, if available.
+    pub reference: Option<f64>,
+    /// Deviation from reference (energy - reference), if available.
+    pub deviation: Option<f64>,
+}
+
+/// Results of a benchmark set with one damping parameter set.
+#[derive(Debug, Clone)]
+pub struct DFTD3BenchmarkResult {
+    /// Name of set.
+    pub set: String,
+    /// Per-system results.
+    pub entries: Vec<DFTD3BenchmarkEntry>,
+    /// Statistics of deviations of systems with reference values.
+    pub statistics: DFTD3ResidualStatistics,
+}
+
+/// Benchmark results for one method and variant of the parameter database.
+#[derive(Debug, Clone)]
+pub struct DFTD3BenchmarkDatabaseEntry {
+    /// Method name (normalized database key).
+    pub method: String,
+    /// DFT-D3 variant (normalized, e.g. `bj`).
+    pub version: String,
+    /// Benchmark results.
+    pub result: DFTD3BenchmarkResult,
+}
+
+/// Benchmark results over the parameter database.
+#[derive(Debug, Clone)]
+pub struct DFTD3BenchmarkDatabaseOutput {
+    /// Results, sorted by variant and method.
+    pub entries: Vec<DFTD3BenchmarkDatabaseEntry>,
+    /// Methods and variants skipped because parameters could not be built or
+    /// evaluated.
+    pub failures: Vec<DFTD3DatabaseFailure>,
+}
+
+impl DFTD3BenchmarkResult {
+    /// Format per-system results as CSV, one row per system.
+    ///
+    /// Columns are `set,system,energy,reference,deviation`; missing reference
+    /// values are left empty.
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("set,system,energy,reference,deviation\n");
+        for row in self.csv_rows() {
+            csv += &row;
+        }
+        csv
+    }
+
+    fn csv_rows(&self) -> Vec<String> {
+        let opt = |x: Option<f64>| x.map_or(String::new(), |x| format!("{x:.12e}"));
+        self.entries
+            .iter()
+            .map(|e| {
+                format!(
+                    "{},{},{:.12e},{},{}\n",
+                    csv_field(&self.set),
+                    csv_field(&e.name),
+                    e.energy,
+                    opt(e.reference),
+                    opt(e.deviation)
+                )
+            })
+            .collect()
+    }
+}
+
+/// Format benchmark results over the parameter database as CSV, one row per
+/// method, variant and system.
+///
+/// Columns are `method,version,set,system,energy,reference,deviation`.
+pub fn dftd3_benchmark_database_to_csv(results: &[DFTD3BenchmarkDatabaseEntry]) -> String {
+    let mut csv = String::from("method,version,set,system,energy,reference,deviation\n");
+    for entry in results {
+        for row in entry.result.csv_rows() {
+            csv += &format!("{},{},{row}", csv_field(&entry.method), csv_field(&entry.version));
+        }
+    }
+    csv
+}
+
+/// Quote CSV field if necessary.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/* #endregion */
+
+/* #region Benchmark runner */
+
+impl DFTD3BenchmarkSet {
+    /// Load benchmark set from TOML string.
+    pub fn from_toml(input: &str) -> Self {
+        Self::from_toml_f(input).unwrap()
+    }
+
+    /// Compute interaction energies with one damping parameter set.
+    pub fn run(&self, param: &DFTD3Param) -> DFTD3BenchmarkResult {
+        self.run_f(param).unwrap()
+    }
+
+    /// Compute interaction energies for every method and variant in the
+    /// parameter database, sorted by variant and method.
+    ///
+    /// Methods that fail are skipped and listed in
+    /// [`DFTD3BenchmarkDatabaseOutput::failures`].
+    ///
+    /// - `atm` - use three-body correction (true) or two-body correction
+    ///   (false)
+    pub fn run_database(&self, atm: bool) -> DFTD3BenchmarkDatabaseOutput {
+        self.run_database_f(atm).unwrap()
+    }
+
+    /// Load benchmark set from TOML string (failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3BenchmarkSet::from_toml`]
+    pub fn from_toml_f(input: &str) -> Result<Self, DFTD3Error> {
+        let input: BenchmarkSetInput = toml::from_str(input)
+            .map_err(|e| DFTD3Error::Rust(format!("Benchmark set parsing error: {e}")))?;
+        let scale = match input.unit.as_deref().map(|u| u.to_lowercase()).as_deref() {
+            None | Some("bohr") => 1.0,
+            Some("angstrom") => 1.0 / BOHR_TO_ANGSTROM,
+            Some(unit) => {
+                return Err(DFTD3Error::Rust(format!(
+                    "Unknown length unit '{unit}', expected 'bohr' or 'angstrom'"
+                )))
+            },
+        };
+        let systems = input
+            .system
+            .into_iter()
+            .map(|s| {
+                check_structure_dims(&s.numbers, &s.positions, None, None)?;
+                check_partition(&s.fragments, s.numbers.len(), &format!("system '{}'", s.name))?;
+                Ok(DFTD3BenchmarkSystem {
+                    name: s.name,
+                    numbers: s.numbers,
+                    positions: s.positions.iter().map(|x| x * scale).collect(),
+                    fragments: s.fragments,
+                    reference: s.reference,
+                })
+            })
+            .collect::<Result<Vec<_>, DFTD3Error>>()?;
+        Ok(Self { name: input.name, systems })
+    }
+
+    /// Compute interaction energies with one damping parameter set
+    /// (failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3BenchmarkSet::run`]
+    pub fn run_f(&self, param: &DFTD3Param) -> Result<DFTD3BenchmarkResult, DFTD3Error> {
+        let reactions = self.reactions()?;
+        self.evaluate(&reactions, param)
+    }
+
+    /// Compute interaction energies for every method and variant in the
+    /// parameter database (failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3BenchmarkSet::run_database`]
+    pub fn run_database_f(&self, atm: bool) -> Result<DFTD3BenchmarkDatabaseOutput, DFTD3Error> {
+        let reactions = self.reactions()?;
+        let (params, mut failures) = database_params_f(atm)?;
+        let mut entries = vec![];
+        for (method, version, param) in params {
+            match self.evaluate(&reactions, &param) {
+                Ok(result) => entries.push(DFTD3BenchmarkDatabaseEntry { method, version, result }),
+                Err(e) => {
+                    failures.push(DFTD3DatabaseFailure { method, version, error: e.to_string() })
+                },
+            }
+        }
+        Ok(DFTD3BenchmarkDatabaseOutput { entries, failures })
+    }
+
+    /// Interaction reactions (complex - fragments) of all systems.
+    fn reactions(&self) -> Result<Vec<DFTD3Reaction>, DFTD3Error> {
+        self.systems
+            .iter()
+            .map(|s| {
+                let mut reaction = DFTD3Reaction::new();
+                reaction.push(
+                    &s.name,
+                    DFTD3Model::new_f(&s.numbers, &s.positions, None, None)?,
+                    1.0,
+                );
+                for (i, fragment) in s.fragments.iter().enumerate() {
+                    let (numbers, positions) = take_atoms(&s.numbers, &s.positions, fragment);
+                    let model = DFTD3Model::new_f(&numbers, &positions, None, None)?;
+                    reaction.push(&format!("{} fragment {i}", s.name), model, -1.0);
+                }
+                Ok(reaction)
+            })
+            .collect()
+    }
+
+    fn evaluate(
+        &self,
+        reactions: &[DFTD3Reaction],
+        param: &DFTD3Param,
+    ) -> Result<DFTD3BenchmarkResult, DFTD3Error> {
+        let entries = self
+            .systems
+            .iter()
+            .zip(reactions)
+            .map(|(s, reaction)| {
+                let energy = reaction.compute_f(param)?.energy;
+                Ok(DFTD3BenchmarkEntry {
+                    name: s.name.clone(),
+                    energy,
+                    reference: s.reference,
+                    deviation: s.reference.map(|r| energy - r),
+                })
+            })
+            .collect::<Result<Vec<_>, DFTD3Error>>()?;
+        let deviations: Vec<f64> = entries.iter().filter_map(|e| e.deviation).collect();
+        Ok(DFTD3BenchmarkResult {
+            set: self.name.clone(),
+            entries,
+            statistics: DFTD3ResidualStatistics::new(&deviations),
+        })
+    }
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_benchmark_set_from_toml() {
+        let input = r#"
+            name = "toy"
+            unit = "angstrom"
+
+            [[system]]
+            name = "dimer, stacked"
+            numbers = [18, 18]
+            positions = [0.0, 0.0, 0.0, 0.0, 0.0, 3.8]
+            fragments = [[0], [1]]
+            reference = -0.0005
+        "#;
+        let set = DFTD3BenchmarkSet::from_toml(input);
+        assert_eq!(set.systems.len(), 1);
+        assert!((set.systems[0].positions[5] - 3.8 / BOHR_TO_ANGSTROM).abs() < 1e-12);
+        // fragments must partition the complex
+        for fragments in ["[[0], [2]]", "[[0, 1], [1]]", "[[0]]", "[[0, 1], []]"] {
+            let input = input.replace("[[0], [1]]", fragments);
+            assert!(DFTD3BenchmarkSet::from_toml_f(&input).is_err(), "{fragments}");
+        }
+        assert!(DFTD3BenchmarkSet::from_toml_f(&input.replace("angstrom", "pm")).is_err());
+
+        let result = DFTD3BenchmarkResult {
+            set: set.name.clone(),
+            entries: vec![DFTD3BenchmarkEntry {
+                name: set.systems[0].name.clone(),
+                energy: -0.0004,
+                reference: None,
+                deviation: None,
+            }],
+            statistics: Default::default(),
+        };
+        let csv = result.to_csv();
+        assert_eq!(csv.lines().nth(1), Some("toy,\"dimer, stacked\",-4.000000000000e-4,,"));
+    }
+}
@@ -0,0 +1,452 @@
+//! Citations of DFT-D3 parameters, with BibTeX and RIS export.
+//!
+//! A calculation with resolved damping parameters ([`DFTD3DampingParam`])
+//! should cite
+//!
+//! - the DFT-D3 method paper ([`DFTD3CitationRole::Method`]);
+//! - the paper(s) of the damping scheme ([`DFTD3CitationRole::Damping`]): BJ
+//!   (rational), zero, Smith 2016 for the modified variants, Witte 2017 for
+//!   optimized power and Schröder 2015 for CSO damping;
+//! - the paper of the method-specific parameters, given by the `doi` of the
+//!   parameter ([`DFTD3CitationRole::Parameters`]).
+//!
+//! References of the method and damping schemes are embedded as data; the
+//! parameter paper is taken from the `[citation]` section of the parameter
+//! database, and is a DOI-only reference if absent there.
+//! References cited in several roles are listed once.
+//!
+//! # Example
+//!
+//! ```
+//! use dftd3::prelude::*;
+//!
+//! let param = dftd3_get_damping_param("b3lyp", "bj");
+//! let citations = dftd3_citations(&param);
+//! let keys: Vec<&str> = citations.iter().map(|c| c.reference.key.as_str()).collect();
+//! assert_eq!(keys, ["grimme2010", "becke2005", "johnson2005", "johnson2006", "grimme2011"]);
+//! println!("{}", dftd3_citations_to_bibtex(&citations));
+//! ```
+
+use crate::parameters::{normalize_version, DFTD3DampingParam, ParameterDatabase};
+
+/* #region Reference data */
+
+/// Embedded reference (static data).
+struct KnownReference {
+    key: &'static str,
+    authors: &'static [&'static str],
+    title: &'static str,
+    journal: &'static str,
+    year: u32,
+    volume: &'static str,
+    issue: Option<&'static str>,
+    pages: &'static str,
+    doi: &'static str,
+}
+
+const GRIMME2010: KnownReference = KnownReference {
+    key: "grimme2010",
+    authors: &["Grimme, S.", "Antony, J.", "Ehrlich, S.", "Krieg, H."],
+    title: "A consistent and accurate ab initio parametrization of density functional dispersion \
+            correction (DFT-D) for the 94 elements H-Pu",
+    journal: "J. Chem. Phys.",
+    year: 2010,
+    volume: "132",
+    issue: Some("15"),
+    pages: "154104",
+    doi: "10.1063/1.3382344",
+};
+
+const BECKE2005: KnownReference = KnownReference {
+    key: "becke2005",
+    authors: &["Becke, A. D.", "Johnson, E. R."],
+    title: "A density-functional model of the dispersion interaction",
+    journal: "J. Chem. Phys.",
+    year: 2005,
+    volume: "123",
+    issue: Some("15"),
+    pages: "154101",
+    doi: "10.1063/1.2065267",
+};
+
+const JOHNSON2005: KnownReference = KnownReference {
+    key: "johnson2005",
+    authors: &["Johnson, E. R.", "Becke, A. D."],
+    title: "A post-Hartree–Fock model of intermolecular interactions",
+    journal: "J. Chem. Phys.",
+    year: 2005,
+    volume: "123",
+    issue: Some("2"),
+    pages: "024101",
+    doi: "10.1063/1.1949201",
+};
+
+const JOHNSON2006: KnownReference = KnownReference {
+    key: "johnson2006",
+    authors: &["Johnson, E. R.", "Becke, A. D."],
+    title: "A post-Hartree-Fock model of intermolecular interactions: Inclusion of higher-order \
+            corrections",
+    journal: "J. Chem. Phys.",
+    year: 2006,
+    volume: "124",
+    issue: Some("17"),
+    pages: "174104",
+    doi: "10.1063/1.2190220",
+};
+
+const GRIMME2011: KnownReference = KnownReference {
+    key: "grimme2011",
+    authors: &["Grimme, S.", "Ehrlich, S.", "Goerigk, L."],
+    title: "Effect of the damping function in dispersion corrected density functional theory",
+    journal: "J. Comput. Chem.",
+    year: 2011,
+    volume: "32",
+    issue: Some("7"),
+    pages: "1456-1465",
+    doi: "10.1002/jcc.21759",
+};
+
+const CHAI2008: KnownReference = KnownReference {
+    key: "chai2008",
+    authors: &["Chai, J.-D.", "Head-Gordon, M."],
+    title: "Long-range corrected hybrid density functionals with damped atom–atom dispersion \
+            corrections",
+    journal: "Phys. Chem. Chem. Phys.",
+    year: 2008,
+    volume: "10",
+    issue: Some("44"),
+    pages: "6615-6620",
+    doi: "10.1039/B810189B",
+};
+
+const SMITH2016: KnownReference = KnownReference {
+    key: "smith2016",
+    authors: &["Smith, D. G. A.", "Burns, L. A.", "Patkowski, K.", "Sherrill, C. D."],
+    title: "Revised damping parameters for the D3 dispersion correction to density functional \
+            theory",
+    journal: "J. Phys. Chem. Lett.",
+    year: 2016,
+    volume: "7",
+    issue: Some("12"),
+    pages: "2197-2203",
+    doi: "10.1021/acs.jpclett.6b00780",
+};
+
+const WITTE2017: KnownReference = KnownReference {
+    key: "witte2017",
+    authors: &["Witte, J.", "Mardirossian, N.", "Neaton, J. B.", "Head-Gordon, M."],
+    title: "Assessing DFT-D3 damping functions across widely used density functionals: Can we do \
+            better?",
+    journal: "J. Chem. Theory Comput.",
+    year: 2017,
+    volume: "13",
+    issue: Some("5"),
+    pages: "2043-2052",
+    doi: "10.1021/acs.jctc.7b00176",
+};
+
+const SCHROEDER2015: KnownReference = KnownReference {
+    key: "schroeder2015",
+    authors: &["Schröder, H.", "Creon, A.", "Schwabe, T."],
+    title: "Reformulation of the D3 (Becke–Johnson) dispersion correction without resorting to \
+            higher than C6 dispersion coefficients",
+    journal: "J. Chem. Theory Comput.",
+    year: 2015,
+    volume: "11",
+    issue: Some("7"),
+    pages: "3163-3170",
+    doi: "10.1021/acs.jctc.5b00400",
+};
+
+/// All embedded references.
+const KNOWN_REFERENCES: &[&KnownReference] = &[
+    &GRIMME2010,
+    &BECKE2005,
+    &JOHNSON2005,
+    &JOHNSON2006,
+    &GRIMME2011,
+    &CHAI2008,
+    &SMITH2016,
+    &WITTE2017,
+    &SCHROEDER2015,
+];
+
+/// References of the damping scheme of a normalized variant.
+fn damping_references(version: &str) -> &'static [&'static KnownReference] {
+    match version {
+        "bj" => &[&BECKE2005, &JOHNSON2005, &JOHNSON2006, &GRIMME2011],
+        "zero" => &[&GRIMME2010, &CHAI2008],
+        "bjm" | "zerom" => &[&SMITH2016],
+        "op" => &[&WITTE2017],
+        "cso" => &[&SCHROEDER2015],
+        _ => &[],
+    }
+}
+
+impl From<&KnownReference> for DFTD3Reference {
+    fn from(r: &KnownReference) -> Self {
+        DFTD3Reference {
+            key: r.key.to_string(),
+            authors: r.authors.iter().map(|a| a.to_string()).collect(),
+            title: Some(r.title.to_string()),
+            journal: Some(r.journal.to_string()),
+            year: Some(r.year),
+            volume: Some(r.volume.to_string()),
+            issue: r.issue.map(str::to_string),
+            pages: Some(r.pages.to_string()),
+            doi: r.doi.to_string(),
+        }
+    }
+}
+
+/* #endregion */
+
+/* #region Citation types */
+
+/// Bibliographic reference.
+///
+/// References not embedded in this crate only carry a DOI (all other fields
+/// are empty).
+#[derive(Debug, Clone, PartialEq)]
+pub struct DFTD3Reference {
+    /// Citation key (e.g. `grimme2010`; `doi_<doi>` for DOI-only references).
+    pub key: String,
+    /// Authors as "Last, F."
+    pub authors: Vec<String>,
+    /// Title.
+    pub title: Option<String>,
+    /// Abbreviated journal name.
+    pub journal: Option<String>,
+    /// Publication year.
+    pub year: Option<u32>,
+    /// Journal volume.
+    pub volume: Option<String>,
+    /// Journal issue.
+    pub issue: Option<String>,
+    /// Page range (`first-last`) or article number.
+    pub pages: Option<String>,
+    /// DOI.
+    pub doi: String,
+}
+
+/// Why a reference is cited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DFTD3CitationRole {
+    /// DFT-D3 method.
+    Method,
+    /// Damping scheme of the parameters.
+    Damping,
+    /// Method-specific damping parameters.
+    Parameters,
+}
+
+/// Reference with the roles it is cited for.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DFTD3Citation {
+    /// Cited reference.
+    pub reference: DFTD3Reference,
+    /// Roles of the reference, in order of first citation.
+    pub roles: Vec<DFTD3CitationRole>,
+}
+
+impl DFTD3Reference {
+    /// Reference of a DOI (case-insensitive), if known: the embedded method
+    /// and damping references, then the `[citation]` section of the global
+    /// parameter database.
+    pub fn from_doi(doi: &str) -> Option<Self> {
+        KNOWN_REFERENCES
+            .iter()
+            .find(|r| r.doi.eq_ignore_ascii_case(doi))
+            .map(|&r| r.into())
+            .or_else(|| ParameterDatabase::global_f().ok()?.citation(doi).cloned())
+    }
+
+    /// Reference of a DOI if known, or a DOI-only reference.
+    pub fn from_doi_or_bare(doi: &str) -> Self {
+        Self::from_doi(doi).unwrap_or_else(|| Self::bare(doi))
+    }
+
+    /// DOI-only reference, with key `doi_<doi>`.
+    pub(crate) fn bare(doi: &str) -> Self {
+        let key: String =
+            doi.chars().map(|c| if c.is_ascii_alphanumeric() { c } else { '_' }).collect();
+        DFTD3Reference {
+            key: format!("doi_{}", key.to_lowercase()),
+            authors: vec![],
+            title: None,
+            journal: None,
+            year: None,
+            volume: None,
+            issue: None,
+            pages: None,
+            doi: doi.to_string(),
+        }
+    }
+
+    /// Format as BibTeX `@article` entry (`@misc` for DOI-only references).
+    pub fn to_bibtex(&self) -> String {
+        let kind = if self.journal.is_some() { "article" } else { "misc" };
+        let mut fields = vec![];
+        if !self.authors.is_empty() {
+            fields.push(("author", self.authors.join(" and ")));
+        }
+        if let Some(title) = &self.title {
+            fields.push(("title", format!("{{{title}}}")));
+        }
+        if let Some(journal) = &self.journal {
+            fields.push(("journal", journal.clone()));
+        }
+        if let Some(year) = self.year {
+            fields.push(("year", year.to_string()));
+        }
+        if let Some(volume) = &self.volume {
+            fields.push(("volume", volume.clone()));
+        }
+        if let Some(issue) = &self.issue {
+            fields.push(("number", issue.clone()));
+        }
+        if let Some(pages) = &self.pages {
+            fields.push(("pages", pages.replace('-', "--")));
+        }
+        fields.push(("doi", self.doi.clone()));
+
+        let mut entry = format!("@{kind}{{{},\n", self.key);
+        for (name, value) in fields {
+            entry += &format!("  {name:<7} = {{{value}}},\n");
+        }
+        entry + "}\n"
+    }
+
+    /// Format as RIS record (`JOUR`, or `GEN` for DOI-only references).
+    pub fn to_ris(&self) -> String {
+        let kind = if self.journal.is_some() { "JOUR" } else { "GEN" };
+        let mut lines = vec![("TY", kind.to_string())];
+        lines.extend(self.authors.iter().map(|a| ("AU", a.clone())));
+        if let Some(title) = &self.title {
+            lines.push(("TI", title.clone()));
+        }
+        if let Some(journal) = &self.journal {
+            lines.push(("JO", journal.clone()));
+        }
+        if let Some(year) = self.year {
+            lines.push(("PY", year.to_string()));
+        }
+        if let Some(volume) = &self.volume {
+            lines.push(("VL", volume.clone()));
+        }
+        if let Some(issue) = &self.issue {
+            lines.push(("IS", issue.clone()));
+        }
+        if let Some(pages) = &self.pages {
+            let mut pages = pages.splitn(2, '-');
+            lines.extend(pages.next().map(|p| ("SP", p.to_string())));
+            lines.extend(pages.next().map(|p| ("EP", p.to_string())));
+        }
+        lines.push(("DO", self.doi.clone()));
+        lines.push(("ER", String::new()));
+        lines.into_iter().map(|(tag, value)| format!("{tag}  - {value}\n")).collect()
+    }
+}
+
+impl std::fmt::Display for DFTD3Reference {
+    /// Short citation, e.g. "Grimme, S. et al. J. Chem. Phys. 2010, 132,
+    /// 154104. doi:10.1063/1.3382344".
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.authors.as_slice() {
+            [] => (),
+            [author] => write!(f, "{author} ")?,
+            [first, second] => write!(f, "{first}; {second} ")?,
+            [first, ..] => write!(f, "{first} et al. ")?,
+        }
+        if let (Some(journal), Some(year)) = (&self.journal, self.year) {
+            write!(f, "{journal} {year}")?;
+            for part in [&self.volume, &self.pages].into_iter().flatten() {
+                write!(f, ", {part}")?;
+            }
+            write!(f, ". ")?;
+        }
+        write!(f, "doi:{}", self.doi)
+    }
+}
+
+/* #endregion */
+
+/* #region Citation API */
+
+/// Citations of resolved damping parameters: method, damping scheme and
+/// parameter paper (by `doi`), in this order and without duplicates.
+pub fn dftd3_citations(param: &DFTD3DampingParam) -> Vec<DFTD3Citation> {
+    dftd3_variant_citations(param.version(), param.doi.as_deref())
+}
+
+/// Citations of a variant (`bj`, `d3zero`, ...) and the DOI of its parameters
+/// (optional); see [`dftd3_citations`].
+///
+/// Unknown variants only cite the method and parameter papers.
+pub fn dftd3_variant_citations(version: &str, doi: Option<&str>) -> Vec<DFTD3Citation> {
+    use DFTD3CitationRole::*;
+    let version = normalize_version(version);
+    let mut references = vec![(DFTD3Reference::from(&GRIMME2010), Method)];
+    references.extend(damping_references(&version).iter().map(|&r| (r.into(), Damping)));
+    references.extend(doi.map(|doi| (DFTD3Reference::from_doi_or_bare(doi), Parameters)));
+
+    let mut citations: Vec<DFTD3Citation> = vec![];
+    for (reference, role) in references {
+        let doi = &reference.doi;
+        match citations.iter_mut().find(|c| c.reference.doi.eq_ignore_ascii_case(doi)) {
+            Some(citation) => citation.roles.push(role),
+            None => citations.push(DFTD3Citation { reference, roles: vec![role] }),
+        }
+    }
+    citations
+}
+
+/// Format citations as BibTeX entries, separated by blank lines.
+pub fn dftd3_citations_to_bibtex(citations: &[DFTD3Citation]) -> String {
+    citations.iter().map(|c| c.reference.to_bibtex()).collect::<Vec<_>>().join("\n")
+}
+
+/// Format citations as RIS records.
+pub fn dftd3_citations_to_ris(citations: &[DFTD3Citation]) -> String {
+    citations.iter().map(|c| c.reference.to_ris()).collect()
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_citation_export() {
+        use DFTD3CitationRole::*;
+        let citations = dftd3_variant_citations("d3zero", Some("10.1063/1.3382344"));
+        assert_eq!(citations.len(), 2);
+        assert_eq!(citations[0].roles, [Method, Damping, Parameters]);
+        assert_eq!(citations[1].reference.key, "chai2008");
+
+        let citations = dftd3_variant_citations("bjm", Some("10.1039/C7CP04913G"));
+        let keys: Vec<&str> = citations.iter().map(|c| c.reference.key.as_str()).collect();
+        assert_eq!(keys, ["grimme2010", "smith2016", "goerigk2017"]);
+        assert_eq!(citations[2].reference.authors[0], "Goerigk, L.");
+
+        let citations = dftd3_variant_citations("bjm", Some("10.5555/Unknown-1"));
+        assert_eq!(citations[2].reference.key, "doi_10_5555_unknown_1");
+
+        let bibtex = citations[1].reference.to_bibtex();
+        assert!(bibtex.starts_with("@article{smith2016,\n  author  = {Smith, D. G. A. and"));
+        assert!(bibtex.contains("  pages   = {2197--2203},\n"));
+        let bibtex = citations[2].reference.to_bibtex();
+        assert_eq!(bibtex, "@misc{doi_10_5555_unknown_1,\n  doi     = {10.5555/Unknown-1},\n}\n");
+
+        let ris = citations[1].reference.to_ris();
+        assert!(ris.starts_with("TY  - JOUR\nAU  - Smith, D. G. A.\n"));
+        assert!(ris.contains("SP  - 2197\nEP  - 2203\n"));
+        assert!(ris.ends_with("DO  - 10.1021/acs.jpclett.6b00780\nER  - \n"));
+        assert_eq!(
+            citations[1].reference.to_string(),
+            "Smith, D. G. A. et al. J. Phys. Chem. Lett. 2016, 7, 2197-2203. \
+             doi:10.1021/acs.jpclett.6b00780"
+        );
+    }
+}
@@ -0,0 +1,287 @@
+//! Cross-check of the TOML database against the library parameters.
+//!
+//! Damping parameters are available from two sources: the s-dftd3 library
+//! (`dftd3_load_*_damping`) and the global TOML database
+//! ([`ParameterDatabase::global`]). This module evaluates a probe structure
+//! with both sources for every method of [`dftd3_list_methods`] and every
+//! variant, i.e. including methods of user parameter files and registered
+//! methods, and reports
+//!
+//! - database entries that fail to convert into damping parameters
+//!   ([`DFTD3CrossCheckIssue::InvalidDatabaseEntry`]);
+//! - methods with a database entry that the library fails to load
+//!   ([`DFTD3CrossCheckIssue::MissingInLibrary`]);
+//! - methods that the library loads, but without a database entry for that
+//!   variant ([`DFTD3CrossCheckIssue::MissingInDatabase`]); methods unknown to
+//!   the database altogether cannot be listed from the library, and are not
+//!   reported;
+//! - two-body energy mismatches, i.e. differing damping parameters
+//!   ([`DFTD3CrossCheckIssue::EnergyMismatch`]);
+//! - mismatches that only appear with the ATM term, i.e. `atm = true` of the
+//!   library versus `s9` of the database
+//!   ([`DFTD3CrossCheckIssue::AtmMismatch`]).
+//!
+//! Comparing energies instead of parameter values avoids depending on the
+//! field layout of the C structs. Variants not enabled by crate features are
+//! skipped.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use dftd3::prelude::*;
+//!
+//! let report = dftd3_cross_check_params(None);
+//! println!("{report}");
+//! assert!(report.passed());
+//! ```
+
+use crate::interface::*;
+use crate::parameters::*;
+use crate::selftest::{NUMBERS_MB16, POSITIONS_MB16};
+use toml::Table;
+
+/// Default tolerance of energy differences (Hartree).
+pub const DFTD3_CROSS_CHECK_TOL: f64 = 1.0e-10;
+
+/* #region Cross-check report */
+
+/// Discrepancy between the library and the database for a method and
+/// variant.
+#[derive(Debug, Clone, PartialEq)]
+pub enum DFTD3CrossCheckIssue {
+    /// Database entry fails to convert into damping parameters.
+    InvalidDatabaseEntry {
+        /// Conversion error.
+        error: String,
+    },
+    /// Database entry exists, but the library fails to load the method.
+    MissingInLibrary {
+        /// Error message of the library.
+        error: String,
+    },
+    /// Library loads the method, but the database has no entry.
+    MissingInDatabase,
+    /// Energies without ATM term differ.
+    EnergyMismatch {
+        /// Energy of library parameters.
+        library: f64,
+        /// Energy of database parameters.
+        database: f64,
+    },
+    /// Energies without ATM term agree, but energies with ATM term differ.
+    AtmMismatch {
+        /// Energy of library parameters.
+        library: f64,
+        /// Energy of database parameters.
+        database: f64,
+    },
+}
+
+/// Cross-check issue of a method and variant.
+#[derive(Debug, Clone)]
+pub struct DFTD3CrossCheckEntry {
+    /// Method name (database key).
+    pub method: String,
+    /// Normalized variant (`bj`, `zero`, ...).
+    pub version: String,
+    /// Discrepancy found.
+    pub issue: DFTD3CrossCheckIssue,
+}
+
+/// Cross-check report of the database against the library.
+#[derive(Debug, Clone)]
+pub struct DFTD3CrossCheckReport {
+    /// Tolerance of energy differences (Hartree).
+    pub tolerance: f64,
+    /// Number of method and variant pairs evaluated with both sources.
+    pub checked: usize,
+    /// Discrepancies found, sorted by method and variant.
+    pub entries: Vec<DFTD3CrossCheckEntry>,
+}
+
+impl DFTD3CrossCheckReport {
+    /// Whether no discrepancies were found.
+    pub fn passed(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Energy and ATM mismatches only (values drifted between the sources).
+    pub fn mismatches(&self) -> Vec<&DFTD3CrossCheckEntry> {
+        use DFTD3CrossCheckIssue::*;
+        self.entries
+            .iter()
+            .filter(|e| matches!(e.issue, EnergyMismatch { .. } | AtmMismatch { .. }))
+            .collect()
+    }
+}
+
+impl std::fmt::Display for DFTD3CrossCheckReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use DFTD3CrossCheckIssue::*;
+        writeln!(
+            f,
+            "DFTD3 parameter cross-check: {} checked, {} issues (tol {:.1e})",
+            self.checked,
+            self.entries.len(),
+            self.tolerance
+        )?;
+        for entry in &self.entries {
+            let name = format!("{}-d3{}", entry.method, entry.version);
+            match &entry.issue {
+                InvalidDatabaseEntry { error } => {
+                    writeln!(f, "{name:<24} invalid database entry: {}", error.trim())?
+                },
+                MissingInLibrary { error } => {
+                    writeln!(f, "{name:<24} missing in library: {}", error.trim())?
+                },
+                MissingInDatabase => writeln!(f, "{name:<24} missing in database")?,
+                EnergyMismatch { library, database } => writeln!(
+                    f,
+                    "{name:<24} energy mismatch: library {library:.12}, database {database:.12}"
+                )?,
+                AtmMismatch { library, database } => writeln!(
+                    f,
+                    "{name:<24} ATM mismatch: library {library:.12}, database {database:.12}"
+                )?,
+            }
+        }
+        Ok(())
+    }
+}
+
+/* #endregion */
+
+/* #region Cross-check driver */
+
+/// Cross-check the global TOML database against the library parameters.
+///
+/// - `tolerance` - optional, default [`DFTD3_CROSS_CHECK_TOL`]; tolerance of
+///   energy differences of the probe structure (Hartree)
+pub fn dftd3_cross_check_params(tolerance: Option<f64>) -> DFTD3CrossCheckReport {
+    dftd3_cross_check_params_f(tolerance).unwrap()
+}
+
+/// Cross-check the global TOML database against the library parameters
+/// (failable).
+///
+/// # See also
+///
+/// [`dftd3_cross_check_params`]
+pub fn dftd3_cross_check_params_f(
+    tolerance: Option<f64>,
+) -> Result<DFTD3CrossCheckReport, DFTD3Error> {
+    let tolerance = tolerance.unwrap_or(DFTD3_CROSS_CHECK_TOL);
+    let model = DFTD3Model::new_f(&NUMBERS_MB16, &POSITIONS_MB16, None, None)?;
+    let energy = |param: &DFTD3Param| Ok(model.get_dispersion_f(param, false)?.energy);
+
+    let methods = dftd3_list_methods();
+    let db = ParameterDatabase::global_f()?;
+    let mut checked = 0;
+    let mut entries = vec![];
+    for method in &methods {
+        for version in enabled_variants() {
+            // energies with [atm = true, atm = false]
+            let database = match db.merged_table(method, version) {
+                Ok(table) => match database_energies(&table, version, energy) {
+                    Ok(energies) => Some(energies),
+                    Err(e) => {
+                        entries.push(DFTD3CrossCheckEntry {
+                            method: method.clone(),
+                            version: version.to_string(),
+                            issue: DFTD3CrossCheckIssue::InvalidDatabaseEntry {
+                                error: e.to_string(),
+                            },
+                        });
+                        continue;
+                    },
+                },
+                Err(_) => None,
+            };
+            let library = [true, false]
+                .into_iter()
+                .map(|atm| energy(&dftd3_load_param_c_f(version, method, atm)?))
+                .collect::<Result<Vec<f64>, DFTD3Error>>()
+                .map(|e| [e[0], e[1]]);
+
+            let issue = match (library, database) {
+                (Err(_), None) => None,
+                (Err(e), Some(_)) => {
+                    Some(DFTD3CrossCheckIssue::MissingInLibrary { error: e.to_string() })
+                },
+                (Ok(_), None) => Some(DFTD3CrossCheckIssue::MissingInDatabase),
+                (Ok(library), Some(database)) => {
+                    checked += 1;
+                    compare_energies(library, database, tolerance)
+                },
+            };
+            if let Some(issue) = issue {
+                entries.push(DFTD3CrossCheckEntry {
+                    method: method.clone(),
+                    version: version.to_string(),
+                    issue,
+                });
+            }
+        }
+    }
+    Ok(DFTD3CrossCheckReport { tolerance, checked, entries })
+}
+
+/// Energies `[atm = true, atm = false]` of a merged database entry.
+fn database_energies(
+    table: &Table,
+    version: &str,
+    energy: impl Fn(&DFTD3Param) -> Result<f64, DFTD3Error>,
+) -> Result<[f64; 2], DFTD3Error> {
+    let param = convert_to_damping_param(table, version)?.param;
+    let mut two_body = param.clone();
+    two_body.set_field("s9", 0.0)?;
+    Ok([energy(&param.new_param_f()?)?, energy(&two_body.new_param_f()?)?])
+}
+
+/// Database variants supported by enabled crate features.
+fn enabled_variants() -> Vec<&'static str> {
+    let enabled = |version: &str| {
+        (version != "op" || cfg!(feature = "api-v0_5"))
+            && (version != "cso" || cfg!(feature = "api-v1_3"))
+    };
+    DATABASE_VARIANTS.iter().copied().filter(|v| enabled(v)).collect()
+}
+
+/// Classify energies `[atm = true, atm = false]` of both sources.
+fn compare_energies(
+    library: [f64; 2],
+    database: [f64; 2],
+    tolerance: f64,
+) -> Option<DFTD3CrossCheckIssue> {
+    if (library[1] - database[1]).abs() > tolerance {
+        Some(DFTD3CrossCheckIssue::EnergyMismatch { library: library[1], database: database[1] })
+    } else if (library[0] - database[0]).abs() > tolerance {
+        Some(DFTD3CrossCheckIssue::AtmMismatch { library: library[0], database: database[0] })
+    } else {
+        None
+    }
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_compare_energies() {
+        use DFTD3CrossCheckIssue::*;
+        assert_eq!(compare_energies([-1.0, -0.9], [-1.0, -0.9], 1e-10), None);
+        assert_eq!(
+            compare_energies([-1.0, -0.9], [-1.1, -0.9], 1e-10),
+            Some(AtmMismatch { library: -1.0, database: -1.1 })
+        );
+        assert_eq!(
+            compare_energies([-1.0, -0.9], [-1.1, -0.8], 1e-10),
+            Some(EnergyMismatch { library: -0.9, database: -0.8 })
+        );
+        assert_eq!(enabled_variants()[..4], ["bj", "zero", "bjm", "zerom"]);
+        assert_eq!(enabled_variants().contains(&"op"), cfg!(feature = "api-v0_5"));
+        assert_eq!(enabled_variants().contains(&"cso"), cfg!(feature = "api-v1_3"));
+    }
+}
@@ -0,0 +1,374 @@
+//! Lattice energy of molecular crystals.
+//!
+//! The dispersion contribution to the lattice energy of a molecular crystal
+//! with `Z` molecules in the unit cell is
+//!
+//! ```text
+//! E_latt = E(crystal) / Z - sum_i E(molecule i) / Z
+//! ```
+//!
+//! Molecules are detected from covalent connectivity across periodic
+//! boundaries, then unwrapped into whole molecules to build isolated
+//! molecular models.
+//!
+//! Per-molecule lattice energies are obtained by partitioning the crystal
+//! energy into atomic contributions with the pairwise-resolved dispersion
+//! energy, so that their mean equals the total lattice energy.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use dftd3::prelude::*;
+//!
+//! # let numbers: Vec<usize> = vec![];
+//! # let positions: Vec<f64> = vec![];
+//! # let lattice: Vec<f64> = vec![];
+//! let driver = DFTD3LatticeEnergyDriver::new(&numbers, &positions, &lattice, None);
+//! println!("number of molecules: {}", driver.molecules().len());
+//!
+//! let param = dftd3_load_param("d3bj", "pbe0", true);
+//! let res = driver.compute(&param, false);
+//! println!("lattice energy per molecule: {}", res.energy);
+//! ```
+
+use crate::elements::covalent_radius;
+use crate::interface::*;
+use crate::utils::*;
+use std::collections::VecDeque;
+
+/// Default scaling of the sum of covalent radii for bond detection.
+pub const DFTD3_BOND_SCALE: f64 = 1.2;
+
+/* #region Molecule detection */
+
+/// A whole molecule detected in the unit cell.
+#[derive(Debug, Clone)]
+pub struct DFTD3CrystalMolecule {
+    /// Indices of the atoms of this molecule in the unit cell.
+    pub atoms: Vec<usize>,
+    /// Atomic numbers of this molecule.
+    pub numbers: Vec<usize>,
+    /// Unwrapped atomic positions of this molecule in Bohr (natom * 3).
+    pub positions: Vec<f64>,
+}
+
+/// Detect molecules in a unit cell by connectivity across periodic boundaries
+/// and unwrap them into whole molecules.
+///
+/// Two atoms are bonded if their distance (for any periodic image) is smaller
+/// than `bond_scale` times the sum of their covalent radii.
+///
+/// - `numbers` - element index (6 for O, 7 for N) in the unit cell
+/// - `positions` - atomic positions in Bohr (natom * 3)
+/// - `lattice` - lattice parameters (3 * 3)
+/// - `periodic` - optional, periodicity (3), default periodic in all directions
+/// - `bond_scale` - scaling of the sum of covalent radii, e.g.
+///   [`DFTD3_BOND_SCALE`]
+///
+/// Returns an error if a connected network extends through periodic
+/// boundaries (covalent or polymeric crystals).
+pub fn dftd3_detect_molecules(
+    numbers: &[usize],
+    positions: &[f64],
+    lattice: &[f64],
+    periodic: Option<&[bool]>,
+    bond_scale: f64,
+) -> Result<Vec<DFTD3CrystalMolecule>, DFTD3Error> {
+    check_structure_dims(numbers, positions, Some(lattice), periodic)?;
+    let natoms = numbers.len();
+    let periodic = periodic.map_or([true; 3], |p| [p[0], p[1], p[2]]);
+
+    // wrap atoms into the unit cell
+    let inv = inverse3(lattice)?;
+    let frac = |r: &[f64]| -> [f64; 3] {
+        std::array::from_fn(|j| (0..3).map(|i| r[i] * inv[3 * i + j]).sum::<f64>())
+    };
+    let cart = |f: &[f64; 3]| -> [f64; 3] {
+        std::array::from_fn(|j| (0..3).map(|i| f[i] * lattice[3 * i + j]).sum::<f64>())
+    };
+    let wrapped: Vec<[f64; 3]> = positions
+        .chunks(3)
+        .map(|r| {
+            let mut f = frac(r);
+            (0..3).filter(|&k| periodic[k]).for_each(|k| f[k] -= f[k].floor());
+            cart(&f)
+        })
+        .collect();
+
+    // cell translations reaching all images within the largest bond cutoff
+    let max_radius = numbers.iter().map(|&z| covalent_radius(z)).fold(0.0, f64::max);
+    let images = lattice_images(lattice, periodic, 2.0 * bond_scale * max_radius)?;
+    let translation = |n: &[i64; 3]| cart(&n.map(|x| x as f64));
+
+    // bonds (j, image) of each atom
+    let mut bonds = vec![vec![]; natoms];
+    for i in 0..natoms {
+        for j in 0..natoms {
+            let cutoff = bond_scale * (covalent_radius(numbers[i]) + covalent_radius(numbers[j]));
+            for n in &images {
+                if i == j && n == &[0, 0, 0] {
+                    continue;
+                }
+                let t = translation(n);
+                let dist2 =
+                    (0..3).map(|x| (wrapped[j][x] + t[x] - wrapped[i][x]).powi(2)).sum::<f64>();
+                if dist2 < cutoff * cutoff {
+                    bonds[i].push((j, *n));
+                }
+            }
+        }
+    }
+
+    // breadth-first search, tracking the cell shift of each atom
+    let mut shift: Vec<Option<[i64; 3]>> = vec![None; natoms];
+    let mut molecules = vec![];
+    for start in 0..natoms {
+        if shift[start].is_some() {
+            continue;
+        }
+        shift[start] = Some([0, 0, 0]);
+        let mut atoms = vec![start];
+        let mut queue = VecDeque::from([start]);
+        while let Some(i) = queue.pop_front() {
+            let si = shift[i].unwrap();
+            for &(j, n) in &bonds[i] {
+                let sj = [si[0] + n[0], si[1] + n[1], si[2] + n[2]];
+                match shift[j] {
+                    None => {
+                        shift[j] = Some(sj);
+                        atoms.push(j);
+                        queue.push_back(j);
+                    },
+                    Some(s) if s != sj => {
+                        return Err(DFTD3Error::Rust(format!(
+                            "Atom {j} is bonded to its own periodic image, not a molecular crystal"
+                        )));
+                    },
+                    Some(_) => (),
+                }
+            }
+        }
+        atoms.sort_unstable();
+        let mol_numbers = atoms.iter().map(|&i| numbers[i]).collect();
+        let mol_positions = atoms
+            .iter()
+            .flat_map(|&i| {
+                let (w, t) = (wrapped[i], translation(&shift[i].unwrap()));
+                (0..3).map(move |x| w[x] + t[x])
+            })
+            .collect();
+        molecules.push(DFTD3CrystalMolecule {
+            atoms,
+            numbers: mol_numbers,
+            positions: mol_positions,
+        });
+    }
+    Ok(molecules)
+}
+
+/* #endregion */
+
+/* #region Lattice energy output */
+
+/// Lattice energy result.
+#[derive(Debug, Clone)]
+pub struct DFTD3LatticeEnergyOutput {
+    /// Lattice energy per molecule, `E(crystal) / Z - sum_i E(molecule i) / Z`.
+    pub energy: f64,
+    /// Dispersion energy of the crystal unit cell.
+    pub energy_crystal: f64,
+    /// Dispersion energy of each isolated molecule.
+    pub molecule_energies: Vec<f64>,
+    /// Lattice energy of each molecule, from the atom-partitioned crystal
+    /// energy minus its isolated energy.
+    pub molecule_lattice_energies: Vec<f64>,
+    /// Gradient of the lattice energy w.r.t. unit-cell positions (natom * 3).
+    pub grad: Option<Vec<f64>>,
+    /// Strain derivatives of the lattice energy (3 * 3).
+    pub sigma: Option<Vec<f64>>,
+}
+
+/* #endregion */
+
+/* #region Lattice energy driver */
+
+/// Molecular crystal lattice energy driver.
+pub struct DFTD3LatticeEnergyDriver {
+    numbers: Vec<usize>,
+    positions: Vec<f64>,
+    lattice: Vec<f64>,
+    periodic: Option<Vec<bool>>,
+    molecules: Vec<DFTD3CrystalMolecule>,
+}
+
+impl DFTD3LatticeEnergyDriver {
+    /// Create new lattice energy driver from unit-cell arrays (in Bohr),
+    /// detecting molecules with default bond scale [`DFTD3_BOND_SCALE`].
+    ///
+    /// - `numbers` - element index (6 for O, 7 for N) in the unit cell
+    /// - `positions` - atomic positions in Bohr (natom * 3)
+    /// - `lattice` - lattice parameters (3 * 3)
+    /// - `periodic` - optional, periodicity (3)
+    pub fn new(
+        numbers: &[usize],
+        positions: &[f64],
+        lattice: &[f64],
+        periodic: Option<&[bool]>,
+    ) -> Self {
+        Self::new_f(numbers, positions, lattice, periodic, DFTD3_BOND_SCALE).unwrap()
+    }
+
+    /// Evaluate the lattice energy, and optionally its gradient and strain
+    /// derivatives.
+    pub fn compute(&self, param: &DFTD3Param, eval_grad: bool) -> DFTD3LatticeEnergyOutput {
+        self.compute_f(param, eval_grad).unwrap()
+    }
+
+    /// Molecules detected in the unit cell.
+    pub fn molecules(&self) -> &[DFTD3CrystalMolecule] {
+        &self.molecules
+    }
+
+    /// Create new lattice energy driver with custom bond scale (failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3LatticeEnergyDriver::new`], [`dftd3_detect_molecules`]
+    pub fn new_f(
+        numbers: &[usize],
+        positions: &[f64],
+        lattice: &[f64],
+        periodic: Option<&[bool]>,
+        bond_scale: f64,
+    ) -> Result<Self, DFTD3Error> {
+        let molecules = dftd3_detect_molecules(numbers, positions, lattice, periodic, bond_scale)?;
+        Ok(Self {
+            numbers: numbers.to_vec(),
+            positions: positions.to_vec(),
+            lattice: lattice.to_vec(),
+            periodic: periodic.map(|x| x.to_vec()),
+            molecules,
+        })
+    }
+
+    /// Evaluate the lattice energy (failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3LatticeEnergyDriver::compute`]
+    pub fn compute_f(
+        &self,
+        param: &DFTD3Param,
+        eval_grad: bool,
+    ) -> Result<DFTD3LatticeEnergyOutput, DFTD3Error> {
+        let natoms = self.numbers.len();
+        let nmol = self.molecules.len() as f64;
+
+        let crystal = DFTD3Model::new_f(
+            &self.numbers,
+            &self.positions,
+            Some(&self.lattice),
+            self.periodic.as_deref(),
+        )?;
+        let res = crystal.get_dispersion_f(param, eval_grad)?;
+        let energy_crystal = res.energy;
+        let mut grad = res.grad;
+        let mut sigma = res.sigma;
+
+        // atom-partitioned crystal energy
+        let pairwise = crystal.get_pairwise_dispersion_f(param)?;
+        let atom_energies: Vec<f64> = (0..natoms)
+            .map(|i| {
+                let row = i * natoms..(i + 1) * natoms;
+                pairwise.pair_energy2[row.clone()].iter().sum::<f64>()
+                    + pairwise.pair_energy3[row].iter().sum::<f64>()
+            })
+            .collect();
+
+        let mut molecule_energies = vec![];
+        let mut molecule_lattice_energies = vec![];
+        for mol in &self.molecules {
+            let model = DFTD3Model::new_f(&mol.numbers, &mol.positions, None, None)?;
+            let res = model.get_dispersion_f(param, eval_grad)?;
+            let energy_in_crystal = mol.atoms.iter().map(|&i| atom_energies[i]).sum::<f64>();
+            molecule_energies.push(res.energy);
+            molecule_lattice_energies.push(energy_in_crystal - res.energy);
+            if let (Some(grad), Some(g)) = (grad.as_mut(), res.grad) {
+                for (imol, &i) in mol.atoms.iter().enumerate() {
+                    (0..3).for_each(|x| grad[3 * i + x] -= g[3 * imol + x]);
+                }
+            }
+            if let (Some(sigma), Some(s)) = (sigma.as_mut(), res.sigma) {
+                sigma.iter_mut().zip(s).for_each(|(x, s)| *x -= s);
+            }
+        }
+
+        grad.iter_mut().flatten().for_each(|x| *x /= nmol);
+        sigma.iter_mut().flatten().for_each(|x| *x /= nmol);
+        let energy = (energy_crystal - molecule_energies.iter().sum::<f64>()) / nmol;
+
+        Ok(DFTD3LatticeEnergyOutput {
+            energy,
+            energy_crystal,
+            molecule_energies,
+            molecule_lattice_energies,
+            grad,
+            sigma,
+        })
+    }
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_molecules() {
+        // two H2 molecules in a cubic cell of 6 bohr, the second one split by
+        // the boundary along x
+        let numbers = vec![1, 1, 1, 1];
+        #[rustfmt::skip]
+        let positions = vec![
+            1.0, 1.0, 1.0,
+            2.4, 1.0, 1.0,
+            5.5, 4.0, 4.0,
+            0.9, 4.0, 4.0,
+        ];
+        let lattice = vec![6.0, 0.0, 0.0, 0.0, 6.0, 0.0, 0.0, 0.0, 6.0];
+        let molecules =
+            dftd3_detect_molecules(&numbers, &positions, &lattice, None, DFTD3_BOND_SCALE).unwrap();
+        assert_eq!(molecules.len(), 2);
+        assert_eq!(molecules[1].atoms, vec![2, 3]);
+        // unwrapped bond length
+        let p = &molecules[1].positions;
+        assert!(((p[3] - p[0]).abs() - 1.4).abs() < 1e-10);
+
+        // H2 bonded across the boundary along y, in a strongly skewed cell
+        // (b = 10 a + 6 y) where the bonded image is three cells away along a
+        #[rustfmt::skip]
+        let positions = vec![
+            1.0, 5.5, 1.0,
+            1.0, 0.9, 1.0,
+            3.0, 3.0, 3.0,
+            3.0, 3.0, 4.4,
+        ];
+        let lattice = vec![6.0, 0.0, 0.0, 60.0, 6.0, 0.0, 0.0, 0.0, 6.0];
+        let molecules =
+            dftd3_detect_molecules(&numbers, &positions, &lattice, None, DFTD3_BOND_SCALE).unwrap();
+        assert_eq!(molecules.len(), 2);
+        for mol in &molecules {
+            let p = &mol.positions;
+            let d2 = (0..3).map(|x| (p[3 + x] - p[x]).powi(2)).sum::<f64>();
+            assert!((d2.sqrt() - 1.4).abs() < 1e-10);
+        }
+
+        // infinite chain of H atoms is not molecular
+        let positions = vec![0.0, 0.0, 0.0, 1.4, 0.0, 0.0];
+        let lattice = vec![2.8, 0.0, 0.0, 0.0, 6.0, 0.0, 0.0, 0.0, 6.0];
+        assert!(
+            dftd3_detect_molecules(&[1, 1], &positions, &lattice, None, DFTD3_BOND_SCALE).is_err()
+        );
+    }
+}
@@ -0,0 +1,311 @@
+//! Structured diff between two parameter databases.
+//!
+//! Databases are compared entry by entry after merging variant defaults
+//! ([`ParameterDatabase::merged_table`]), so that semantically identical
+//! entries compare equal, e.g. `s6=1.0` written out explicitly versus taken
+//! from `[default.parameter]`. Methods are matched by normalized name (`b97-1`
+//! and `b97_1` are the same method). The diff lists
+//!
+//! - methods added or removed;
+//! - variants added or removed for methods present in both databases;
+//! - changed numeric values, with relative change;
+//! - changed DOIs.
+//!
+//! # Example
+//!
+//! Review in-house additions layered over the embedded database:
+//!
+//! ```
+//! use dftd3::prelude::*;
+//!
+//! let old = ParameterDatabase::embedded();
+//! let mut new = old.clone();
+//! new.merge_toml_str("[parameter.b3lyp]\nd3.bj = {a1=0.4, s8=1.9889, a2=4.4211}", "in-house.toml");
+//! let diff = old.diff(&new);
+//! assert_eq!(diff.changed_values.len(), 1);
+//! println!("{diff}");
+//! ```
+
+use crate::interface::DFTD3Error;
+use crate::parameters::*;
+use std::path::Path;
+use toml::Table;
+
+/* #region Diff types */
+
+/// Changed numeric value of an entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterValueChange {
+    /// Method key (of the new database).
+    pub method: String,
+    /// Normalized variant.
+    pub version: String,
+    /// Parameter field.
+    pub field: String,
+    /// Value of the old database, `None` if absent.
+    pub old: Option<f64>,
+    /// Value of the new database, `None` if absent.
+    pub new: Option<f64>,
+}
+
+impl ParameterValueChange {
+    /// Relative change `(new - old) / |old|`, if both values are present and
+    /// `old` is non-zero.
+    pub fn relative_change(&self) -> Option<f64> {
+        match (self.old, self.new) {
+            (Some(old), Some(new)) if old != 0.0 => Some((new - old) / old.abs()),
+            _ => None,
+        }
+    }
+}
+
+/// Changed DOI of an entry.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterDoiChange {
+    /// Method key (of the new database).
+    pub method: String,
+    /// Normalized variant.
+    pub version: String,
+    /// DOI of the old database, `None` if absent.
+    pub old: Option<String>,
+    /// DOI of the new database, `None` if absent.
+    pub new: Option<String>,
+}
+
+/// Structured diff between an old and a new parameter database.
+///
+/// All lists are sorted by method key and variant (conventional order).
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParameterDatabaseDiff {
+    /// Method keys only in the new database.
+    pub added_methods: Vec<String>,
+    /// Method keys only in the old database.
+    pub removed_methods: Vec<String>,
+    /// Variants (method, variant) only in the new database, of methods in both.
+    pub added_variants: Vec<(String, String)>,
+    /// Variants (method, variant) only in the old database, of methods in both.
+    pub removed_variants: Vec<(String, String)>,
+    /// Changed numeric values of entries in both databases.
+    pub changed_values: Vec<ParameterValueChange>,
+    /// Changed DOIs of entries in both databases.
+    pub changed_dois: Vec<ParameterDoiChange>,
+}
+
+impl ParameterDatabaseDiff {
+    /// Whether the databases are semantically identical.
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+impl std::fmt::Display for ParameterDatabaseDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Parameter database diff: {} methods added, {} removed; {} variants added, {} \
+             removed; {} values changed; {} DOIs changed",
+            self.added_methods.len(),
+            self.removed_methods.len(),
+            self.added_variants.len(),
+            self.removed_variants.len(),
+            self.changed_values.len(),
+            self.changed_dois.len(),
+        )?;
+        for method in &self.added_methods {
+            writeln!(f, "+ parameter.{method}")?;
+        }
+        for method in &self.removed_methods {
+            writeln!(f, "- parameter.{method}")?;
+        }
+        for (method, version) in &self.added_variants {
+            writeln!(f, "+ parameter.{method}.d3.{version}")?;
+        }
+        for (method, version) in &self.removed_variants {
+            writeln!(f, "- parameter.{method}.d3.{version}")?;
+        }
+        let value = |v: Option<f64>| v.map_or("(none)".to_string(), |v| format!("{v:?}"));
+        for change in &self.changed_values {
+            let ParameterValueChange { method, version, field, old, new } = change;
+            write!(
+                f,
+                "~ parameter.{method}.d3.{version}.{field}: {} -> {}",
+                value(*old),
+                value(*new)
+            )?;
+            match change.relative_change() {
+                Some(rel) => writeln!(f, " ({:+.2}%)", rel * 100.0)?,
+                None => writeln!(f)?,
+            }
+        }
+        let doi = |d: &Option<String>| d.clone().unwrap_or_else(|| "(none)".to_string());
+        for ParameterDoiChange { method, version, old, new } in &self.changed_dois {
+            writeln!(f, "~ parameter.{method}.d3.{version}.doi: {} -> {}", doi(old), doi(new))?;
+        }
+        Ok(())
+    }
+}
+
+/* #endregion */
+
+/* #region Diff driver */
+
+impl ParameterDatabase {
+    /// Diff of this (old) database to a new database.
+    pub fn diff(&self, new: &ParameterDatabase) -> ParameterDatabaseDiff {
+        let mut diff = ParameterDatabaseDiff::default();
+        for method in self.methods() {
+            if match_method(new, &method).is_none() {
+                diff.removed_methods.push(method);
+            }
+        }
+        for method in new.methods() {
+            let Some(old_method) = match_method(self, &method) else {
+                diff.added_methods.push(method);
+                continue;
+            };
+            for &version in DATABASE_VARIANTS {
+                let old = self.merged_table(old_method, version).ok();
+                let current = new.merged_table(&method, version).ok();
+                let entry = (method.clone(), version.to_string());
+                match (old, current) {
+                    (None, None) => (),
+                    (None, Some(_)) => diff.added_variants.push(entry),
+                    (Some(_), None) => diff.removed_variants.push(entry),
+                    (Some(old), Some(current)) => diff_entry(&mut diff, entry, &old, &current),
+                }
+            }
+        }
+        diff
+    }
+}
+
+/// Method key of a database matching a method key of the other database by
+/// normalized name (not through aliases).
+fn match_method<'a>(db: &'a ParameterDatabase, method: &str) -> Option<&'a str> {
+    db.resolve_method(method).filter(|_| db.resolve_alias(method).is_none())
+}
+
+/// Compare merged tables of an entry present in both databases.
+fn diff_entry(
+    diff: &mut ParameterDatabaseDiff,
+    (method, version): (String, String),
+    old: &Table,
+    new: &Table,
+) {
+    let number = |table: &Table, field: &str| {
+        let value = table.get(field)?;
+        value.as_float().or_else(|| value.as_integer().map(|i| i as f64))
+    };
+    let mut fields: Vec<&String> = old.keys().chain(new.keys()).collect();
+    fields.sort();
+    fields.dedup();
+    for field in fields {
+        let (old_value, new_value) = (number(old, field), number(new, field));
+        if old_value != new_value {
+            diff.changed_values.push(ParameterValueChange {
+                method: method.clone(),
+                version: version.clone(),
+                field: field.clone(),
+                old: old_value,
+                new: new_value,
+            });
+        }
+    }
+    let doi = |table: &Table| table.get("doi").and_then(|v| v.as_str()).map(str::to_string);
+    if doi(old) != doi(new) {
+        diff.changed_dois.push(ParameterDoiChange {
+            method,
+            version,
+            old: doi(old),
+            new: doi(new),
+        });
+    }
+}
+
+/// Diff between two parameter files in the format of `parameters.toml`
+/// (panics on error).
+pub fn dftd3_diff_param_files<P: AsRef<Path>>(old: P, new: P) -> ParameterDatabaseDiff {
+    dftd3_diff_param_files_f(old, new).unwrap()
+}
+
+/// Diff between two parameter files (failable).
+///
+/// Both files must be complete databases (with `[default]` section); to
+/// review a partial user file, merge it over the old database with
+/// [`ParameterDatabase::merge_files`] and use [`ParameterDatabase::diff`].
+///
+/// # See also
+///
+/// [`dftd3_diff_param_files`]
+pub fn dftd3_diff_param_files_f<P: AsRef<Path>>(
+    old: P,
+    new: P,
+) -> Result<ParameterDatabaseDiff, DFTD3Error> {
+    let read = |path: &Path| {
+        let toml_str = std::fs::read_to_string(path).map_err(|e| {
+            DFTD3Error::ParametersError(format!(
+                "Failed to read parameter file {}: {e}",
+                path.display()
+            ))
+        })?;
+        ParameterDatabase::from_toml_str(&toml_str)
+    };
+    Ok(read(old.as_ref())?.diff(&read(new.as_ref())?))
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_database_diff() {
+        let base = r#"
+            [default]
+            d3 = ["bj", "zero"]
+            [default.parameter]
+            d3.bj = {s6=1.0, s9=1.0, alp=14.0, damping="rational"}
+            d3.zero = {s6=1.0, s9=1.0, rs8=1.0, alp=14.0, damping="zero"}
+            d3.bjm = {s6=1.0, s9=1.0, alp=14.0, damping="rational"}
+            d3.zerom = {s6=1.0, s9=1.0, rs8=1.0, alp=14.0, damping="mzero"}
+        "#;
+        let old = ParameterDatabase::from_toml_str(&format!(
+            r#"{base}
+            [parameter.b97_1]
+            d3.bj = {{a1=0.4, s8=2.0, a2=4.0, doi="10.0/a"}}
+            d3.zero = {{rs6=1.2, s8=1.0}}
+            [parameter.gone]
+            d3.bj = {{a1=0.4, s8=2.0, a2=4.0}}
+            "#
+        ))
+        .unwrap();
+        assert!(old.diff(&old).is_empty());
+
+        // explicit defaults and separators in method names compare equal
+        let new = ParameterDatabase::from_toml_str(&format!(
+            r#"{base}
+            [parameter.b97-1]
+            d3.bj = {{s6=1, a1=0.5, s8=2.0, a2=4.0, doi="10.0/b"}}
+            d3.bjm = {{a1=0.4, s8=2.0, a2=4.0}}
+            [parameter.new]
+            d3.bj = {{a1=0.4, s8=2.0, a2=4.0}}
+            "#
+        ))
+        .unwrap();
+        let diff = old.diff(&new);
+        assert_eq!(diff.added_methods, ["new"]);
+        assert_eq!(diff.removed_methods, ["gone"]);
+        assert_eq!(diff.added_variants, [("b97-1".to_string(), "bjm".to_string())]);
+        assert_eq!(diff.removed_variants, [("b97-1".to_string(), "zero".to_string())]);
+        assert_eq!(diff.changed_values.len(), 1);
+        assert_eq!(diff.changed_values[0].field, "a1");
+        assert!((diff.changed_values[0].relative_change().unwrap() - 0.25).abs() < 1e-12);
+        assert_eq!(diff.changed_dois[0].new.as_deref(), Some("10.0/b"));
+
+        let text = diff.to_string();
+        assert!(text.contains("\n~ parameter.b97-1.d3.bj.a1: 0.4 -> 0.5 (+25.00%)\n"));
+        assert!(text.contains("\n- parameter.b97-1.d3.zero\n"));
+        assert!(text.ends_with("~ parameter.b97-1.d3.bj.doi: 10.0/a -> 10.0/b\n"));
+    }
+}
@@ -0,0 +1,66 @@
+//! Element data used by the high-level drivers.
+
+/// Bohr radius in Angstrom.
+pub(crate) const BOHR_TO_ANGSTROM: f64 = 0.52917721067;
+
+/// Element symbols, indexed by atomic number (index 0 is a dummy).
+#[rustfmt::skip]
+pub(crate) const SYMBOLS: [&str; 119] = [
+    "X",
+    "H", "He",
+    "Li", "Be", "B", "C", "N", "O", "F", "Ne",
+    "Na", "Mg", "Al", "Si", "P", "S", "Cl", "Ar",
+    "K", "Ca",
+    "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn",
+    "Ga", "Ge", "As", "Se", "Br", "Kr",
+    "Rb", "Sr",
+    "Y", "Zr", "Nb", "Mo", "Tc", "Ru", "Rh", "Pd", "Ag", "Cd",
+    "In", "Sn", "Sb", "Te", "I", "Xe",
+    "Cs", "Ba",
+    "La", "Ce", "Pr", "Nd", "Pm", "Sm", "Eu", "Gd", "Tb", "Dy", "Ho", "Er", "Tm", "Yb",
+    "Lu", "Hf", "Ta", "W", "Re", "Os", "Ir", "Pt", "Au", "Hg",
+    "Tl", "Pb", "Bi", "Po", "At", "Rn",
+    "Fr", "Ra",
+    "Ac", "Th", "Pa", "U", "Np", "Pu", "Am", "Cm", "Bk", "Cf", "Es", "Fm", "Md", "No",
+    "Lr", "Rf", "Db", "Sg", "Bh", "Hs", "Mt", "Ds", "Rg", "Cn",
+    "Nh", "Fl", "Mc", "Lv", "Ts", "Og",
+];
+
+/// Element symbol of atomic number (`X` if out of range).
+pub(crate) fn symbol(number: usize) -> &'static str {
+    SYMBOLS.get(number).copied().unwrap_or("X")
+}
+
+/// Single-bond covalent radii in Angstrom, indexed by atomic number (index 0
+/// is a dummy).
+///
+/// Pyykkö, P.; Atsumi, M. Molecular single-bond covalent radii for elements
+/// 1–118. *Chem. Eur. J.*, **2009**, *15*, 186–197. doi: [10.1002/chem.200800987](https://dx.doi.org/10.1002/chem.200800987).
+#[cfg(feature = "api-v0_5")]
+#[rustfmt::skip]
+pub(crate) const COVALENT_RADII: [f64; 119] = [
+    0.00,
+    0.32, 0.46,
+    1.33, 1.02, 0.85, 0.75, 0.71, 0.63, 0.64, 0.67,
+    1.55, 1.39, 1.26, 1.16, 1.11, 1.03, 0.99, 0.96,
+    1.96, 1.71,
+    1.48, 1.36, 1.34, 1.22, 1.19, 1.16, 1.11, 1.10, 1.12, 1.18,
+    1.24, 1.21, 1.21, 1.16, 1.14, 1.17,
+    2.10, 1.85,
+    1.63, 1.54, 1.47, 1.38, 1.28, 1.25, 1.25, 1.20, 1.28, 1.36,
+    1.42, 1.40, 1.40, 1.36, 1.33, 1.31,
+    2.32, 1.96,
+    1.80, 1.63, 1.76, 1.74, 1.73, 1.72, 1.68, 1.69, 1.68, 1.67, 1.66, 1.65, 1.64, 1.70,
+    1.62, 1.52, 1.46, 1.37, 1.31, 1.29, 1.22, 1.23, 1.24, 1.33,
+    1.44, 1.44, 1.51, 1.45, 1.47, 1.42,
+    2.23, 2.01,
+    1.86, 1.75, 1.69, 1.70, 1.71, 1.72, 1.66, 1.66, 1.68, 1.68, 1.65, 1.67, 1.73, 1.76,
+    1.61, 1.57, 1.49, 1.43, 1.41, 1.34, 1.29, 1.28, 1.21, 1.22,
+    1.36, 1.43, 1.62, 1.75, 1.65, 1.57,
+];
+
+/// Covalent radius of atomic number in Bohr.
+#[cfg(feature = "api-v0_5")]
+pub(crate) fn covalent_radius(number: usize) -> f64 {
+    COVALENT_RADII.get(number).copied().unwrap_or(0.0) / BOHR_TO_ANGSTROM
+}
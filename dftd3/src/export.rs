@@ -0,0 +1,326 @@
+//! Export of the parameter database as tables (CSV, Markdown, JSON).
+//!
+//! Two tables can be generated from a [`ParameterDatabase`]:
+//!
+//! - [`AvailabilityTable`]: method × variant availability matrix;
+//! - [`ParameterTable`]: damping parameters of every method and variant, with
+//!   variant defaults merged (fields [`PARAMETER_TABLE_FIELDS`] and DOI).
+//!
+//! Rows are sorted by method key, and variants are in the conventional order
+//! (`bj`, `zero`, `bjm`, `zerom`, `op`, `cso`), so that the output is
+//! deterministic. JSON output requires the `json` feature.
+//!
+//! # Example
+//!
+//! ```
+//! use dftd3::prelude::*;
+//!
+//! let table = dftd3_parameter_table(Some("bj"));
+//! let csv = table.to_csv();
+//! assert!(csv.starts_with("method,name,family,version,s6,s8,s9,a1,a2,a3,a4,rs6,rs8,alp,bet,doi\n"));
+//! println!("{}", dftd3_availability_table().to_markdown());
+//! ```
+
+use crate::parameters::*;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// Parameter fields (columns) of [`ParameterTable`], in output order.
+pub const PARAMETER_TABLE_FIELDS: &[&str] =
+    &["s6", "s8", "s9", "a1", "a2", "a3", "a4", "rs6", "rs8", "alp", "bet"];
+
+/* #region Table types */
+
+/// Method × variant availability matrix.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AvailabilityTable {
+    /// Variants (columns), in the conventional order.
+    pub variants: Vec<String>,
+    /// Rows, sorted by method key.
+    pub rows: Vec<AvailabilityRow>,
+}
+
+/// Row of [`AvailabilityTable`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AvailabilityRow {
+    /// Method key.
+    pub method: String,
+    /// Display name (method key if absent).
+    pub name: String,
+    /// Functional family, if known.
+    pub family: Option<FunctionalFamily>,
+    /// Variants available for the method, in the conventional order.
+    pub available: Vec<String>,
+}
+
+/// Damping parameters of methods and variants.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParameterTable {
+    /// Rows, sorted by method key and variant.
+    pub rows: Vec<ParameterTableRow>,
+}
+
+/// Row of [`ParameterTable`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParameterTableRow {
+    /// Method key.
+    pub method: String,
+    /// Display name (method key if absent).
+    pub name: String,
+    /// Functional family, if known.
+    pub family: Option<FunctionalFamily>,
+    /// Normalized variant.
+    pub version: String,
+    /// Parameter values (variant defaults merged) by field name.
+    pub params: BTreeMap<String, f64>,
+    /// Reference DOI, if present.
+    pub doi: Option<String>,
+}
+
+/* #endregion */
+
+/* #region Table generation */
+
+impl ParameterDatabase {
+    /// Method × variant availability matrix of all methods.
+    pub fn availability_table(&self) -> AvailabilityTable {
+        let rows = self
+            .methods()
+            .into_iter()
+            .map(|method| {
+                let variants = self.variants(&method);
+                let available = DATABASE_VARIANTS
+                    .iter()
+                    .filter(|v| variants.iter().any(|a| a == *v))
+                    .map(|v| v.to_string())
+                    .collect();
+                AvailabilityRow {
+                    name: self.display_name(&method).unwrap_or(&method).to_string(),
+                    family: self.metadata(&method).and_then(|m| m.family),
+                    available,
+                    method,
+                }
+            })
+            .collect();
+        AvailabilityTable {
+            variants: DATABASE_VARIANTS.iter().map(|v| v.to_string()).collect(),
+            rows,
+        }
+    }
+
+    /// Damping parameters of all methods, of a variant or all variants
+    /// (`version = None`).
+    pub fn parameter_table(&self, version: Option<&str>) -> ParameterTable {
+        let version = version.map(normalize_version);
+        let mut rows = vec![];
+        for method in self.methods() {
+            for &variant in DATABASE_VARIANTS {
+                if version.as_deref().is_some_and(|v| v != variant) {
+                    continue;
+                }
+                let Ok(table) = self.merged_table(&method, variant) else { continue };
+                let params = PARAMETER_TABLE_FIELDS
+                    .iter()
+                    .filter_map(|&field| {
+                        let value = table.get(field)?;
+                        let value =
+                            value.as_float().or_else(|| value.as_integer().map(|i| i as f64));
+                        Some((field.to_string(), value?))
+                    })
+                    .collect();
+                rows.push(ParameterTableRow {
+                    name: self.display_name(&method).unwrap_or(&method).to_string(),
+                    family: self.metadata(&method).and_then(|m| m.family),
+                    version: variant.to_string(),
+                    params,
+                    doi: self.doi(&method, variant),
+                    method: method.clone(),
+                });
+            }
+        }
+        ParameterTable { rows }
+    }
+}
+
+/// Method × variant availability matrix of the global database.
+pub fn dftd3_availability_table() -> AvailabilityTable {
+    ParameterDatabase::global().availability_table()
+}
+
+/// Damping parameters of all methods of the global database, of a variant or
+/// all variants (`version = None`).
+pub fn dftd3_parameter_table(version: Option<&str>) -> ParameterTable {
+    ParameterDatabase::global().parameter_table(version)
+}
+
+/* #endregion */
+
+/* #region Output formats */
+
+impl AvailabilityTable {
+    /// Format as CSV; availability is `1` or `0`.
+    pub fn to_csv(&self) -> String {
+        let header = ["method", "name", "family"]
+            .into_iter()
+            .chain(self.variants.iter().map(|v| v.as_str()));
+        let mut csv = csv_line(header.map(str::to_string));
+        for row in &self.rows {
+            let flags = self.variants.iter().map(|v| (row.available.contains(v) as u8).to_string());
+            let cells = [row.method.clone(), row.name.clone(), family_cell(row.family)];
+            csv += &csv_line(cells.into_iter().chain(flags));
+        }
+        csv
+    }
+
+    /// Format as Markdown table; available variants are marked with `✓`.
+    pub fn to_markdown(&self) -> String {
+        let header = ["Method", "Name", "Family"]
+            .into_iter()
+            .chain(self.variants.iter().map(|v| v.as_str()));
+        let mut markdown = markdown_header(header.map(str::to_string).collect());
+        for row in &self.rows {
+            let flags = self
+                .variants
+                .iter()
+                .map(|v| if row.available.contains(v) { "✓" } else { "" }.to_string());
+            let cells = [format!("`{}`", row.method), row.name.clone(), family_cell(row.family)];
+            markdown += &markdown_line(cells.into_iter().chain(flags));
+        }
+        markdown
+    }
+
+    /// Format as pretty-printed JSON (requires the `json` feature).
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("availability table should be serializable")
+    }
+}
+
+impl ParameterTable {
+    /// Format as CSV; fields absent for a variant are empty.
+    pub fn to_csv(&self) -> String {
+        let header = ["method", "name", "family", "version"]
+            .into_iter()
+            .chain(PARAMETER_TABLE_FIELDS.iter().copied())
+            .chain(["doi"]);
+        let mut csv = csv_line(header.map(str::to_string));
+        for row in &self.rows {
+            csv += &csv_line(self.cells(row, row.method.clone()));
+        }
+        csv
+    }
+
+    /// Format as Markdown table; fields absent for a variant are empty.
+    pub fn to_markdown(&self) -> String {
+        let header = ["Method", "Name", "Family", "Variant"]
+            .into_iter()
+            .chain(PARAMETER_TABLE_FIELDS.iter().copied())
+            .chain(["DOI"]);
+        let mut markdown = markdown_header(header.map(str::to_string).collect());
+        for row in &self.rows {
+            markdown += &markdown_line(self.cells(row, format!("`{}`", row.method)));
+        }
+        markdown
+    }
+
+    /// Format as pretty-printed JSON (requires the `json` feature).
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).expect("parameter table should be serializable")
+    }
+
+    /// Cells of a row in column order, with formatted method cell.
+    fn cells(&self, row: &ParameterTableRow, method: String) -> impl Iterator<Item = String> {
+        let params: Vec<String> = PARAMETER_TABLE_FIELDS
+            .iter()
+            .map(|&field| row.params.get(field).map(|v| format!("{v:?}")).unwrap_or_default())
+            .collect();
+        [method, row.name.clone(), family_cell(row.family), row.version.clone()]
+            .into_iter()
+            .chain(params)
+            .chain([row.doi.clone().unwrap_or_default()])
+    }
+}
+
+fn family_cell(family: Option<FunctionalFamily>) -> String {
+    family.map(|f| f.to_string()).unwrap_or_default()
+}
+
+/// CSV line of cells, quoted if they contain `,`, `"` or line breaks.
+fn csv_line(cells: impl Iterator<Item = String>) -> String {
+    let cells: Vec<String> = cells
+        .map(|cell| {
+            if cell.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", cell.replace('"', "\"\""))
+            } else {
+                cell
+            }
+        })
+        .collect();
+    cells.join(",") + "\n"
+}
+
+/// Markdown header line and separator line.
+fn markdown_header(cells: Vec<String>) -> String {
+    let separator = vec!["---".to_string(); cells.len()];
+    markdown_line(cells.into_iter()) + &markdown_line(separator.into_iter())
+}
+
+/// Markdown table line, with `|` in cells escaped.
+fn markdown_line(cells: impl Iterator<Item = String>) -> String {
+    let cells: Vec<String> = cells.map(|cell| cell.replace('|', "\\|")).collect();
+    format!("| {} |\n", cells.join(" | "))
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_export_tables() {
+        let db = ParameterDatabase::from_toml_str(
+            r#"
+            [default]
+            d3 = ["bj", "zero"]
+            [default.parameter]
+            d3.bj = {s6=1.0, s9=1.0, alp=14.0, damping="rational"}
+            d3.zero = {s6=1.0, s9=1.0, rs8=1.0, alp=14.0, damping="zero"}
+            d3.bjm = {s6=1.0, s9=1.0, alp=14.0, damping="rational"}
+            d3.zerom = {s6=1.0, s9=1.0, rs8=1.0, alp=14.0, damping="mzero"}
+            [parameter.pbe]
+            meta = {name="PBE", family="gga"}
+            d3.zero = {rs6=1.217, s8=0.722, doi="10.1063/1.3382344"}
+            d3.bj = {a1=0.4289, s8=0.7875, a2=4.4407, doi="10.1002/jcc.21759"}
+            [parameter.my-func]
+            meta = {name="My, Func"}
+            d3.bjm = {a1=0.5, s8=1, a2=5}
+            "#,
+        )
+        .unwrap();
+
+        let availability = db.availability_table();
+        assert_eq!(availability.rows[0].method, "my-func");
+        assert_eq!(availability.rows[1].available, ["bj", "zero"]);
+        let csv = availability.to_csv();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("method,name,family,bj,zero,bjm,zerom,op,cso"));
+        assert_eq!(lines.next(), Some("my-func,\"My, Func\",,0,0,1,0,0,0"));
+        assert_eq!(lines.next(), Some("pbe,PBE,GGA,1,1,0,0,0,0"));
+        let markdown = availability.to_markdown();
+        assert!(markdown.contains("| --- | --- | --- | --- |"));
+        assert!(markdown.ends_with("| `pbe` | PBE | GGA | ✓ | ✓ |  |  |  |  |\n"));
+
+        let table = db.parameter_table(None);
+        let keys: Vec<(&str, &str)> =
+            table.rows.iter().map(|r| (r.method.as_str(), r.version.as_str())).collect();
+        assert_eq!(keys, [("my-func", "bjm"), ("pbe", "bj"), ("pbe", "zero")]);
+        assert_eq!(table.rows[0].params["s8"], 1.0);
+        let csv = db.parameter_table(Some("d3zero")).to_csv();
+        assert_eq!(
+            csv.lines().nth(1),
+            Some("pbe,PBE,GGA,zero,1.0,0.722,1.0,,,,,1.217,1.0,14.0,,10.1063/1.3382344")
+        );
+    }
+}
@@ -0,0 +1,485 @@
+//! Damping parameter fitting by nonlinear least squares.
+//!
+//! Given a reference set of reactions (or single structures) with target
+//! dispersion energies, a chosen subset of damping parameters is optimized
+//! with the Levenberg-Marquardt algorithm, minimizing
+//!
+//! ```text
+//! chi2 = sum_i w_i (dE_i(p) - t_i)^2
+//! ```
+//!
+//! where `t_i` is the target, usually the dispersion-free residual
+//! `E_ref - E_DFT` of the i-th entry. Each entry is a [`DFTD3Reaction`], so
+//! interaction energies are expressed as `complex - monomers`; a single
+//! structure is a reaction with one species of coefficient 1.
+//!
+//! Standard errors are estimated from the covariance `s^2 (J^T W J)^-1` at
+//! the optimum, with `s^2 = chi2 / (n - k)`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use dftd3::prelude::*;
+//!
+//! # let (numbers, positions) = (vec![1_usize], vec![0.0; 3]);
+//! # let (target, weight) = (-0.001, 1.0);
+//! let mut set = vec![];
+//! let reaction = DFTD3Reaction::new()
+//!     .species("dimer", DFTD3Model::new(&numbers, &positions, None, None), 1.0);
+//! set.push(DFTD3FitData::new("dimer", reaction, target, weight));
+//!
+//! // start from the B3LYP-D3(BJ) values, fit s8, a1 and a2
+//! let initial = dftd3_get_damping_param("b3lyp", "bj").param;
+//! let options =
+//!     DFTD3FitOptionsBuilder::default().fields(vec!["s8".into(), "a1".into(), "a2".into()]).build().unwrap();
+//! let res = dftd3_fit_damping_param(&set, &initial, &options);
+//! for ((field, value), err) in res.fields.iter().zip(&res.values).zip(&res.std_errors) {
+//!     println!("{field} = {value:.6} +/- {err:.6}");
+//! }
+//! println!("RMSD: {:.3e}", res.statistics.rmsd);
+//! println!("{}", res.to_toml_entry("myfunctional"));
+//! ```
+
+use crate::interface::*;
+use crate::parameters::*;
+use crate::reaction::*;
+use crate::sensitivity::*;
+use derive_builder::Builder;
+
+/* #region Fit data and options */
+
+/// One entry of a reference set.
+pub struct DFTD3FitData {
+    /// Name of entry.
+    pub name: String,
+    /// Reaction (or single structure) whose dispersion energy is fitted.
+    pub reaction: DFTD3Reaction,
+    /// Target dispersion energy (e.g. `E_ref - E_DFT`) in Hartree.
+    pub target: f64,
+    /// Weight of entry.
+    pub weight: f64,
+}
+
+impl DFTD3FitData {
+    /// Create new entry of a reference set.
+    pub fn new(name: &str, reaction: DFTD3Reaction, target: f64, weight: f64) -> Self {
+        Self { name: name.to_string(), reaction, target, weight }
+    }
+}
+
+/// Options for damping parameter fitting.
+#[derive(Builder, Debug, Clone)]
+#[builder(pattern = "owned", build_fn(error = "DFTD3Error"))]
+pub struct DFTD3FitOptions {
+    #[doc = r"parameter fields to be optimized (e.g. `s8`, `a1`, `a2`)"]
+    pub fields: Vec<String>,
+    #[builder(default = 100)]
+    #[doc = r"optional, default 100; maximum number of Levenberg-Marquardt iterations"]
+    pub max_iter: usize,
+    #[builder(default = 1.0e-10)]
+    #[doc = r"optional, default 1.0e-10; convergence threshold of relative change of chi2"]
+    pub tol: f64,
+    #[builder(default = 1.0e-4)]
+    #[doc = r"optional, default 1.0e-4; relative step of finite-difference Jacobian"]
+    pub step: f64,
+}
+
+/* #endregion */
+
+/* #region Fit result */
+
+/// Residual statistics of a reference set (residual = model - target).
+#[derive(Debug, Clone, Default)]
+pub struct DFTD3ResidualStatistics {
+    /// Number of entries.
+    pub count: usize,
+    /// Mean deviation.
+    pub md: f64,
+    /// Mean absolute deviation.
+    pub mad: f64,
+    /// Root mean square deviation.
+    pub rmsd: f64,
+    /// Maximum absolute deviation.
+    pub max_abs: f64,
+}
+
+impl DFTD3ResidualStatistics {
+    /// Compute statistics of residuals.
+    pub fn new(residuals: &[f64]) -> Self {
+        let count = residuals.len();
+        if count == 0 {
+            return Self::default();
+        }
+        let n = count as f64;
+        Self {
+            count,
+            md: residuals.iter().sum::<f64>() / n,
+            mad: residuals.iter().map(|r| r.abs()).sum::<f64>() / n,
+            rmsd: (residuals.iter().map(|r| r * r).sum::<f64>() / n).sqrt(),
+            max_abs: residuals.iter().map(|r| r.abs()).fold(0.0, f64::max),
+        }
+    }
+}
+
+/// Damping parameter fitting result.
+#[derive(Debug, Clone)]
+pub struct DFTD3FitResult {
+    /// Fitted damping parameters (all fields).
+    pub param: DFTD3DampingParamEnum,
+    /// Names of fitted fields.
+    pub fields: Vec<String>,
+    /// Fitted values, in the order of `fields`.
+    pub values: Vec<f64>,
+    /// Standard errors of fitted values, in the order of `fields`.
+    pub std_errors: Vec<f64>,
+    /// Model dispersion energies of entries.
+    pub energies: Vec<f64>,
+    /// Residuals (model - target) of entries.
+    pub residuals: Vec<f64>,
+    /// Residual statistics (unweighted).
+    pub statistics: DFTD3ResidualStatistics,
+    /// Weighted sum of squared residuals.
+    pub chi2: f64,
+    /// Number of iterations.
+    pub niter: usize,
+    /// Whether the fit converged.
+    pub converged: bool,
+}
+
+impl DFTD3FitResult {
+    /// Format fitted parameters as an entry of the parameter database.
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3DampingParam::to_toml_entry`]
+    pub fn to_toml_entry(&self, method: &str) -> String {
+        DFTD3DampingParam { param: self.param.clone(), doi: None }.to_toml_entry(method)
+    }
+}
+
+/* #endregion */
+
+/* #region Fitting driver */
+
+/// Fit damping parameters to a reference set.
+///
+/// - `set` - reference set
+/// - `initial` - initial damping parameters; also fixes the variant and the
+///   values of fields not optimized
+/// - `options` - fitted fields and convergence criteria
+pub fn dftd3_fit_damping_param(
+    set: &[DFTD3FitData],
+    initial: &DFTD3DampingParamEnum,
+    options: &DFTD3FitOptions,
+) -> DFTD3FitResult {
+    dftd3_fit_damping_param_f(set, initial, options).unwrap()
+}
+
+/// Fit damping parameters to a reference set (failable).
+///
+/// # See also
+///
+/// [`dftd3_fit_damping_param`]
+pub fn dftd3_fit_damping_param_f(
+    set: &[DFTD3FitData],
+    initial: &DFTD3DampingParamEnum,
+    options: &DFTD3FitOptions,
+) -> Result<DFTD3FitResult, DFTD3Error> {
+    let fields = &options.fields;
+    let nparam = fields.len();
+    let ndata = set.len();
+    if nparam == 0 {
+        return Err(DFTD3Error::Rust("No parameter fields to be fitted".into()));
+    }
+    if ndata < nparam {
+        return Err(DFTD3Error::Rust(format!(
+            "Reference set ({ndata} entries) is smaller than number of fitted fields ({nparam})"
+        )));
+    }
+    let values = fields
+        .iter()
+        .map(|f| {
+            initial.get_field(f).ok_or_else(|| {
+                DFTD3Error::ParametersError(format!(
+                    "Unknown parameter '{f}' for variant '{}'",
+                    initial.version()
+                ))
+            })
+        })
+        .collect::<Result<Vec<f64>, DFTD3Error>>()?;
+
+    let weights: Vec<f64> = set.iter().map(|d| d.weight.sqrt()).collect();
+    let with_values = |values: &[f64]| -> Result<DFTD3DampingParamEnum, DFTD3Error> {
+        let mut param = initial.clone();
+        for (f, &v) in fields.iter().zip(values) {
+            param.set_field(f, v)?;
+        }
+        Ok(param)
+    };
+    let energies_at = |param: DFTD3DampingParamEnum| -> Result<Vec<f64>, DFTD3Error> {
+        let param = param.new_param_f()?;
+        set.iter().map(|d| Ok(d.reaction.compute_f(&param)?.energy)).collect()
+    };
+    // weighted residuals
+    let residuals_of = |energies: &[f64]| -> Vec<f64> {
+        set.iter().zip(energies).zip(&weights).map(|((d, e), w)| w * (e - d.target)).collect()
+    };
+    // energies and weighted residuals, none for parameters the library rejects
+    let evaluate = |values: &[f64]| -> Result<FitEvaluation<Vec<f64>>, DFTD3Error> {
+        let Ok(param) = with_values(values).and_then(|p| p.new_param_f()) else {
+            return Ok(None);
+        };
+        let energies = set
+            .iter()
+            .map(|d| Ok(d.reaction.compute_f(&param)?.energy))
+            .collect::<Result<Vec<f64>, DFTD3Error>>()?;
+        let resid = residuals_of(&energies);
+        Ok(Some((energies, resid)))
+    };
+
+    // central-difference Jacobian of weighted residuals (ndata * nparam)
+    let jacobian_of = |values: &[f64]| -> Result<Vec<f64>, DFTD3Error> {
+        let mut jac =
+            param_jacobian_with(&with_values(values)?, fields, options.step, energies_at)?;
+        jac.chunks_mut(nparam)
+            .zip(&weights)
+            .for_each(|(row, w)| row.iter_mut().for_each(|x| *x *= w));
+        Ok(jac)
+    };
+
+    // report invalid initial parameters with the error of the library
+    with_values(&values)?.new_param_f()?;
+    let (point, niter, converged) = levenberg_marquardt(options, values, evaluate, jacobian_of)?;
+    let FitPoint { values, data: energies, resid, chi2 } = point;
+
+    // standard errors from covariance s^2 (J^T W J)^-1, with J at the optimum
+    let (jtj, _) = normal_equations(&jacobian_of(&values)?, &resid, nparam);
+    let s2 = if ndata > nparam { chi2 / (ndata - nparam) as f64 } else { 0.0 };
+    let std_errors = (0..nparam)
+        .map(|j| {
+            let mut unit = vec![0.0; nparam];
+            unit[j] = 1.0;
+            solve_linear(&jtj, &unit, nparam).map_or(f64::NAN, |col| (s2 * col[j]).sqrt())
+        })
+        .collect();
+
+    let residuals: Vec<f64> = set.iter().zip(&energies).map(|(d, e)| e - d.target).collect();
+    Ok(DFTD3FitResult {
+        param: with_values(&values)?,
+        fields: fields.clone(),
+        values,
+        std_errors,
+        energies,
+        statistics: DFTD3ResidualStatistics::new(&residuals),
+        residuals,
+        chi2,
+        niter,
+        converged,
+    })
+}
+
+/* #endregion */
+
+/* #region Levenberg-Marquardt loop */
+
+/// Point of the Levenberg-Marquardt iteration.
+struct FitPoint<T> {
+    values: Vec<f64>,
+    /// Data evaluated with the residuals (e.g. energies).
+    data: T,
+    /// Weighted residuals.
+    resid: Vec<f64>,
+    chi2: f64,
+}
+
+/// Data and weighted residuals at a point, `None` outside the valid parameter
+/// domain.
+type FitEvaluation<T> = Option<(T, Vec<f64>)>;
+
+/// Levenberg-Marquardt loop over fitted values, returns the final point, the
+/// number of iterations and whether converged.
+///
+/// `evaluate` returns data and weighted residuals at a point, or `None` if the
+/// values are outside the valid parameter domain; such trial steps are
+/// rejected like uphill steps. `jacobian_of` returns the row-major Jacobian of
+/// weighted residuals.
+fn levenberg_marquardt<T>(
+    options: &DFTD3FitOptions,
+    values: Vec<f64>,
+    mut evaluate: impl FnMut(&[f64]) -> Result<FitEvaluation<T>, DFTD3Error>,
+    mut jacobian_of: impl FnMut(&[f64]) -> Result<Vec<f64>, DFTD3Error>,
+) -> Result<(FitPoint<T>, usize, bool), DFTD3Error> {
+    let nparam = values.len();
+    let point_at = |values: Vec<f64>, (data, resid): (T, Vec<f64>)| {
+        let chi2 = dot(&resid, &resid);
+        FitPoint { values, data, resid, chi2 }
+    };
+    let Some(initial) = evaluate(&values)? else {
+        return Err(DFTD3Error::ParametersError(format!(
+            "Initial values {values:?} of fitted fields are invalid"
+        )));
+    };
+    let mut point = point_at(values, initial);
+    let mut lambda = 1.0e-3;
+    let mut converged = false;
+    let mut niter = 0;
+
+    while niter < options.max_iter {
+        niter += 1;
+        let jac = jacobian_of(&point.values)?;
+        let (jtj, jtr) = normal_equations(&jac, &point.resid, nparam);
+
+        // damped step, increase damping until chi2 decreases
+        let mut accepted = None;
+        while lambda < 1.0e12 {
+            let mut a = jtj.clone();
+            (0..nparam).for_each(|j| a[j * nparam + j] += lambda * jtj[j * nparam + j].max(1e-30));
+            let rhs: Vec<f64> = jtr.iter().map(|x| -x).collect();
+            let Some(delta) = solve_linear(&a, &rhs, nparam) else {
+                lambda *= 10.0;
+                continue;
+            };
+            let trial: Vec<f64> = point.values.iter().zip(&delta).map(|(v, d)| v + d).collect();
+            // invalid parameters are rejected, as steps increasing chi2
+            if let Some(eval) = evaluate(&trial)? {
+                let trial = point_at(trial, eval);
+                if trial.chi2 < point.chi2 {
+                    lambda = (lambda / 10.0).max(1.0e-12);
+                    accepted = Some(trial);
+                    break;
+                }
+            }
+            lambda *= 10.0;
+        }
+
+        let Some(trial) = accepted else {
+            // no downhill step found: at a minimum within numerical precision
+            converged = true;
+            break;
+        };
+        let decrease = point.chi2 - trial.chi2;
+        point = trial;
+        if decrease <= options.tol * point.chi2.max(f64::MIN_POSITIVE) {
+            converged = true;
+            break;
+        }
+    }
+    Ok((point, niter, converged))
+}
+
+/* #endregion */
+
+/* #region Linear algebra utilities */
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+/// Normal equations `(J^T J, J^T r)` of a row-major Jacobian with `ncol`
+/// columns.
+fn normal_equations(jac: &[f64], resid: &[f64], ncol: usize) -> (Vec<f64>, Vec<f64>) {
+    let mut jtj = vec![0.0; ncol * ncol];
+    let mut jtr = vec![0.0; ncol];
+    for (row, r) in jac.chunks(ncol).zip(resid) {
+        for a in 0..ncol {
+            jtr[a] += row[a] * r;
+            for b in 0..ncol {
+                jtj[a * ncol + b] += row[a] * row[b];
+            }
+        }
+    }
+    (jtj, jtr)
+}
+
+/// Solve `A x = b` (row-major `n * n`) by Gaussian elimination with partial
+/// pivoting; returns `None` for singular matrices.
+fn solve_linear(a: &[f64], b: &[f64], n: usize) -> Option<Vec<f64>> {
+    let mut a = a.to_vec();
+    let mut x = b.to_vec();
+    let scale = a.iter().map(|v| v.abs()).fold(0.0, f64::max);
+    for col in 0..n {
+        let pivot =
+            (col..n).max_by(|&i, &j| a[i * n + col].abs().total_cmp(&a[j * n + col].abs()))?;
+        if a[pivot * n + col].abs() <= 1e-14 * scale {
+            return None;
+        }
+        if pivot != col {
+            (0..n).for_each(|k| a.swap(pivot * n + k, col * n + k));
+            x.swap(pivot, col);
+        }
+        for row in col + 1..n {
+            let f = a[row * n + col] / a[col * n + col];
+            (col..n).for_each(|k| a[row * n + k] -= f * a[col * n + k]);
+            x[row] -= f * x[col];
+        }
+    }
+    for col in (0..n).rev() {
+        let s: f64 = (col + 1..n).map(|k| a[col * n + k] * x[k]).sum();
+        x[col] = (x[col] - s) / a[col * n + col];
+    }
+    Some(x)
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fit_helpers() {
+        let a = [4.0, 1.0, 0.0, 1.0, 3.0, 1.0, 0.0, 1.0, 2.0];
+        let x = solve_linear(&a, &[1.0, 2.0, 3.0], 3).unwrap();
+        let ax: Vec<f64> = a.chunks(3).map(|row| dot(row, &x)).collect();
+        ax.iter().zip([1.0, 2.0, 3.0]).for_each(|(a, b)| assert!((a - b).abs() < 1e-12));
+        assert!(solve_linear(&[1.0, 2.0, 2.0, 4.0], &[1.0, 1.0], 2).is_none());
+
+        let stats = DFTD3ResidualStatistics::new(&[1.0, -3.0]);
+        assert_eq!((stats.md, stats.mad, stats.max_abs), (-1.0, 2.0, 3.0));
+        assert!((stats.rmsd - 5.0_f64.sqrt()).abs() < 1e-12);
+
+        // parameter field access and database entry export
+        let mut param = dftd3_get_damping_param("b3lyp", "bj");
+        assert_eq!(param.param.version(), "bj");
+        assert_eq!(param.param.get_field("a1"), Some(0.3981));
+        assert!(param.param.set_field("rs6", 1.0).is_err());
+        param.param.set_field("a1", 0.4).unwrap();
+        assert_eq!(
+            param.to_toml_entry("B3-LYP"),
+            "[parameter.b3lyp]\nd3.bj = {a1=0.4, a2=4.4211, s8=1.9889, doi=\"10.1002/jcc.21759\"}\n"
+        );
+    }
+
+    #[test]
+    fn test_levenberg_marquardt() {
+        let options = DFTD3FitOptionsBuilder::default().fields(vec![]).build().unwrap();
+
+        // y = a exp(-b t), exact data of a = 2, b = 0.5; b < 0 is invalid
+        let t: Vec<f64> = (0..8).map(|i| 0.5 * i as f64).collect();
+        let y: Vec<f64> = t.iter().map(|t| 2.0 * (-0.5 * t).exp()).collect();
+        let evaluate = |x: &[f64]| -> Result<FitEvaluation<()>, DFTD3Error> {
+            let resid = t.iter().zip(&y).map(|(t, y)| x[0] * (-x[1] * t).exp() - y).collect();
+            Ok((x[1] >= 0.0).then_some(((), resid)))
+        };
+        let jacobian_of = |x: &[f64]| -> Result<Vec<f64>, DFTD3Error> {
+            let row = |t: f64| [(-x[1] * t).exp(), -x[0] * t * (-x[1] * t).exp()];
+            Ok(t.iter().flat_map(|&t| row(t)).collect())
+        };
+        let (point, niter, converged) =
+            levenberg_marquardt(&options, vec![1.0, 0.1], evaluate, jacobian_of).unwrap();
+        assert!(converged && niter < options.max_iter);
+        assert!((point.values[0] - 2.0).abs() < 1e-6 && (point.values[1] - 0.5).abs() < 1e-6);
+        assert!(point.chi2 < 1e-12);
+        assert!(levenberg_marquardt(&options, vec![1.0, -0.1], evaluate, jacobian_of).is_err());
+
+        // r = x - 2 with x <= 1 valid: steps beyond the bound are rejected,
+        // the fit ends at the bound instead of failing
+        let evaluate = |x: &[f64]| Ok((x[0] <= 1.0).then_some(((), vec![x[0] - 2.0])));
+        let jacobian_of = |_: &[f64]| Ok(vec![1.0]);
+        let (point, _, converged) =
+            levenberg_marquardt(&options, vec![0.0], evaluate, jacobian_of).unwrap();
+        assert!(converged);
+        assert_eq!(point.values, [1.0]);
+        assert_eq!(point.chi2, 1.0);
+    }
+}
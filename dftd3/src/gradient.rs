@@ -0,0 +1,371 @@
+//! Gradient post-processing: frozen atoms, net force and torque removal.
+//!
+//! Gradients from [`DFTD3Model::get_dispersion`] (and counterpoise gradients
+//! of GCP, which share [`DFTD3Output`]) are projected in a fixed order:
+//!
+//! 1. gradients of frozen atoms are zeroed;
+//! 2. rigid translations (net force) of the free atoms are projected out, if
+//!    requested;
+//! 3. rigid rotations (net torque about the centroid of the free atoms) are
+//!    projected out, if requested; this only makes sense for molecules.
+//!
+//! Rigid-body motions are taken over the free atoms only, so the projected
+//! gradient keeps frozen atoms at zero and has no net force (and torque).
+//!
+//! The norm of each removed component is reported in
+//! [`DFTD3GradientProjection`]. For a converged real-space sum the net force of
+//! the D3 gradient vanishes, so a large removed translation indicates cutoff
+//! artifacts.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use dftd3::prelude::*;
+//!
+//! # let numbers: Vec<usize> = vec![];
+//! # let positions: Vec<f64> = vec![];
+//! let param = dftd3_load_param("d3bj", "pbe0", true);
+//! let model = DFTD3Model::new(&numbers, &positions, None, None);
+//! let mut res = model.get_dispersion(&param, true);
+//!
+//! let options = DFTD3GradientProjectorOptionsBuilder::default()
+//!     .frozen(vec![0])
+//!     .remove_rotation(true)
+//!     .build()
+//!     .unwrap();
+//! let projector = DFTD3GradientProjector::from_model(&model, options);
+//! let removed = projector.project_output(&mut res).unwrap();
+//! println!("removed net force: {:.3e}", removed.translation);
+//! ```
+
+use crate::interface::*;
+use crate::utils::*;
+use derive_builder::Builder;
+
+/* #region Projector options */
+
+/// Options for gradient projection.
+#[derive(Builder, Debug, Clone)]
+#[builder(pattern = "owned", build_fn(error = "DFTD3Error"))]
+pub struct DFTD3GradientProjectorOptions {
+    #[builder(default)]
+    #[doc = r"optional, default empty; indices of frozen atoms, whose gradients are zeroed"]
+    pub frozen: Vec<usize>,
+    #[builder(default = true)]
+    #[doc = r"optional, default true; project out rigid translations (net force)"]
+    pub remove_translation: bool,
+    #[builder(default = false)]
+    #[doc = r"optional, default false; project out rigid rotations (net torque), for molecules"]
+    pub remove_rotation: bool,
+}
+
+impl Default for DFTD3GradientProjectorOptions {
+    fn default() -> Self {
+        DFTD3GradientProjectorOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/* #endregion */
+
+/* #region Projection report */
+
+/// Magnitudes removed by gradient projection.
+#[derive(Debug, Clone, Default)]
+pub struct DFTD3GradientProjection {
+    /// Net force (sum of gradients) before projection.
+    pub net_force: [f64; 3],
+    /// Net torque about the centroid of the free atoms before projection, the
+    /// same center as of the removed rotations.
+    pub net_torque: [f64; 3],
+    /// Norm of the removed translational component.
+    pub translation: f64,
+    /// Norm of the removed rotational component.
+    pub rotation: f64,
+    /// Norm of the removed gradients of frozen atoms.
+    pub frozen: f64,
+}
+
+/* #endregion */
+
+/* #region Projector */
+
+/// Constraint-aware gradient projector for a given geometry.
+pub struct DFTD3GradientProjector {
+    positions: Vec<f64>,
+    options: DFTD3GradientProjectorOptions,
+    /// Centroid of the free atoms.
+    centroid: [f64; 3],
+    /// Orthonormal basis of the removed rigid-body motions (natom * 3 each).
+    basis_translation: Vec<Vec<f64>>,
+    basis_rotation: Vec<Vec<f64>>,
+}
+
+impl DFTD3GradientProjector {
+    /// Create new gradient projector.
+    ///
+    /// - `positions` - atomic positions in Bohr (natom * 3)
+    /// - `options` - frozen atoms and rigid-body projections
+    pub fn new(positions: &[f64], options: DFTD3GradientProjectorOptions) -> Self {
+        Self::new_f(positions, options).unwrap()
+    }
+
+    /// Create new gradient projector for the current geometry of a model.
+    ///
+    /// - `model` - dispersion model whose gradients are projected
+    /// - `options` - frozen atoms and rigid-body projections
+    pub fn from_model(model: &DFTD3Model, options: DFTD3GradientProjectorOptions) -> Self {
+        Self::from_model_f(model, options).unwrap()
+    }
+
+    /// Project gradient (natom * 3) in place.
+    pub fn project(&self, grad: &mut [f64]) -> DFTD3GradientProjection {
+        self.project_f(grad).unwrap()
+    }
+
+    /// Project gradient of dispersion or counterpoise output in place.
+    ///
+    /// Returns `None` if the output does not contain gradient.
+    pub fn project_output(&self, output: &mut DFTD3Output) -> Option<DFTD3GradientProjection> {
+        self.project_output_f(output).unwrap()
+    }
+
+    /// Create new gradient projector (failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3GradientProjector::new`]
+    pub fn new_f(
+        positions: &[f64],
+        options: DFTD3GradientProjectorOptions,
+    ) -> Result<Self, DFTD3Error> {
+        if !positions.len().is_multiple_of(3) {
+            return Err(DFTD3Error::Rust(format!(
+                "Invalid dimension for positions, expected multiple of 3, got {}",
+                positions.len()
+            )));
+        }
+        let natoms = positions.len() / 3;
+        check_atom_indices(&options.frozen, natoms, "frozen atoms")?;
+
+        // rigid-body motions of the free atoms
+        let mut free = vec![true; natoms];
+        options.frozen.iter().for_each(|&i| free[i] = false);
+        let free_positions: Vec<f64> = positions
+            .chunks(3)
+            .zip(&free)
+            .filter(|(_, &f)| f)
+            .flat_map(|(r, _)| r.to_vec())
+            .collect();
+        let centroid = centroid(&free_positions);
+        let mut basis_translation = vec![];
+        if options.remove_translation {
+            for k in 0..3 {
+                let v: Vec<f64> = (0..3 * natoms)
+                    .map(|x| if x % 3 == k && free[x / 3] { 1.0 } else { 0.0 })
+                    .collect();
+                orthonormal_push(&mut basis_translation, &[], v);
+            }
+        }
+        let mut basis_rotation = vec![];
+        if options.remove_rotation {
+            for k in 0..3 {
+                // e_k x (r_i - c) for free atoms
+                let v = positions
+                    .chunks(3)
+                    .zip(&free)
+                    .flat_map(|(r, &f)| {
+                        let d = [r[0] - centroid[0], r[1] - centroid[1], r[2] - centroid[2]];
+                        let mut e = [0.0; 3];
+                        e[k] = if f { 1.0 } else { 0.0 };
+                        cross(e, d)
+                    })
+                    .collect();
+                orthonormal_push(&mut basis_rotation, &basis_translation, v);
+            }
+        }
+
+        Ok(Self {
+            positions: positions.to_vec(),
+            options,
+            centroid,
+            basis_translation,
+            basis_rotation,
+        })
+    }
+
+    /// Create new gradient projector for the current geometry of a model
+    /// (failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3GradientProjector::from_model`]
+    pub fn from_model_f(
+        model: &DFTD3Model,
+        options: DFTD3GradientProjectorOptions,
+    ) -> Result<Self, DFTD3Error> {
+        Self::new_f(model.get_positions(), options)
+    }
+
+    /// Project gradient (natom * 3) in place (failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3GradientProjector::project`]
+    pub fn project_f(&self, grad: &mut [f64]) -> Result<DFTD3GradientProjection, DFTD3Error> {
+        if grad.len() != self.positions.len() {
+            return Err(DFTD3Error::Rust(format!(
+                "Invalid dimension for gradient, expected {}, got {}",
+                self.positions.len(),
+                grad.len()
+            )));
+        }
+
+        let centroid = self.centroid;
+        let mut report = DFTD3GradientProjection::default();
+        for (r, g) in self.positions.chunks(3).zip(grad.chunks(3)) {
+            let d = [r[0] - centroid[0], r[1] - centroid[1], r[2] - centroid[2]];
+            let t = cross(d, [g[0], g[1], g[2]]);
+            (0..3).for_each(|x| {
+                report.net_force[x] += g[x];
+                report.net_torque[x] += t[x];
+            });
+        }
+
+        let mut frozen = 0.0;
+        for &i in &self.options.frozen {
+            frozen += grad[3 * i..3 * i + 3].iter().map(|g| g * g).sum::<f64>();
+            grad[3 * i..3 * i + 3].fill(0.0);
+        }
+        report.frozen = frozen.sqrt();
+        report.translation = project_out(grad, &self.basis_translation);
+        report.rotation = project_out(grad, &self.basis_rotation);
+        Ok(report)
+    }
+
+    /// Project gradient of dispersion or counterpoise output in place
+    /// (failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3GradientProjector::project_output`]
+    pub fn project_output_f(
+        &self,
+        output: &mut DFTD3Output,
+    ) -> Result<Option<DFTD3GradientProjection>, DFTD3Error> {
+        output.grad.as_mut().map(|grad| self.project_f(grad)).transpose()
+    }
+}
+
+/* #endregion */
+
+/* #region Vector utilities */
+
+fn centroid(positions: &[f64]) -> [f64; 3] {
+    let natoms = (positions.len() / 3).max(1) as f64;
+    std::array::from_fn(|x| positions.chunks(3).map(|r| r[x]).sum::<f64>() / natoms)
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+/// Orthonormalize `v` against `fixed` and `basis` (Gram-Schmidt), and push it
+/// to `basis` unless it is linearly dependent (e.g. rotation about the axis of
+/// a linear molecule).
+fn orthonormal_push(basis: &mut Vec<Vec<f64>>, fixed: &[Vec<f64>], mut v: Vec<f64>) {
+    let scale = dot(&v, &v).sqrt();
+    for u in fixed.iter().chain(basis.iter()) {
+        let c = dot(u, &v);
+        v.iter_mut().zip(u).for_each(|(v, u)| *v -= c * u);
+    }
+    let norm = dot(&v, &v).sqrt();
+    if norm > 1e-8 * scale.max(1.0) {
+        v.iter_mut().for_each(|v| *v /= norm);
+        basis.push(v);
+    }
+}
+
+/// Remove components along orthonormal `basis` from `grad`, returns the norm
+/// of the removed component.
+fn project_out(grad: &mut [f64], basis: &[Vec<f64>]) -> f64 {
+    let mut removed = 0.0;
+    for u in basis {
+        let c = dot(u, grad);
+        grad.iter_mut().zip(u).for_each(|(g, u)| *g -= c * u);
+        removed += c * c;
+    }
+    removed.sqrt()
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_gradient_projection() {
+        // linear triatomic along z, plus one off-axis atom
+        let positions = vec![0.0, 0.0, -2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 2.0, 1.5, 0.0, 0.0];
+        let grad0 = vec![0.1, 0.2, 0.3, -0.05, 0.0, 0.01, 0.02, -0.1, 0.0, 0.0, 0.03, -0.02];
+
+        // translation only: net force vanishes, removed norm equals |F| / sqrt(natoms)
+        let mut grad = grad0.clone();
+        let projector = DFTD3GradientProjector::new(&positions, Default::default());
+        let report = projector.project(&mut grad);
+        let force = report.net_force;
+        let fnorm = force.iter().map(|f| f * f).sum::<f64>().sqrt();
+        assert!((report.translation - fnorm / 2.0).abs() < 1e-12);
+        (0..3).for_each(|x| assert!(grad.chunks(3).map(|g| g[x]).sum::<f64>().abs() < 1e-12));
+
+        // frozen atoms: zeroed, and no net force (and torque) is re-introduced
+        for (frozen, rotation) in [(vec![3], true), (vec![0], true), (vec![1, 3], false)] {
+            let options = DFTD3GradientProjectorOptionsBuilder::default()
+                .remove_rotation(rotation)
+                .frozen(frozen.clone())
+                .build()
+                .unwrap();
+            let mut grad = grad0.clone();
+            let projector = DFTD3GradientProjector::new(&positions, options);
+            let report = projector.project(&mut grad);
+            assert!(report.frozen > 0.0);
+            frozen.iter().for_each(|&i| assert_eq!(&grad[3 * i..3 * i + 3], &[0.0; 3]));
+            let report =
+                DFTD3GradientProjector::new(&positions, Default::default()).project(&mut grad);
+            assert!(report.net_force.iter().all(|f| f.abs() < 1e-12), "{report:?}");
+            if rotation {
+                assert!(report.net_torque.iter().all(|t| t.abs() < 1e-12), "{report:?}");
+            }
+        }
+        let mut grad = grad0.clone();
+        let options =
+            DFTD3GradientProjectorOptionsBuilder::default().remove_rotation(true).build().unwrap();
+        let report = DFTD3GradientProjector::new(&positions, options).project(&mut grad);
+        assert!(report.rotation > 0.0);
+        let report = DFTD3GradientProjector::new(&positions, Default::default()).project(&mut grad);
+        assert!(report.net_force.iter().all(|f| f.abs() < 1e-12));
+        assert!(report.net_torque.iter().all(|t| t.abs() < 1e-12));
+
+        // reported torque is about the centroid of the free atoms, so a pure
+        // rotation of the free atoms is reported and removed as such
+        let options = DFTD3GradientProjectorOptionsBuilder::default()
+            .remove_rotation(true)
+            .frozen(vec![3])
+            .build()
+            .unwrap();
+        let projector = DFTD3GradientProjector::new(&positions, options);
+        // rotation about the y axis through the free centroid (origin), and a
+        // force on the frozen atom
+        let mut grad = vec![-2.0, 0.0, 0.0, 0.0, 0.0, 0.0, 2.0, 0.0, 0.0, 0.0, 0.0, 1.0];
+        let report = projector.project(&mut grad);
+        assert_eq!(report.net_force, [0.0, 0.0, 1.0]);
+        assert!((report.net_torque[1] - 6.5).abs() < 1e-12, "{report:?}");
+        assert!((report.rotation - 8.0_f64.sqrt()).abs() < 1e-12);
+        assert!((report.frozen - 1.0).abs() < 1e-12);
+        assert!(grad.iter().all(|g| g.abs() < 1e-12));
+    }
+}
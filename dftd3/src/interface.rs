@@ -1,6 +1,10 @@
 //! DFTD3 interface (safe wrapper).
 
 use crate::ffi;
+#[cfg(feature = "api-v0_4")]
+use crate::parameters::*;
+#[cfg(feature = "api-v0_4")]
+use crate::parsing::{dftd3_parse_damping_param_f, resolve_method_label};
 use derive_builder::{Builder, UninitializedFieldError};
 use serde::{Deserialize, Serialize};
 use serde_inline_default::serde_inline_default;
@@ -135,8 +139,8 @@ impl std::fmt::Display for DFTD3Error {
 /// Represents a wrapped structure object in `s-dftd3`. The molecular structure
 /// data object has a fixed number of atoms and immutable atomic identifiers.
 ///
-/// Note that except for number of atoms is stored in this struct, geometric
-/// positions and lattice is not retrivable. API caller should handle these
+/// Note that only number of atoms and atomic positions are stored in this
+/// struct, lattice is not retrivable. API caller should handle these
 /// information for themselves.
 ///
 /// # Note
@@ -152,6 +156,8 @@ pub struct DFTD3Structure {
     pub(crate) ptr: ffi::dftd3_structure,
     /// Number of atoms in the structure.
     natoms: usize,
+    /// Atomic positions of the last creation or update in Bohr (natom * 3).
+    positions: Vec<f64>,
 }
 
 impl Drop for DFTD3Structure {
@@ -202,6 +208,11 @@ impl DFTD3Structure {
         self.natoms
     }
 
+    /// Get atomic positions (in Bohr, natom * 3) for this current structure.
+    pub fn get_positions(&self) -> &[f64] {
+        &self.positions
+    }
+
     /// Create new molecular structure data from arrays (in Bohr, failable).
     ///
     /// # See also
@@ -254,7 +265,7 @@ impl DFTD3Structure {
         };
         match error.check() {
             true => Err(error),
-            false => Ok(Self { ptr, natoms }),
+            false => Ok(Self { ptr, natoms, positions: positions.to_vec() }),
         }
     }
 
@@ -296,7 +307,10 @@ impl DFTD3Structure {
         };
         match error.check() {
             true => Err(error),
-            false => Ok(()),
+            false => {
+                self.positions = positions.to_vec();
+                Ok(())
+            },
         }
     }
 }
@@ -666,6 +680,15 @@ pub fn dftd3_load_param(version: &str, method: &str, atm: bool) -> DFTD3Param {
 #[cfg(feature = "api-v0_4")]
 /// Load damping parameters by functional and DFT-D3 versions.
 ///
+/// `method` may also be a method label like "B3LYP-D3(BJ)-ATM" (see
+/// [`dftd3_parse_method_label`](crate::parsing::dftd3_parse_method_label)),
+/// whose variant must agree with `version`; `-ATM` forces `atm = true`.
+///
+/// Aliases registered by [`dftd3_register_alias`] are resolved first. Methods
+/// unknown to the s-dftd3 library are looked up in the parameter database of
+/// this crate, which includes methods registered by [`dftd3_register_method`]
+/// or loaded from parameter files.
+///
 /// # See also
 ///
 /// [`dftd3_load_param`]
@@ -673,6 +696,31 @@ pub fn dftd3_load_param_f(
     version: &str,
     method: &str,
     atm: bool,
+) -> Result<DFTD3Param, DFTD3Error> {
+    let (method, version, atm) = resolve_method_label(method, Some(version), Some(atm))?;
+    let version = version.unwrap_or_default();
+    let resolved = ParameterDatabase::global_f()
+        .ok()
+        .and_then(|db| db.resolve_alias(&method).map(|m| m.to_string()));
+    let method = resolved.as_deref().unwrap_or(&method);
+    dftd3_load_param_c_f(&version, method, atm).or_else(|error| {
+        let mut input = toml::Table::new();
+        input.insert("version".into(), version.into());
+        input.insert("method".into(), method.into());
+        input.insert("atm".into(), atm.into());
+        match dftd3_parse_damping_param_f(&input) {
+            Ok(param) => param.new_param_f(),
+            Err(_) => Err(error),
+        }
+    })
+}
+
+/// Load damping parameters from the s-dftd3 library only.
+#[cfg(feature = "api-v0_4")]
+pub(crate) fn dftd3_load_param_c_f(
+    version: &str,
+    method: &str,
+    atm: bool,
 ) -> Result<DFTD3Param, DFTD3Error> {
     let version = version.to_lowercase().replace(['-', '_', ' '], "");
     match version.as_str() {
@@ -1160,6 +1208,11 @@ impl DFTD3Model {
         self.structure.get_natoms()
     }
 
+    /// Get atomic positions (in Bohr, natom * 3) for this current structure.
+    pub fn get_positions(&self) -> &[f64] {
+        self.structure.get_positions()
+    }
+
     /// Create new D3 dispersion model from structure.
     pub fn from_structure(structure: DFTD3Structure) -> Self {
         Self::from_structure_f(structure).unwrap()
@@ -33,9 +33,37 @@ pub mod ffi_dynamic;
 #[cfg(feature = "dynamic_loading")]
 pub use ffi_dynamic as ffi;
 
+#[cfg(feature = "api-v0_4")]
+pub mod benchmark;
+pub mod citation;
+#[cfg(feature = "api-v0_4")]
+pub mod crosscheck;
+#[cfg(feature = "api-v0_5")]
+pub mod crystal;
+pub mod diff;
+pub mod export;
+#[cfg(feature = "api-v0_4")]
+pub mod fitting;
+pub mod gradient;
 pub mod interface;
+pub mod mbe;
+pub mod oniom;
+pub mod optimize;
 pub mod parameters;
 pub mod parsing;
+pub mod probe;
+pub mod reaction;
+#[cfg(feature = "api-v0_4")]
+pub mod selftest;
+#[cfg(feature = "api-v0_4")]
+pub mod sensitivity;
+pub mod validate;
+
+#[cfg(feature = "api-v0_4")]
+pub use selftest::selftest;
+
+pub(crate) mod elements;
+pub(crate) mod utils;
 
 #[cfg(feature = "gcp")]
 pub mod interface_gcp;
@@ -43,9 +71,31 @@ pub mod interface_gcp;
 pub mod prelude {
     //! Use `dftd3::prelude::*` to import all the commonly used structs and
     //! functions.
+    #[cfg(feature = "api-v0_4")]
+    pub use crate::benchmark::*;
+    pub use crate::citation::*;
+    #[cfg(feature = "api-v0_4")]
+    pub use crate::crosscheck::*;
+    #[cfg(feature = "api-v0_5")]
+    pub use crate::crystal::*;
+    pub use crate::diff::*;
+    pub use crate::export::*;
+    #[cfg(feature = "api-v0_4")]
+    pub use crate::fitting::*;
+    pub use crate::gradient::*;
     pub use crate::interface::*;
+    pub use crate::mbe::*;
+    pub use crate::oniom::*;
+    pub use crate::optimize::*;
     pub use crate::parameters::*;
     pub use crate::parsing::*;
+    pub use crate::probe::*;
+    pub use crate::reaction::*;
+    #[cfg(feature = "api-v0_4")]
+    pub use crate::selftest::*;
+    #[cfg(feature = "api-v0_4")]
+    pub use crate::sensitivity::*;
+    pub use crate::validate::*;
 
     #[cfg(feature = "gcp")]
     pub use crate::interface_gcp::*;
@@ -0,0 +1,300 @@
+//! Fragment many-body expansion (MBE) of the dispersion energy.
+//!
+//! The dispersion energy of a system partitioned into fragments can be
+//! expanded as
+//!
+//! ```text
+//! E = sum_I E_I + sum_{I<J} dE_IJ + sum_{I<J<K} dE_IJK + ...
+//! ```
+//!
+//! with the 2-body increment `dE_IJ = E_IJ - E_I - E_J` and the 3-body
+//! increment `dE_IJK = E_IJK - dE_IJ - dE_IK - dE_JK - E_I - E_J - E_K`.
+//!
+//! For the pairwise-additive part of DFT-D3 the expansion is exact at second
+//! order, except for the dependence of C6 coefficients on coordination
+//! numbers. Higher-order increments and the residual against the full-system
+//! energy therefore measure the ATM and CN-dependence effects.
+//!
+//! Periodic structures are fragmented within the unit cell: every
+//! fragment-subsystem keeps the lattice and periodicity of the full system.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use dftd3::prelude::*;
+//!
+//! // water dimer in angstrom, converted to bohr below
+//! let numbers = vec![8, 1, 1, 8, 1, 1];
+//! #[rustfmt::skip]
+//! let positions = vec![
+//!     -1.551007, -0.114520,  0.000000,
+//!     -1.934259,  0.762503,  0.000000,
+//!     -0.599677,  0.040712,  0.000000,
+//!      1.350625,  0.111469,  0.000000,
+//!      1.680398, -0.373741, -0.758561,
+//!      1.680398, -0.373741,  0.758561,
+//! ];
+//! let positions = positions.iter().map(|x| x / 0.52917721067).collect::<Vec<f64>>();
+//! let fragments = vec![vec![0, 1, 2], vec![3, 4, 5]];
+//!
+//! let param = dftd3_get_damping_param("b3lyp", "bj");
+//! let driver = DFTD3MBEDriver::new(&numbers, &positions, None, None, &fragments);
+//! let res = driver.compute(&param, false);
+//! println!("2-body interaction: {}", res.energy_2body);
+//! println!("residual          : {}", res.residual());
+//! ```
+
+use crate::interface::*;
+use crate::utils::*;
+use rayon::prelude::*;
+
+/* #region MBE output */
+
+/// Fragment-level many-body expansion result.
+#[derive(Debug, Clone)]
+pub struct DFTD3MBEOutput {
+    /// Dispersion energy of the full system.
+    pub energy: f64,
+    /// Dispersion energy of each isolated fragment (1-body terms).
+    pub monomer_energies: Vec<f64>,
+    /// 2-body increments for each fragment pair `(I, J)` with `I < J`.
+    pub dimer_increments: Vec<([usize; 2], f64)>,
+    /// 3-body increments for each fragment triple `(I, J, K)` with `I < J < K`,
+    /// only if trimers were requested.
+    pub trimer_increments: Option<Vec<([usize; 3], f64)>>,
+    /// Sum of 1-body terms.
+    pub energy_1body: f64,
+    /// Sum of 2-body increments.
+    pub energy_2body: f64,
+    /// Sum of 3-body increments, only if trimers were requested.
+    pub energy_3body: Option<f64>,
+}
+
+impl DFTD3MBEOutput {
+    /// Dispersion energy recovered by the truncated expansion.
+    pub fn energy_mbe(&self) -> f64 {
+        self.energy_1body + self.energy_2body + self.energy_3body.unwrap_or(0.0)
+    }
+
+    /// Difference between the full-system energy and the truncated expansion.
+    pub fn residual(&self) -> f64 {
+        self.energy - self.energy_mbe()
+    }
+}
+
+/* #endregion */
+
+/* #region MBE driver */
+
+/// Many-body expansion driver over user-defined fragments.
+///
+/// Fragments are given as lists of atom indices, and must be a partition of
+/// all atoms in the structure (each atom belongs to exactly one fragment).
+///
+/// All subsystems (monomers, dimers, optional trimers and the full system) are
+/// evaluated in parallel, each with its own [`DFTD3Model`]. Damping parameters
+/// are given as a parameter struct (e.g. [`DFTD3RationalDampingParam`] or
+/// [`DFTD3DampingParam`](crate::parameters::DFTD3DampingParam)), from which
+/// every worker thread builds its own [`DFTD3Param`], as library handles are
+/// not shared between threads.
+pub struct DFTD3MBEDriver {
+    numbers: Vec<usize>,
+    positions: Vec<f64>,
+    lattice: Option<Vec<f64>>,
+    periodic: Option<Vec<bool>>,
+    fragments: Vec<Vec<usize>>,
+}
+
+impl DFTD3MBEDriver {
+    /// Create new MBE driver from arrays (in Bohr) and fragment definitions.
+    ///
+    /// - `numbers` - element index (6 for O, 7 for N) in the structure
+    /// - `positions` - atomic positions in Bohr (natom * 3)
+    /// - `lattice` - optional, lattice parameters (3 * 3)
+    /// - `periodic` - optional, periodicity (3)
+    /// - `fragments` - atom indices of each fragment
+    pub fn new(
+        numbers: &[usize],
+        positions: &[f64],
+        lattice: Option<&[f64]>,
+        periodic: Option<&[bool]>,
+        fragments: &[Vec<usize>],
+    ) -> Self {
+        Self::new_f(numbers, positions, lattice, periodic, fragments).unwrap()
+    }
+
+    /// Evaluate the many-body expansion up to 2-body, or 3-body if `trimers`
+    /// is set.
+    pub fn compute<P>(&self, param: &P, trimers: bool) -> DFTD3MBEOutput
+    where
+        P: DFTD3ParamAPI + Clone + Sync,
+    {
+        self.compute_f(param, trimers).unwrap()
+    }
+
+    /// Get number of fragments.
+    pub fn get_nfragments(&self) -> usize {
+        self.fragments.len()
+    }
+
+    /// Create new MBE driver from arrays (in Bohr, failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3MBEDriver::new`]
+    pub fn new_f(
+        numbers: &[usize],
+        positions: &[f64],
+        lattice: Option<&[f64]>,
+        periodic: Option<&[bool]>,
+        fragments: &[Vec<usize>],
+    ) -> Result<Self, DFTD3Error> {
+        check_structure_dims(numbers, positions, lattice, periodic)?;
+        check_partition(fragments, numbers.len(), "the structure")?;
+        Ok(Self {
+            numbers: numbers.to_vec(),
+            positions: positions.to_vec(),
+            lattice: lattice.map(|x| x.to_vec()),
+            periodic: periodic.map(|x| x.to_vec()),
+            fragments: fragments.to_vec(),
+        })
+    }
+
+    /// Evaluate the many-body expansion (failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3MBEDriver::compute`]
+    pub fn compute_f<P>(&self, param: &P, trimers: bool) -> Result<DFTD3MBEOutput, DFTD3Error>
+    where
+        P: DFTD3ParamAPI + Clone + Sync,
+    {
+        let nfrag = self.fragments.len();
+        let dimers: Vec<(usize, usize)> =
+            (0..nfrag).flat_map(|i| (i + 1..nfrag).map(move |j| (i, j))).collect();
+        let triples: Vec<(usize, usize, usize)> = match trimers {
+            true => (0..nfrag)
+                .flat_map(|i| {
+                    (i + 1..nfrag).flat_map(move |j| (j + 1..nfrag).map(move |k| (i, j, k)))
+                })
+                .collect(),
+            false => vec![],
+        };
+
+        // all subsystems to be evaluated: full system, monomers, dimers, trimers
+        let mut jobs: Vec<Vec<usize>> = vec![(0..self.numbers.len()).collect()];
+        jobs.extend((0..nfrag).map(|i| self.fragment_atoms(&[i])));
+        jobs.extend(dimers.iter().map(|&(i, j)| self.fragment_atoms(&[i, j])));
+        jobs.extend(triples.iter().map(|&(i, j, k)| self.fragment_atoms(&[i, j, k])));
+
+        // one library parameter handle per worker thread; errors are passed on
+        // as messages, since error handles are not sent between threads
+        let energies = jobs
+            .par_iter()
+            .map_init(
+                || param.clone().new_param_f(),
+                |worker_param, atoms| {
+                    match worker_param {
+                        Ok(worker_param) => self.subsystem_energy(worker_param, atoms),
+                        Err(e) => Err(DFTD3Error::Rust(e.to_string())),
+                    }
+                    .map_err(|e| e.to_string())
+                },
+            )
+            .collect::<Result<Vec<f64>, String>>()
+            .map_err(DFTD3Error::Rust)?;
+
+        let energy = energies[0];
+        let monomer_energies = energies[1..1 + nfrag].to_vec();
+        let dimer_energies = &energies[1 + nfrag..1 + nfrag + dimers.len()];
+        let trimer_energies = &energies[1 + nfrag + dimers.len()..];
+
+        let mut pair = vec![0.0; nfrag * nfrag];
+        let dimer_increments = dimers
+            .iter()
+            .zip(dimer_energies)
+            .map(|(&(i, j), &e)| {
+                let de = e - monomer_energies[i] - monomer_energies[j];
+                pair[i * nfrag + j] = de;
+                ([i, j], de)
+            })
+            .collect::<Vec<_>>();
+        let trimer_increments = trimers.then(|| {
+            triples
+                .iter()
+                .zip(trimer_energies)
+                .map(|(&(i, j, k), &e)| {
+                    let de = e
+                        - pair[i * nfrag + j]
+                        - pair[i * nfrag + k]
+                        - pair[j * nfrag + k]
+                        - monomer_energies[i]
+                        - monomer_energies[j]
+                        - monomer_energies[k];
+                    ([i, j, k], de)
+                })
+                .collect::<Vec<_>>()
+        });
+
+        let energy_1body = monomer_energies.iter().sum();
+        let energy_2body = dimer_increments.iter().map(|(_, de)| de).sum();
+        let energy_3body = trimer_increments.as_ref().map(|inc| inc.iter().map(|(_, de)| de).sum());
+
+        Ok(DFTD3MBEOutput {
+            energy,
+            monomer_energies,
+            dimer_increments,
+            trimer_increments,
+            energy_1body,
+            energy_2body,
+            energy_3body,
+        })
+    }
+
+    /// Sorted atom indices of the union of given fragments.
+    fn fragment_atoms(&self, frags: &[usize]) -> Vec<usize> {
+        let mut atoms: Vec<usize> =
+            frags.iter().flat_map(|&i| self.fragments[i].iter().copied()).collect();
+        atoms.sort_unstable();
+        atoms
+    }
+
+    /// Dispersion energy of a subsystem, keeping lattice and periodicity.
+    fn subsystem_energy(&self, param: &DFTD3Param, atoms: &[usize]) -> Result<f64, DFTD3Error> {
+        let (numbers, positions) = take_atoms(&self.numbers, &self.positions, atoms);
+        let model = DFTD3Model::new_f(
+            &numbers,
+            &positions,
+            self.lattice.as_deref(),
+            self.periodic.as_deref(),
+        )?;
+        Ok(model.get_dispersion_f(param, false)?.energy)
+    }
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_invalid_fragments() {
+        let numbers = vec![1, 1, 1];
+        let positions = vec![0.0, 0.0, 0.0, 0.0, 0.0, 1.4, 0.0, 0.0, 5.0];
+        let driver =
+            |frags: &[Vec<usize>]| DFTD3MBEDriver::new_f(&numbers, &positions, None, None, frags);
+        // overlapping fragments
+        assert!(driver(&[vec![0, 1], vec![1, 2]]).is_err());
+        // missing atom
+        assert!(driver(&[vec![0, 1]]).is_err());
+        // out of range
+        assert!(driver(&[vec![0, 1], vec![2, 3]]).is_err());
+        // empty fragment
+        assert!(driver(&[vec![0, 1, 2], vec![]]).is_err());
+        // valid partition
+        let driver = driver(&[vec![0, 1], vec![2]]).unwrap();
+        assert_eq!(driver.get_nfragments(), 2);
+        assert_eq!(driver.fragment_atoms(&[1, 0]), vec![0, 1, 2]);
+    }
+}
@@ -0,0 +1,335 @@
+//! ONIOM-style subtractive two-layer dispersion driver.
+//!
+//! The dispersion energy in a subtractive QM/QM' scheme reads
+//!
+//! ```text
+//! E = E(real, low) + E(model, high) - E(model, low)
+//! ```
+//!
+//! where the model system is a subset of the real system, capped by link atoms
+//! at bonds cut by the model-region boundary. Either level may use its own
+//! damping parameters, or be switched off (contributing zero).
+//!
+//! A link atom `L` capping the cut bond between model atom `Q` and real atom
+//! `R` is placed at
+//!
+//! ```text
+//! r_L = r_Q + g (r_R - r_Q)
+//! ```
+//!
+//! with scale factor `g`. Gradients on link atoms are projected back onto `Q`
+//! and `R` by the chain rule, i.e. `(1 - g) dE/dr_L` on `Q` and `g dE/dr_L` on
+//! `R`.
+//!
+//! Only molecular (non-periodic) systems are supported.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use dftd3::prelude::*;
+//!
+//! // ethanol, model region is the hydroxymethyl group (atoms 0, 2, 3, 4, 8)
+//! let numbers = vec![6, 6, 8, 1, 1, 1, 1, 1, 1];
+//! #[rustfmt::skip]
+//! let positions = vec![
+//!      0.0000,  0.0000,  0.0000,
+//!      2.8610,  0.0000,  0.0000,
+//!     -0.8900,  2.5020,  0.0000,
+//!     -0.6930, -0.9760,  1.6920,
+//!     -0.6930, -0.9760, -1.6920,
+//!      3.5540,  0.9760,  1.6920,
+//!      3.5540,  0.9760, -1.6920,
+//!      3.6120, -1.9130,  0.0000,
+//!     -2.7270,  2.5630,  0.0000,
+//! ];
+//! let model_atoms = vec![0, 2, 3, 4, 8];
+//! let link_atoms = vec![DFTD3LinkAtom::new(0, 1, 0.709)];
+//!
+//! let high = dftd3_load_param("d3bj", "pbe0", true);
+//! let low = dftd3_load_param("d3bj", "b973c", true);
+//! let driver = DFTD3ONIOMDriver::new(&numbers, &positions, &model_atoms, &link_atoms);
+//! let res = driver.compute(Some(&high), Some(&low), true);
+//! println!("ONIOM dispersion energy: {}", res.energy);
+//! ```
+
+use crate::interface::*;
+use crate::utils::*;
+use derive_builder::Builder;
+
+/* #region Link atom */
+
+/// Link atom capping a bond cut by the model-region boundary.
+#[derive(Builder, Debug, Clone)]
+#[builder(pattern = "owned", build_fn(error = "DFTD3Error"))]
+pub struct DFTD3LinkAtom {
+    /// Index (in real system) of the model-region atom of the cut bond.
+    pub model_atom: usize,
+    /// Index (in real system) of the real-region atom replaced by link atom.
+    pub real_atom: usize,
+    /// Scale factor `g` for placement along the cut bond.
+    pub scale: f64,
+    #[builder(default = 1)]
+    #[doc = r"optional, default 1 (hydrogen)"]
+    pub element: usize,
+}
+
+impl DFTD3LinkAtom {
+    /// Create new hydrogen link atom.
+    ///
+    /// - `model_atom` - index of the model-region atom of the cut bond
+    /// - `real_atom` - index of the real-region atom of the cut bond
+    /// - `scale` - scale factor `g` along the cut bond
+    pub fn new(model_atom: usize, real_atom: usize, scale: f64) -> Self {
+        Self { model_atom, real_atom, scale, element: 1 }
+    }
+
+    /// Position of the link atom in the real-system geometry (in Bohr).
+    pub fn position(&self, positions: &[f64]) -> [f64; 3] {
+        let (q, r) = (self.model_atom, self.real_atom);
+        std::array::from_fn(|x| {
+            positions[3 * q + x] + self.scale * (positions[3 * r + x] - positions[3 * q + x])
+        })
+    }
+}
+
+/* #endregion */
+
+/* #region ONIOM output */
+
+/// ONIOM dispersion result.
+#[derive(Debug, Clone)]
+pub struct DFTD3ONIOMOutput {
+    /// Combined dispersion energy.
+    pub energy: f64,
+    /// Combined gradient on real-system atoms (natom * 3).
+    pub grad: Option<Vec<f64>>,
+    /// Dispersion energy of the real system at low level.
+    pub energy_real_low: f64,
+    /// Dispersion energy of the model system at high level.
+    pub energy_model_high: f64,
+    /// Dispersion energy of the model system at low level.
+    pub energy_model_low: f64,
+}
+
+/* #endregion */
+
+/* #region ONIOM driver */
+
+/// Subtractive two-layer ONIOM dispersion driver.
+pub struct DFTD3ONIOMDriver {
+    numbers: Vec<usize>,
+    positions: Vec<f64>,
+    model_atoms: Vec<usize>,
+    link_atoms: Vec<DFTD3LinkAtom>,
+}
+
+impl DFTD3ONIOMDriver {
+    /// Create new ONIOM driver from real-system arrays (in Bohr).
+    ///
+    /// - `numbers` - element index (6 for O, 7 for N) in the real system
+    /// - `positions` - atomic positions in Bohr (natom * 3)
+    /// - `model_atoms` - indices of real-system atoms in the model region
+    /// - `link_atoms` - link atoms capping cut bonds
+    pub fn new(
+        numbers: &[usize],
+        positions: &[f64],
+        model_atoms: &[usize],
+        link_atoms: &[DFTD3LinkAtom],
+    ) -> Self {
+        Self::new_f(numbers, positions, model_atoms, link_atoms).unwrap()
+    }
+
+    /// Evaluate the ONIOM dispersion energy and optionally its gradient.
+    ///
+    /// `None` for `high` or `low` switches the dispersion correction off at
+    /// that level.
+    pub fn compute(
+        &self,
+        high: Option<&DFTD3Param>,
+        low: Option<&DFTD3Param>,
+        eval_grad: bool,
+    ) -> DFTD3ONIOMOutput {
+        self.compute_f(high, low, eval_grad).unwrap()
+    }
+
+    /// Update real-system coordinates (in Bohr).
+    ///
+    /// Link atoms follow the new geometry automatically.
+    pub fn update(&mut self, positions: &[f64]) {
+        self.update_f(positions).unwrap()
+    }
+
+    /// Get number of atoms in the model system, including link atoms.
+    pub fn get_natoms_model(&self) -> usize {
+        self.model_atoms.len() + self.link_atoms.len()
+    }
+
+    /// Atomic numbers and positions of the capped model system.
+    pub fn model_system(&self) -> (Vec<usize>, Vec<f64>) {
+        let (mut numbers, mut positions) =
+            take_atoms(&self.numbers, &self.positions, &self.model_atoms);
+        for link in &self.link_atoms {
+            numbers.push(link.element);
+            positions.extend(link.position(&self.positions));
+        }
+        (numbers, positions)
+    }
+
+    /// Create new ONIOM driver from real-system arrays (in Bohr, failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3ONIOMDriver::new`]
+    pub fn new_f(
+        numbers: &[usize],
+        positions: &[f64],
+        model_atoms: &[usize],
+        link_atoms: &[DFTD3LinkAtom],
+    ) -> Result<Self, DFTD3Error> {
+        check_structure_dims(numbers, positions, None, None)?;
+        let natoms = numbers.len();
+        check_atom_indices(model_atoms, natoms, "model region")?;
+        let mut in_model = vec![false; natoms];
+        for &i in model_atoms {
+            if in_model[i] {
+                return Err(DFTD3Error::Rust(format!("Atom {i} is duplicated in model region")));
+            }
+            in_model[i] = true;
+        }
+        for (ilink, link) in link_atoms.iter().enumerate() {
+            let what = format!("link atom {ilink}");
+            check_atom_indices(&[link.model_atom, link.real_atom], natoms, &what)?;
+            if !in_model[link.model_atom] {
+                return Err(DFTD3Error::Rust(format!(
+                    "Model atom {} of {what} is not in model region",
+                    link.model_atom
+                )));
+            }
+            if in_model[link.real_atom] {
+                return Err(DFTD3Error::Rust(format!(
+                    "Real atom {} of {what} should not be in model region",
+                    link.real_atom
+                )));
+            }
+            if link.scale <= 0.0 {
+                return Err(DFTD3Error::Rust(format!(
+                    "Scale factor of {what} should be positive, got {}",
+                    link.scale
+                )));
+            }
+        }
+        Ok(Self {
+            numbers: numbers.to_vec(),
+            positions: positions.to_vec(),
+            model_atoms: model_atoms.to_vec(),
+            link_atoms: link_atoms.to_vec(),
+        })
+    }
+
+    /// Evaluate the ONIOM dispersion energy and gradient (failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3ONIOMDriver::compute`]
+    pub fn compute_f(
+        &self,
+        high: Option<&DFTD3Param>,
+        low: Option<&DFTD3Param>,
+        eval_grad: bool,
+    ) -> Result<DFTD3ONIOMOutput, DFTD3Error> {
+        let natoms = self.numbers.len();
+        let mut grad = eval_grad.then(|| vec![0.0; 3 * natoms]);
+
+        // real system, low level
+        let mut energy_real_low = 0.0;
+        if let Some(low) = low {
+            let model = DFTD3Model::new_f(&self.numbers, &self.positions, None, None)?;
+            let res = model.get_dispersion_f(low, eval_grad)?;
+            energy_real_low = res.energy;
+            if let (Some(grad), Some(g)) = (grad.as_mut(), res.grad) {
+                grad.iter_mut().zip(g).for_each(|(x, g)| *x += g);
+            }
+        }
+
+        // model system, high and low level
+        let (model_numbers, model_positions) = self.model_system();
+        let mut energy_model = [0.0; 2];
+        for (ilevel, (param, factor)) in [(high, 1.0), (low, -1.0)].into_iter().enumerate() {
+            let Some(param) = param else { continue };
+            let model = DFTD3Model::new_f(&model_numbers, &model_positions, None, None)?;
+            let res = model.get_dispersion_f(param, eval_grad)?;
+            energy_model[ilevel] = res.energy;
+            if let (Some(grad), Some(g)) = (grad.as_mut(), res.grad) {
+                self.project_model_grad(grad, &g, factor);
+            }
+        }
+        let [energy_model_high, energy_model_low] = energy_model;
+
+        Ok(DFTD3ONIOMOutput {
+            energy: energy_real_low + energy_model_high - energy_model_low,
+            grad,
+            energy_real_low,
+            energy_model_high,
+            energy_model_low,
+        })
+    }
+
+    /// Update real-system coordinates (in Bohr, failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3ONIOMDriver::update`]
+    pub fn update_f(&mut self, positions: &[f64]) -> Result<(), DFTD3Error> {
+        check_structure_dims(&self.numbers, positions, None, None)?;
+        self.positions = positions.to_vec();
+        Ok(())
+    }
+
+    /// Accumulate `factor` times the model-system gradient onto real atoms,
+    /// projecting link-atom contributions onto both atoms of the cut bond.
+    fn project_model_grad(&self, grad: &mut [f64], model_grad: &[f64], factor: f64) {
+        for (imodel, &i) in self.model_atoms.iter().enumerate() {
+            for x in 0..3 {
+                grad[3 * i + x] += factor * model_grad[3 * imodel + x];
+            }
+        }
+        let offset = self.model_atoms.len();
+        for (ilink, link) in self.link_atoms.iter().enumerate() {
+            let (q, r, g) = (link.model_atom, link.real_atom, link.scale);
+            for x in 0..3 {
+                let gl = factor * model_grad[3 * (offset + ilink) + x];
+                grad[3 * q + x] += (1.0 - g) * gl;
+                grad[3 * r + x] += g * gl;
+            }
+        }
+    }
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_link_atom_projection() {
+        let numbers = vec![6, 6, 1];
+        let positions = vec![0.0, 0.0, 0.0, 3.0, 0.0, 0.0, -2.0, 0.0, 0.0];
+        let links = vec![DFTD3LinkAtom::new(0, 1, 0.7)];
+        let driver = DFTD3ONIOMDriver::new(&numbers, &positions, &[0, 2], &links);
+
+        let (model_numbers, model_positions) = driver.model_system();
+        assert_eq!(model_numbers, vec![6, 1, 1]);
+        assert!((model_positions[6] - 2.1).abs() < 1e-12);
+
+        // unit force on link atom splits by (1 - g) and g
+        let mut grad = vec![0.0; 9];
+        driver.project_model_grad(&mut grad, &[0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0], 1.0);
+        assert!((grad[0] - 0.3).abs() < 1e-12);
+        assert!((grad[3] - 0.7).abs() < 1e-12);
+        assert_eq!(grad[6], 0.0);
+
+        // real atom of link should not be inside model region
+        assert!(DFTD3ONIOMDriver::new_f(&numbers, &positions, &[0, 1], &links).is_err());
+    }
+}
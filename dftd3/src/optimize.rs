@@ -0,0 +1,645 @@
+//! D3-only geometry relaxation, with optional cell relaxation.
+//!
+//! Atomic positions are relaxed with L-BFGS or FIRE on the D3 dispersion
+//! energy. An extra energy and gradient callback (for example a cheap force
+//! field or a QM surrogate) can be added on top of D3.
+//!
+//! For periodic models, the cell can be relaxed together with the positions
+//! from the strain derivatives `sigma`. The cell is parametrized by a
+//! deformation `D = I + eps` of the initial lattice, and positions by their
+//! undeformed coordinates `x = D^-1 r`, so that
+//!
+//! ```text
+//! dE/dx   = D^T dE/dr
+//! dE/deps = sigma D^-T
+//! ```
+//!
+//! Frozen atoms keep their undeformed coordinates, i.e. they follow the cell
+//! deformation but do not move otherwise.
+//!
+//! Convergence requires both the largest atomic gradient norm (including rows
+//! of the strain gradient for cell relaxation) to be below `grad_tol`, and the
+//! energy change of the last step to be below `energy_tol`.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use dftd3::prelude::*;
+//!
+//! # let numbers: Vec<usize> = vec![];
+//! # let positions: Vec<f64> = vec![];
+//! let param = dftd3_load_param("d3bj", "pbe0", true);
+//! let options = DFTD3OptimizeOptionsBuilder::default()
+//!     .algorithm(DFTD3OptimizeAlgorithm::FIRE)
+//!     .frozen(vec![0, 1])
+//!     .trajectory("relax.xyz")
+//!     .build()
+//!     .unwrap();
+//! let optimizer = DFTD3Optimizer::new(&numbers, &positions, None, None, options);
+//!
+//! // D3 only
+//! let res = optimizer.run(&param);
+//! println!("converged: {}, energy: {}", res.converged, res.energy);
+//!
+//! // D3 and a harmonic restraint of all atoms to the origin
+//! let res = optimizer.run_with(&param, |positions, _lattice| {
+//!     let energy = 0.5e-3 * positions.iter().map(|x| x * x).sum::<f64>();
+//!     let grad = positions.iter().map(|x| 1e-3 * x).collect();
+//!     Ok(DFTD3Output { energy, grad: Some(grad), sigma: None })
+//! });
+//! ```
+
+use crate::elements::*;
+use crate::interface::*;
+use crate::utils::*;
+use derive_builder::Builder;
+use std::collections::VecDeque;
+use std::io::Write;
+
+/// Initial time step of FIRE (atomic units, unit masses).
+const FIRE_DT_START: f64 = 1.0;
+/// Maximum time step of FIRE.
+const FIRE_DT_MAX: f64 = 10.0;
+
+/// Extra energy and gradient callback: takes positions (natom * 3) and
+/// optional lattice (3 * 3) in Bohr, returns energy, gradient and (for cell
+/// relaxation) strain derivatives.
+type ExtraCallback<'a, 'b> =
+    &'a mut (dyn FnMut(&[f64], Option<&[f64]>) -> Result<DFTD3Output, DFTD3Error> + 'b);
+
+/* #region Optimizer options */
+
+/// Relaxation algorithm.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DFTD3OptimizeAlgorithm {
+    /// Limited-memory BFGS, with step rejection on energy increase.
+    #[default]
+    LBFGS,
+    /// Fast inertial relaxation engine.
+    FIRE,
+}
+
+/// Options for geometry relaxation (quantities in atomic units).
+#[derive(Builder, Debug, Clone)]
+#[builder(pattern = "owned", build_fn(error = "DFTD3Error"))]
+pub struct DFTD3OptimizeOptions {
+    #[builder(default)]
+    #[doc = r"optional, default L-BFGS; relaxation algorithm"]
+    pub algorithm: DFTD3OptimizeAlgorithm,
+    #[builder(default = 500)]
+    #[doc = r"optional, default 500; maximum number of steps"]
+    pub max_steps: usize,
+    #[builder(default = 4.5e-4)]
+    #[doc = r"optional, default 4.5e-4; convergence threshold of largest atomic gradient norm"]
+    pub grad_tol: f64,
+    #[builder(default = 1.0e-6)]
+    #[doc = r"optional, default 1.0e-6; convergence threshold of energy change"]
+    pub energy_tol: f64,
+    #[builder(default = 0.2)]
+    #[doc = r"optional, default 0.2; largest atomic displacement per step"]
+    pub max_step: f64,
+    #[builder(default = 10)]
+    #[doc = r"optional, default 10; number of L-BFGS correction pairs"]
+    pub memory: usize,
+    #[builder(default)]
+    #[doc = r"optional, default empty; indices of frozen atoms"]
+    pub frozen: Vec<usize>,
+    #[builder(default = false)]
+    #[doc = r"optional, default false; relax lattice of periodic models"]
+    pub relax_cell: bool,
+    #[builder(default, setter(into, strip_option))]
+    #[doc = r"optional; extended xyz trajectory file (positions in Angstrom, energy in Hartree)"]
+    pub trajectory: Option<String>,
+}
+
+impl Default for DFTD3OptimizeOptions {
+    fn default() -> Self {
+        DFTD3OptimizeOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/* #endregion */
+
+/* #region Optimizer output */
+
+/// Geometry relaxation result.
+#[derive(Debug, Clone)]
+pub struct DFTD3OptimizeOutput {
+    /// Relaxed atomic positions in Bohr (natom * 3).
+    pub positions: Vec<f64>,
+    /// Relaxed lattice in Bohr (3 * 3), if periodic.
+    pub lattice: Option<Vec<f64>>,
+    /// Final energy (D3 and extra callback).
+    pub energy: f64,
+    /// Final Cartesian gradient (natom * 3).
+    pub grad: Vec<f64>,
+    /// Final strain derivatives (3 * 3), if the cell is relaxed.
+    pub sigma: Option<Vec<f64>>,
+    /// Whether convergence criteria are met.
+    pub converged: bool,
+    /// Number of steps taken.
+    pub nsteps: usize,
+    /// Energies of the initial and every accepted structure.
+    pub energies: Vec<f64>,
+}
+
+/* #endregion */
+
+/* #region Optimizer */
+
+/// Geometry relaxation driver.
+pub struct DFTD3Optimizer {
+    numbers: Vec<usize>,
+    positions: Vec<f64>,
+    lattice: Option<Vec<f64>>,
+    periodic: Option<Vec<bool>>,
+    options: DFTD3OptimizeOptions,
+}
+
+/// Structure and gradients at one point of the relaxation.
+struct EvalPoint {
+    energy: f64,
+    /// Gradient with respect to optimization variables.
+    grad_x: Vec<f64>,
+    positions: Vec<f64>,
+    lattice: Option<Vec<f64>>,
+    grad: Vec<f64>,
+    sigma: Option<Vec<f64>>,
+}
+
+impl DFTD3Optimizer {
+    /// Create new relaxation driver.
+    ///
+    /// - `numbers` - element index (6 for O, 7 for N) of the structure
+    /// - `positions` - atomic positions in Bohr (natom * 3)
+    /// - `lattice` - optional, lattice parameters (3 * 3)
+    /// - `periodic` - optional, periodicity (3)
+    /// - `options` - algorithm, convergence criteria, frozen atoms and
+    ///   trajectory
+    pub fn new(
+        numbers: &[usize],
+        positions: &[f64],
+        lattice: Option<&[f64]>,
+        periodic: Option<&[bool]>,
+        options: DFTD3OptimizeOptions,
+    ) -> Self {
+        Self::new_f(numbers, positions, lattice, periodic, options).unwrap()
+    }
+
+    /// Relax with D3 dispersion only.
+    pub fn run(&self, param: &DFTD3Param) -> DFTD3OptimizeOutput {
+        self.run_f(param).unwrap()
+    }
+
+    /// Relax with D3 dispersion and an extra energy and gradient callback.
+    ///
+    /// The callback takes positions (natom * 3) and optional lattice (3 * 3)
+    /// in Bohr. It must return the gradient, and for cell relaxation also the
+    /// strain derivatives.
+    pub fn run_with<F>(&self, param: &DFTD3Param, callback: F) -> DFTD3OptimizeOutput
+    where
+        F: FnMut(&[f64], Option<&[f64]>) -> Result<DFTD3Output, DFTD3Error>,
+    {
+        self.run_with_f(param, callback).unwrap()
+    }
+
+    /// Create new relaxation driver (failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3Optimizer::new`]
+    pub fn new_f(
+        numbers: &[usize],
+        positions: &[f64],
+        lattice: Option<&[f64]>,
+        periodic: Option<&[bool]>,
+        options: DFTD3OptimizeOptions,
+    ) -> Result<Self, DFTD3Error> {
+        check_structure_dims(numbers, positions, lattice, periodic)?;
+        check_atom_indices(&options.frozen, numbers.len(), "frozen atoms")?;
+        if options.relax_cell && lattice.is_none() {
+            return Err(DFTD3Error::Rust("Cell relaxation requires a lattice".into()));
+        }
+        if options.max_step <= 0.0 {
+            return Err(DFTD3Error::Rust(format!(
+                "Maximum step should be positive, got {}",
+                options.max_step
+            )));
+        }
+        Ok(Self {
+            numbers: numbers.to_vec(),
+            positions: positions.to_vec(),
+            lattice: lattice.map(|l| l.to_vec()),
+            periodic: periodic.map(|p| p.to_vec()),
+            options,
+        })
+    }
+
+    /// Relax with D3 dispersion only (failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3Optimizer::run`]
+    pub fn run_f(&self, param: &DFTD3Param) -> Result<DFTD3OptimizeOutput, DFTD3Error> {
+        self.run_impl(param, None)
+    }
+
+    /// Relax with D3 dispersion and an extra energy and gradient callback
+    /// (failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3Optimizer::run_with`]
+    pub fn run_with_f<F>(
+        &self,
+        param: &DFTD3Param,
+        mut callback: F,
+    ) -> Result<DFTD3OptimizeOutput, DFTD3Error>
+    where
+        F: FnMut(&[f64], Option<&[f64]>) -> Result<DFTD3Output, DFTD3Error>,
+    {
+        self.run_impl(param, Some(&mut callback))
+    }
+
+    fn run_impl(
+        &self,
+        param: &DFTD3Param,
+        mut callback: Option<ExtraCallback<'_, '_>>,
+    ) -> Result<DFTD3OptimizeOutput, DFTD3Error> {
+        let opts = &self.options;
+        let mut model = DFTD3Model::new_f(
+            &self.numbers,
+            &self.positions,
+            self.lattice.as_deref(),
+            self.periodic.as_deref(),
+        )?;
+        let mut trajectory = match &opts.trajectory {
+            Some(path) => Some(std::io::BufWriter::new(
+                std::fs::File::create(path).map_err(|e| io_error(path, e))?,
+            )),
+            None => None,
+        };
+
+        let mut evaluate = |x: &[f64]| self.evaluate(&mut model, param, callback.as_deref_mut(), x);
+        let mut record = |point: &EvalPoint| -> Result<(), DFTD3Error> {
+            if let (Some(writer), Some(path)) = (trajectory.as_mut(), &opts.trajectory) {
+                self.write_frame(writer, point).map_err(|e| io_error(path, e))?;
+            }
+            Ok(())
+        };
+
+        // optimization variables: undeformed positions, then scaled strain
+        let mut x = self.positions.clone();
+        if opts.relax_cell {
+            x.extend([0.0; 9]);
+        }
+        let (point, converged, nsteps, energies) = minimize(opts, x, &mut evaluate, &mut record)?;
+
+        if let (Some(mut writer), Some(path)) = (trajectory, &opts.trajectory) {
+            writer.flush().map_err(|e| io_error(path, e))?;
+        }
+        Ok(DFTD3OptimizeOutput {
+            positions: point.positions,
+            lattice: point.lattice,
+            energy: point.energy,
+            grad: point.grad,
+            sigma: point.sigma.filter(|_| opts.relax_cell),
+            converged,
+            nsteps,
+            energies,
+        })
+    }
+
+    /// Evaluate energy and gradients at optimization variables `x`.
+    fn evaluate(
+        &self,
+        model: &mut DFTD3Model,
+        param: &DFTD3Param,
+        callback: Option<ExtraCallback<'_, '_>>,
+        x: &[f64],
+    ) -> Result<EvalPoint, DFTD3Error> {
+        let natoms = self.numbers.len();
+        let relax_cell = self.options.relax_cell;
+
+        // deformation D = I + eps, strain variables are scaled by natoms
+        let mut deform = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+        if relax_cell {
+            deform.iter_mut().zip(&x[3 * natoms..]).for_each(|(d, e)| *d += e / natoms as f64);
+        }
+        let apply = |v: &[f64]| -> Vec<f64> {
+            v.chunks(3)
+                .flat_map(|r| (0..3).map(move |i| (0..3).map(|j| deform[3 * i + j] * r[j]).sum()))
+                .collect()
+        };
+        let positions = apply(&x[..3 * natoms]);
+        let lattice = self.lattice.as_ref().map(|l| if relax_cell { apply(l) } else { l.clone() });
+
+        model.update_f(&positions, lattice.as_deref())?;
+        let res = model.get_dispersion_f(param, true)?;
+        let (mut energy, mut grad, mut sigma) = (res.energy, res.grad.unwrap(), res.sigma);
+        if let Some(callback) = callback {
+            let extra = callback(&positions, lattice.as_deref())?;
+            let extra_grad = extra.grad.ok_or_else(|| {
+                DFTD3Error::Rust("Extra energy callback should return gradient".into())
+            })?;
+            if extra_grad.len() != 3 * natoms {
+                return Err(DFTD3Error::Rust(format!(
+                    "Invalid dimension for extra gradient, expected {}, got {}",
+                    3 * natoms,
+                    extra_grad.len()
+                )));
+            }
+            energy += extra.energy;
+            grad.iter_mut().zip(extra_grad).for_each(|(g, e)| *g += e);
+            if relax_cell {
+                let extra_sigma = extra.sigma.ok_or_else(|| {
+                    DFTD3Error::Rust(
+                        "Extra energy callback should return strain derivatives".into(),
+                    )
+                })?;
+                if let Some(sigma) = sigma.as_mut() {
+                    sigma.iter_mut().zip(extra_sigma).for_each(|(s, e)| *s += e);
+                }
+            }
+        }
+
+        // gradient with respect to optimization variables
+        let mut grad_x: Vec<f64> = grad
+            .chunks(3)
+            .flat_map(|g| (0..3).map(move |j| (0..3).map(|i| deform[3 * i + j] * g[i]).sum()))
+            .collect();
+        for &i in &self.options.frozen {
+            grad_x[3 * i..3 * i + 3].fill(0.0);
+        }
+        if relax_cell {
+            let sigma = sigma.as_ref().unwrap();
+            let inv = inverse3(&deform)?;
+            for i in 0..3 {
+                for j in 0..3 {
+                    let g: f64 = (0..3).map(|k| sigma[3 * i + k] * inv[3 * j + k]).sum();
+                    grad_x.push(g / natoms as f64);
+                }
+            }
+        }
+
+        Ok(EvalPoint { energy, grad_x, positions, lattice, grad, sigma })
+    }
+
+    /// Append one frame to an extended xyz trajectory.
+    fn write_frame<W: Write>(&self, writer: &mut W, point: &EvalPoint) -> std::io::Result<()> {
+        writeln!(writer, "{}", self.numbers.len())?;
+        let mut comment = String::new();
+        if let Some(lattice) = &point.lattice {
+            let lattice: Vec<String> =
+                lattice.iter().map(|x| format!("{:.8}", x * BOHR_TO_ANGSTROM)).collect();
+            let pbc: Vec<&str> = match &self.periodic {
+                Some(p) => p.iter().map(|&p| if p { "T" } else { "F" }).collect(),
+                None => vec!["T"; 3],
+            };
+            comment += &format!("Lattice=\"{}\" pbc=\"{}\" ", lattice.join(" "), pbc.join(" "));
+        }
+        comment += &format!("Properties=species:S:1:pos:R:3 energy={:.12}", point.energy);
+        writeln!(writer, "{comment}")?;
+        for (&z, r) in self.numbers.iter().zip(point.positions.chunks(3)) {
+            let [x, y, w] = [r[0], r[1], r[2]].map(|x| x * BOHR_TO_ANGSTROM);
+            writeln!(writer, "{:<3} {x:16.8} {y:16.8} {w:16.8}", symbol(z))?;
+        }
+        Ok(())
+    }
+}
+
+/* #endregion */
+
+/* #region Relaxation loop */
+
+/// Relaxation loop over optimization variables `x`, returns the final point,
+/// whether converged, the number of steps and the energies of the initial and
+/// every accepted point.
+fn minimize(
+    opts: &DFTD3OptimizeOptions,
+    mut x: Vec<f64>,
+    evaluate: &mut dyn FnMut(&[f64]) -> Result<EvalPoint, DFTD3Error>,
+    record: &mut dyn FnMut(&EvalPoint) -> Result<(), DFTD3Error>,
+) -> Result<(EvalPoint, bool, usize, Vec<f64>), DFTD3Error> {
+    let mut point = evaluate(&x)?;
+    record(&point)?;
+    let mut energies = vec![point.energy];
+    let mut delta_e = 0.0_f64;
+    let mut converged = false;
+    let mut nsteps = 0;
+
+    // L-BFGS state
+    let mut history: VecDeque<(Vec<f64>, Vec<f64>, f64)> = VecDeque::new();
+    let mut trust = opts.max_step;
+    // FIRE state
+    let mut velocity = vec![0.0; x.len()];
+    let (mut dt, mut alpha, mut npositive) = (FIRE_DT_START, 0.1, 0);
+
+    let is_converged = |point: &EvalPoint, delta_e: f64| {
+        max_row_norm(&point.grad_x) < opts.grad_tol && delta_e.abs() < opts.energy_tol
+    };
+    while nsteps < opts.max_steps {
+        if is_converged(&point, delta_e) {
+            converged = true;
+            break;
+        }
+        nsteps += 1;
+
+        let step = match opts.algorithm {
+            DFTD3OptimizeAlgorithm::LBFGS => {
+                let mut d = lbfgs_direction(&history, &point.grad_x);
+                if history.is_empty() || dot(&d, &point.grad_x) >= 0.0 {
+                    history.clear();
+                    d = point.grad_x.iter().map(|g| -g).collect();
+                    scale_to(&mut d, trust);
+                }
+                d
+            },
+            DFTD3OptimizeAlgorithm::FIRE => {
+                let force: Vec<f64> = point.grad_x.iter().map(|g| -g).collect();
+                if dot(&force, &velocity) > 0.0 {
+                    let (vnorm, fnorm) = (norm(&velocity), norm(&force));
+                    velocity
+                        .iter_mut()
+                        .zip(&force)
+                        .for_each(|(v, f)| *v = (1.0 - alpha) * *v + alpha * vnorm * f / fnorm);
+                    if npositive > 5 {
+                        dt = (dt * 1.1).min(FIRE_DT_MAX);
+                        alpha *= 0.99;
+                    }
+                    npositive += 1;
+                } else {
+                    velocity.iter_mut().for_each(|v| *v = 0.0);
+                    (dt, alpha, npositive) = (dt * 0.5, 0.1, 0);
+                }
+                velocity.iter_mut().zip(&force).for_each(|(v, f)| *v += dt * f);
+                velocity.iter().map(|v| dt * v).collect()
+            },
+        };
+        let mut step = step;
+        if max_row_norm(&step) > trust {
+            scale_to(&mut step, trust);
+        }
+
+        let x_new: Vec<f64> = x.iter().zip(&step).map(|(x, s)| x + s).collect();
+        let new_point = evaluate(&x_new)?;
+
+        if opts.algorithm == DFTD3OptimizeAlgorithm::LBFGS {
+            if new_point.energy > point.energy {
+                // reject step, shrink trust radius and restart from steepest descent
+                history.clear();
+                trust *= 0.5;
+                if trust < 1e-8 {
+                    break;
+                }
+                continue;
+            }
+            trust = (trust * 2.0).min(opts.max_step);
+            let y: Vec<f64> =
+                new_point.grad_x.iter().zip(&point.grad_x).map(|(a, b)| a - b).collect();
+            let sy = dot(&step, &y);
+            if sy > 1e-16 {
+                history.push_back((step, y, 1.0 / sy));
+                if history.len() > opts.memory {
+                    history.pop_front();
+                }
+            }
+        }
+
+        record(&new_point)?;
+        delta_e = new_point.energy - point.energy;
+        energies.push(new_point.energy);
+        (x, point) = (x_new, new_point);
+    }
+    // the last allowed step may have converged
+    converged = converged || is_converged(&point, delta_e);
+    Ok((point, converged, nsteps, energies))
+}
+
+/* #endregion */
+
+/* #region Vector utilities */
+
+fn io_error(path: &str, err: std::io::Error) -> DFTD3Error {
+    DFTD3Error::Rust(format!("Failed to write trajectory {path}: {err}"))
+}
+
+fn dot(a: &[f64], b: &[f64]) -> f64 {
+    a.iter().zip(b).map(|(a, b)| a * b).sum()
+}
+
+fn norm(a: &[f64]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+/// Largest norm of 3-vectors (atoms, or rows of the strain gradient).
+fn max_row_norm(a: &[f64]) -> f64 {
+    a.chunks(3).map(norm).fold(0.0, f64::max)
+}
+
+/// Scale vector so that its largest row norm equals `length`.
+fn scale_to(a: &mut [f64], length: f64) {
+    let max = max_row_norm(a);
+    if max > 0.0 {
+        a.iter_mut().for_each(|x| *x *= length / max);
+    }
+}
+
+/// L-BFGS two-loop recursion, returns the search direction `-H g`.
+fn lbfgs_direction(history: &VecDeque<(Vec<f64>, Vec<f64>, f64)>, grad: &[f64]) -> Vec<f64> {
+    let mut q = grad.to_vec();
+    let mut alphas = vec![0.0; history.len()];
+    for (k, (s, y, rho)) in history.iter().enumerate().rev() {
+        alphas[k] = rho * dot(s, &q);
+        q.iter_mut().zip(y).for_each(|(q, y)| *q -= alphas[k] * y);
+    }
+    if let Some((s, y, _)) = history.back() {
+        let gamma = dot(s, y) / dot(y, y);
+        q.iter_mut().for_each(|q| *q *= gamma);
+    }
+    for (k, (s, y, rho)) in history.iter().enumerate() {
+        let beta = rho * dot(y, &q);
+        q.iter_mut().zip(s).for_each(|(q, s)| *q += (alphas[k] - beta) * s);
+    }
+    q.iter_mut().for_each(|q| *q = -*q);
+    q
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lbfgs_direction() {
+        // quadratic E = 1/2 x^T A x with A = diag(1, 4, 9); exact curvature pairs
+        // along all axes recover the Newton step
+        let diag = [1.0, 4.0, 9.0];
+        let mut history = VecDeque::new();
+        for i in 0..3 {
+            let mut s = vec![0.0; 3];
+            s[i] = 1.0;
+            let y: Vec<f64> = s.iter().zip(diag).map(|(s, a)| s * a).collect();
+            let rho = 1.0 / dot(&s, &y);
+            history.push_back((s, y, rho));
+        }
+        let grad = [1.0, 4.0, 9.0];
+        let d = lbfgs_direction(&history, &grad);
+        d.iter().for_each(|d| assert!((d + 1.0).abs() < 1e-12));
+
+        let mut v = vec![3.0, 4.0, 0.0, 0.0, 0.0, 1.0];
+        scale_to(&mut v, 1.0);
+        assert!((max_row_norm(&v) - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_minimize_quadratic() {
+        // E = 1/2 sum_i k_i (x_i - c_i)^2 of two atoms, minimum at c
+        let k = [0.5, 1.0, 2.0, 0.3, 0.8, 1.5];
+        let c = [0.1, -0.2, 0.3, 1.5, 0.0, -0.4];
+        let mut evaluate = |x: &[f64]| -> Result<EvalPoint, DFTD3Error> {
+            let grad: Vec<f64> = (0..6).map(|i| k[i] * (x[i] - c[i])).collect();
+            let energy = (0..6).map(|i| 0.5 * k[i] * (x[i] - c[i]).powi(2)).sum();
+            let positions = x.to_vec();
+            Ok(EvalPoint {
+                energy,
+                grad_x: grad.clone(),
+                positions,
+                lattice: None,
+                grad,
+                sigma: None,
+            })
+        };
+        let mut record = |_: &EvalPoint| Ok(());
+        let x0 = vec![0.0, 0.0, 0.0, 3.0, 1.0, 0.0];
+        let energy0 = evaluate(&x0).unwrap().energy;
+
+        for algorithm in [DFTD3OptimizeAlgorithm::LBFGS, DFTD3OptimizeAlgorithm::FIRE] {
+            let opts = DFTD3OptimizeOptionsBuilder::default()
+                .algorithm(algorithm)
+                .grad_tol(1e-6)
+                .energy_tol(1e-10)
+                .build()
+                .unwrap();
+            let (point, converged, nsteps, energies) =
+                minimize(&opts, x0.clone(), &mut evaluate, &mut record).unwrap();
+            assert!(converged, "{algorithm:?}");
+            assert!(point.energy < energy0 && point.energy < 1e-10, "{algorithm:?}");
+            if algorithm == DFTD3OptimizeAlgorithm::LBFGS {
+                // accepted steps never increase the energy
+                assert!(energies.windows(2).all(|e| e[1] <= e[0]));
+            }
+
+            // converging on the last allowed step is reported as converged
+            let opts = DFTD3OptimizeOptions { max_steps: nsteps, ..opts };
+            let (_, converged, _, _) =
+                minimize(&opts, x0.clone(), &mut evaluate, &mut record).unwrap();
+            assert!(converged, "{algorithm:?}");
+            let opts = DFTD3OptimizeOptions { max_steps: nsteps - 1, ..opts };
+            let (_, converged, _, _) =
+                minimize(&opts, x0.clone(), &mut evaluate, &mut record).unwrap();
+            assert!(!converged, "{algorithm:?}");
+        }
+    }
+}
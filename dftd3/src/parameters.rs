@@ -4,21 +4,42 @@
 //! embedded TOML database. Unlike the FFI-based parameter loading
 //! (`DFTD3Param`), this module exposes the actual parameter values and allows
 //! inspection of damping parameters for various XC functionals.
+//!
+//! The embedded database is parsed once and indexed by normalized method names
+//! in [`ParameterDatabase`]. User parameter files in the same format can be
+//! layered over it, from the `DFTD3_PARAM_PATH` environment variable (read on
+//! first lookup) and [`dftd3_load_param_files`]; later files take precedence,
+//! and replaced entries are reported as [`ParameterConflict`]. The free
+//! functions of this module, and parsing in [`crate::parsing`], query this
+//! merged global database ([`ParameterDatabase::global`]).
+//!
+//! Methods may carry metadata ([`MethodMetadata`]: display name, aliases,
+//! functional family, range separation and notes) in an optional `meta` table,
+//! which can be queried by [`ParameterDatabase::find_methods`].
 
+use crate::citation::DFTD3Reference;
 use crate::interface::*;
-use serde::Deserialize;
-use std::collections::HashMap;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{OnceLock, RwLock, RwLockReadGuard, RwLockWriteGuard};
 use toml::Table;
 
 // Embed TOML at compile time
 const PARAMETERS_TOML: &str = include_str!("parameters.toml");
 
+/// Normalized DFT-D3 variants of the database, scanned by database drivers.
+pub(crate) const DATABASE_VARIANTS: &[&str] = &["bj", "zero", "bjm", "zerom", "op", "cso"];
+
 /* #region TOML data structures */
 
 /// D3 variants under a method (e.g., d3.bj, d3.zero under [parameter.b3lyp]).
 /// TOML creates nested structure: parameter.b3lyp.d3.bj
 #[derive(Debug, Clone, Deserialize, Default)]
 struct D3Variants {
+    #[serde(default)]
+    meta: Option<MethodMetadata>,
+    #[serde(default)]
     d3: D3MethodParams,
 }
 
@@ -36,11 +57,30 @@ struct D3MethodParams {
     cso: Option<Table>,
 }
 
+impl D3MethodParams {
+    /// Present variant tables by normalized variant.
+    fn into_tables(self) -> BTreeMap<String, Table> {
+        [
+            ("bj", self.bj),
+            ("zero", self.zero),
+            ("bjm", self.bjm),
+            ("zerom", self.zerom),
+            ("op", self.op),
+            ("cso", self.cso),
+        ]
+        .into_iter()
+        .filter_map(|(version, table)| Some((version.to_string(), table?)))
+        .collect()
+    }
+}
+
 /// Full TOML structure.
 #[derive(Debug, Clone, Deserialize)]
 struct ParameterDataBase {
     default: DefaultSection,
     parameter: HashMap<String, D3Variants>,
+    #[serde(default)]
+    citation: BTreeMap<String, CitationEntry>,
 }
 
 /// Default section with default damping types and base parameters.
@@ -48,7 +88,6 @@ struct ParameterDataBase {
 /// structure.
 #[derive(Debug, Clone, Deserialize)]
 struct DefaultSection {
-    #[allow(dead_code)]
     d3: Vec<String>,
     parameter: DefaultParameterSection,
 }
@@ -74,12 +113,126 @@ struct D3DefaultParams {
     cso: Option<Table>,
 }
 
+/// User parameter file layered over the database; all sections are optional.
+#[derive(Debug, Clone, Deserialize, Default)]
+struct ParameterDataBaseLayer {
+    #[serde(default)]
+    default: Option<DefaultSectionLayer>,
+    #[serde(default)]
+    parameter: HashMap<String, D3Variants>,
+    #[serde(default)]
+    citation: BTreeMap<String, CitationEntry>,
+}
+
+/// Reference of a DOI (`[citation."<doi>"]`); authors are given as "First
+/// Last".
+#[derive(Debug, Clone, Deserialize, Default)]
+struct CitationEntry {
+    title: Option<String>,
+    #[serde(default)]
+    author: Vec<String>,
+    journal: Option<String>,
+    volume: Option<String>,
+    issue: Option<String>,
+    pages: Option<String>,
+    year: Option<String>,
+}
+
+/// Optional `[default]` section of a user parameter file.
+#[derive(Debug, Clone, Deserialize, Default)]
+struct DefaultSectionLayer {
+    #[serde(default)]
+    d3: Option<Vec<String>>,
+    #[serde(default)]
+    parameter: Option<DefaultParameterSectionLayer>,
+}
+
+/// Optional `[default.parameter]` section of a user parameter file.
+#[derive(Debug, Clone, Deserialize, Default)]
+struct DefaultParameterSectionLayer {
+    d3: D3MethodParams,
+}
+
+/* #endregion */
+
+/* #region Method metadata */
+
+/// Functional family of a method.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FunctionalFamily {
+    /// Local density approximation.
+    Lda,
+    /// Generalized gradient approximation.
+    Gga,
+    /// Meta-GGA.
+    MetaGga,
+    /// Hybrid functional (global or range-separated).
+    Hybrid,
+    /// Double hybrid functional.
+    DoubleHybrid,
+    /// Hartree-Fock.
+    Hf,
+    /// Composite method (e.g. HF-3c, B97-3c) or other electronic structure
+    /// method.
+    Composite,
+}
+
+impl std::fmt::Display for FunctionalFamily {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            FunctionalFamily::Lda => "LDA",
+            FunctionalFamily::Gga => "GGA",
+            FunctionalFamily::MetaGga => "meta-GGA",
+            FunctionalFamily::Hybrid => "hybrid",
+            FunctionalFamily::DoubleHybrid => "double hybrid",
+            FunctionalFamily::Hf => "HF",
+            FunctionalFamily::Composite => "composite",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// Metadata of a method (`meta` table of a `[parameter.<method>]` entry).
+///
+/// ```text
+/// [parameter.wb97x]
+/// meta = {name="wB97X", aliases=["ωB97X"], family="hybrid", range_separated=true}
+/// ```
+///
+/// All fields are optional in TOML; entries without `meta` have no metadata.
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct MethodMetadata {
+    /// Display name, e.g. "M06-2X" for `m062x`.
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Alternative names; resolved as aliases of the method.
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Functional family.
+    #[serde(default)]
+    pub family: Option<FunctionalFamily>,
+    /// Whether the functional is range-separated (screened or long-range
+    /// corrected exchange).
+    #[serde(default)]
+    pub range_separated: bool,
+    /// Free-form notes.
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
 /* #endregion */
 
 /* #region Public parameter structs */
 
 /// Damping parameters with actual values exposed, plus optional metadata like
 /// DOI. This wraps a damping-type-specific enum and provides DOI reference.
+///
+/// Serializes to the explicit form of the parsing format, tagged by
+/// `version` (`{version = "bj", a1 = ..., a2 = ..., alp = ..., s6 = ..., s8 =
+/// ..., s9 = ..., doi = ...}`); deserializes from any input accepted by
+/// [`dftd3_parse_damping_param_f`](crate::parsing::dftd3_parse_damping_param_f),
+/// including method lookups.
 #[derive(Debug, Clone)]
 pub struct DFTD3DampingParam {
     /// The actual damping parameters (variant-specific)
@@ -90,6 +243,8 @@ pub struct DFTD3DampingParam {
 
 /// Enum holding variant-specific damping parameters.
 /// Each variant uses the corresponding struct from interface.rs.
+///
+/// Serde format as [`DFTD3DampingParam`], without `doi`.
 #[derive(Debug, Clone)]
 pub enum DFTD3DampingParamEnum {
     #[cfg(feature = "api-v0_4")]
@@ -178,6 +333,103 @@ impl DFTD3DampingParamEnum {
             DFTD3DampingParamEnum::CSO(_) => None, // CSO doesn't have s8
         }
     }
+
+    /// Get DFT-D3 variant name (`bj`, `zero`, `bjm`, `zerom`, `op` or `cso`).
+    pub fn version(&self) -> &'static str {
+        match self {
+            #[cfg(feature = "api-v0_4")]
+            DFTD3DampingParamEnum::Rational(_) => "bj",
+            #[cfg(feature = "api-v0_4")]
+            DFTD3DampingParamEnum::Zero(_) => "zero",
+            #[cfg(feature = "api-v0_4")]
+            DFTD3DampingParamEnum::ModifiedRational(_) => "bjm",
+            #[cfg(feature = "api-v0_4")]
+            DFTD3DampingParamEnum::ModifiedZero(_) => "zerom",
+            #[cfg(feature = "api-v0_5")]
+            DFTD3DampingParamEnum::OptimizedPower(_) => "op",
+            #[cfg(feature = "api-v1_3")]
+            DFTD3DampingParamEnum::CSO(_) => "cso",
+        }
+    }
+
+    /// Get all parameter values as a TOML table (field name to value).
+    pub fn to_table(&self) -> Table {
+        let table = match self {
+            #[cfg(feature = "api-v0_4")]
+            DFTD3DampingParamEnum::Rational(data) => Table::try_from(data),
+            #[cfg(feature = "api-v0_4")]
+            DFTD3DampingParamEnum::Zero(data) => Table::try_from(data),
+            #[cfg(feature = "api-v0_4")]
+            DFTD3DampingParamEnum::ModifiedRational(data) => Table::try_from(data),
+            #[cfg(feature = "api-v0_4")]
+            DFTD3DampingParamEnum::ModifiedZero(data) => Table::try_from(data),
+            #[cfg(feature = "api-v0_5")]
+            DFTD3DampingParamEnum::OptimizedPower(data) => Table::try_from(data),
+            #[cfg(feature = "api-v1_3")]
+            DFTD3DampingParamEnum::CSO(data) => Table::try_from(data),
+        };
+        // plain structs of floats always serialize
+        table.unwrap()
+    }
+
+    /// Get value of a parameter field by name (e.g. `a1`, `rs6`), if present
+    /// in this variant.
+    pub fn get_field(&self, field: &str) -> Option<f64> {
+        self.to_table().get(field).and_then(|v| v.as_float())
+    }
+
+    /// Set value of a parameter field by name (e.g. `a1`, `rs6`).
+    ///
+    /// Returns an error if the field is not present in this variant.
+    pub fn set_field(&mut self, field: &str, value: f64) -> Result<(), DFTD3Error> {
+        let mut table = self.to_table();
+        if !table.contains_key(field) {
+            return Err(DFTD3Error::ParametersError(format!(
+                "Unknown parameter '{field}' for variant '{}'",
+                self.version()
+            )));
+        }
+        table.insert(field.to_string(), toml::Value::Float(value));
+        *self = convert_to_damping_param(&table, self.version())?.param;
+        Ok(())
+    }
+}
+
+impl DFTD3DampingParam {
+    /// Get DFT-D3 variant name (`bj`, `zero`, `bjm`, `zerom`, `op` or `cso`).
+    pub fn version(&self) -> &'static str {
+        self.param.version()
+    }
+
+    /// Format as an entry of the parameter database, in the same format as
+    /// `parameters.toml`.
+    ///
+    /// Only fields that differ from the variant defaults are written; `s9` is
+    /// omitted since it is controlled by the `atm` flag.
+    ///
+    /// ```text
+    /// [parameter.<method>]
+    /// d3.bj = {a1=0.3981, s8=1.9889, a2=4.4211, doi="10.1002/jcc.21759"}
+    /// ```
+    pub fn to_toml_entry(&self, method: &str) -> String {
+        let version = self.param.version();
+        let defaults = get_default_param_table(version).unwrap_or_default();
+        let mut fields: Vec<String> = self
+            .param
+            .to_table()
+            .into_iter()
+            .filter(|(key, value)| key != "s9" && defaults.get(key) != Some(value))
+            .map(|(key, value)| format!("{key}={value}"))
+            .collect();
+        if let Some(doi) = &self.doi {
+            fields.push(format!("doi={}", toml::Value::from(doi.as_str())));
+        }
+        format!(
+            "[parameter.{}]\nd3.{version} = {{{}}}\n",
+            normalize_method(method),
+            fields.join(", ")
+        )
+    }
 }
 
 /* #endregion */
@@ -209,12 +461,972 @@ impl DFTD3ParamAPI for DFTD3DampingParam {
 
 /* #endregion */
 
+/* #region Serde support */
+
+/// Serialize as map `version`, parameter fields (all, sorted) and `doi`.
+fn serialize_tagged<S: serde::Serializer>(
+    param: &DFTD3DampingParamEnum,
+    doi: Option<&str>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    use serde::ser::SerializeMap;
+    let table = param.to_table();
+    let mut map = serializer.serialize_map(Some(table.len() + 1 + doi.is_some() as usize))?;
+    map.serialize_entry("version", param.version())?;
+    for (field, value) in &table {
+        map.serialize_entry(field, value)?;
+    }
+    if let Some(doi) = doi {
+        map.serialize_entry("doi", doi)?;
+    }
+    map.end()
+}
+
+impl Serialize for DFTD3DampingParam {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_tagged(&self.param, self.doi.as_deref(), serializer)
+    }
+}
+
+impl Serialize for DFTD3DampingParamEnum {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serialize_tagged(self, None, serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for DFTD3DampingParam {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let input = Table::deserialize(deserializer)?;
+        crate::parsing::dftd3_parse_damping_param_f(&input)
+            .map_err(|e| serde::de::Error::custom(e.get_message()))
+    }
+}
+
+impl<'de> Deserialize<'de> for DFTD3DampingParamEnum {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        DFTD3DampingParam::deserialize(deserializer).map(|param| param.param)
+    }
+}
+
+/* #endregion */
+
+/* #region Parameter database */
+
+/// Indexed DFT-D3 damping parameter database.
+///
+/// The embedded `parameters.toml` is parsed once on first access of
+/// [`ParameterDatabase::embedded`]; method names are looked up through an
+/// index of normalized names (lowercase, without `-`, `_` and spaces), and
+/// variants are normalized by aliases (`d3bj`, `mbj`, ...).
+///
+/// # Example
+///
+/// ```
+/// use dftd3::prelude::*;
+///
+/// let db = ParameterDatabase::embedded();
+/// assert_eq!(db.resolve_method("B97-1"), Some("b97_1"));
+/// assert!(db.variants("bp").contains(&"cso".to_string()));
+/// assert!(db.methods_for_variant("d3op").contains(&"b97d".to_string()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct ParameterDatabase {
+    /// Default variants (`[default] d3`).
+    default_variants: Vec<String>,
+    /// Default parameter tables by normalized variant.
+    defaults: BTreeMap<String, Table>,
+    /// Raw entries by method key and normalized variant.
+    entries: BTreeMap<String, BTreeMap<String, Table>>,
+    /// Method key by normalized method name.
+    index: HashMap<String, String>,
+    /// Method key by normalized alias name.
+    aliases: BTreeMap<String, String>,
+    /// Metadata by method key.
+    metadata: BTreeMap<String, MethodMetadata>,
+    /// References of the `[citation]` section by lowercase DOI.
+    citations: BTreeMap<String, DFTD3Reference>,
+    /// Source of entries merged from parameter files, by method key (`None`
+    /// for defaults) and normalized variant.
+    sources: HashMap<(Option<String>, String), String>,
+}
+
+impl ParameterDatabase {
+    /// Database of the embedded `parameters.toml`, parsed once.
+    pub fn embedded() -> &'static ParameterDatabase {
+        static DATABASE: OnceLock<ParameterDatabase> = OnceLock::new();
+        DATABASE.get_or_init(|| {
+            ParameterDatabase::from_toml_str(PARAMETERS_TOML)
+                .expect("embedded parameters.toml should be valid")
+        })
+    }
+
+    /// Parse database from TOML string in the format of `parameters.toml`.
+    pub fn from_toml_str(toml_str: &str) -> Result<Self, DFTD3Error> {
+        let db = parse_data_base(toml_str)?;
+        let d = db.default.parameter.d3;
+        let defaults = [
+            ("bj", Some(d.bj)),
+            ("zero", Some(d.zero)),
+            ("bjm", Some(d.bjm)),
+            ("zerom", Some(d.zerom)),
+            ("op", d.op),
+            ("cso", d.cso),
+        ]
+        .into_iter()
+        .filter_map(|(version, table)| Some((version.to_string(), table?)))
+        .collect();
+
+        let db_citations = db.citation;
+        let mut entries = BTreeMap::new();
+        let mut index = HashMap::new();
+        let mut metadata = vec![];
+        for (method, variants) in db.parameter {
+            if let Some(other) = index.insert(normalize_method(&method), method.clone()) {
+                return Err(DFTD3Error::ParametersError(format!(
+                    "Methods '{other}' and '{method}' have the same normalized name"
+                )));
+            }
+            metadata.extend(variants.meta.map(|meta| (method.clone(), meta)));
+            entries.insert(method, variants.d3.into_tables());
+        }
+
+        let mut db = Self {
+            default_variants: db.default.d3,
+            defaults,
+            entries,
+            index,
+            aliases: BTreeMap::new(),
+            metadata: BTreeMap::new(),
+            citations: BTreeMap::new(),
+            sources: HashMap::new(),
+        };
+        for (doi, entry) in db_citations {
+            db.insert_citation(&doi, entry);
+        }
+        // aliases are checked against all method names
+        for (method, meta) in metadata {
+            db.insert_metadata(&method, meta)?;
+        }
+        Ok(db)
+    }
+
+    /// Method key of the database for a method name or alias, if present.
+    pub fn resolve_method(&self, method: &str) -> Option<&str> {
+        let method = normalize_method(method);
+        self.index.get(&method).or_else(|| self.aliases.get(&method)).map(|s| s.as_str())
+    }
+
+    /// Method key that an alias refers to, if `alias` is a registered alias.
+    pub fn resolve_alias(&self, alias: &str) -> Option<&str> {
+        self.aliases.get(&normalize_method(alias)).map(|s| s.as_str())
+    }
+
+    /// Registered aliases (normalized alias to method key).
+    pub fn aliases(&self) -> &BTreeMap<String, String> {
+        &self.aliases
+    }
+
+    /// All method keys, sorted.
+    pub fn methods(&self) -> Vec<String> {
+        self.entries.keys().cloned().collect()
+    }
+
+    /// Normalized variants available for a method (empty if not found).
+    pub fn variants(&self, method: &str) -> Vec<String> {
+        self.method_entry(method).map(|e| e.keys().cloned().collect()).unwrap_or_default()
+    }
+
+    /// Method keys having an entry of a variant, sorted.
+    pub fn methods_for_variant(&self, version: &str) -> Vec<String> {
+        let version = normalize_version(version);
+        self.entries
+            .iter()
+            .filter(|(_, variants)| variants.contains_key(&version))
+            .map(|(method, _)| method.clone())
+            .collect()
+    }
+
+    /// Metadata of a method, if present.
+    pub fn metadata(&self, method: &str) -> Option<&MethodMetadata> {
+        self.metadata.get(self.resolve_method(method)?)
+    }
+
+    /// Display name of a method: the metadata name, or the method key if
+    /// absent; `None` if the method is not found.
+    pub fn display_name(&self, method: &str) -> Option<&str> {
+        let key = self.resolve_method(method)?;
+        Some(self.metadata.get(key).and_then(|m| m.name.as_deref()).unwrap_or(key))
+    }
+
+    /// Method keys of a functional family (optional) having an entry of a
+    /// variant (optional), sorted; e.g. all hybrid functionals with D3(BJ):
+    ///
+    /// ```
+    /// use dftd3::prelude::*;
+    ///
+    /// let db = ParameterDatabase::embedded();
+    /// let methods = db.find_methods(Some(FunctionalFamily::Hybrid), Some("d3bj"));
+    /// assert!(methods.contains(&"b3lyp".to_string()));
+    /// assert!(!methods.contains(&"m062x".to_string())); // zero damping only
+    /// ```
+    pub fn find_methods(
+        &self,
+        family: Option<FunctionalFamily>,
+        version: Option<&str>,
+    ) -> Vec<String> {
+        let version = version.map(normalize_version);
+        self.entries
+            .iter()
+            .filter(|(_, variants)| version.as_ref().is_none_or(|v| variants.contains_key(v)))
+            .filter(|(method, _)| {
+                family.is_none_or(|f| self.metadata.get(*method).and_then(|m| m.family) == Some(f))
+            })
+            .map(|(method, _)| method.clone())
+            .collect()
+    }
+
+    /// Reference of a DOI (case-insensitive) in the `[citation]` section, if
+    /// present.
+    pub fn citation(&self, doi: &str) -> Option<&DFTD3Reference> {
+        self.citations.get(&doi.to_lowercase())
+    }
+
+    /// Default variants of the database (`[default] d3`).
+    pub fn default_variants(&self) -> &[String] {
+        &self.default_variants
+    }
+
+    /// Recommended variant of a method: the first default variant
+    /// ([`ParameterDatabase::default_variants`]) with an entry for the method.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the method is not found, or has none of the
+    /// default variants (e.g. only `op`).
+    pub fn default_variant(&self, method: &str) -> Result<String, DFTD3Error> {
+        let key = self.resolve_method(method).ok_or_else(|| {
+            DFTD3Error::ParametersError(
+                ParameterLookupIssue::UnknownMethod {
+                    method: method.to_string(),
+                    suggestions: self.suggest_methods(method, SUGGESTION_LIMIT),
+                }
+                .to_string(),
+            )
+        })?;
+        let available = self.variants(key);
+        let version = self.default_variants.iter().find(|v| available.contains(v));
+        version.cloned().ok_or_else(|| {
+            DFTD3Error::ParametersError(format!(
+                "No default variant ({}) found for method '{key}'; {key} has {}",
+                self.default_variants.join(", "),
+                conventional_order(&available).join(", ")
+            ))
+        })
+    }
+
+    /// Default parameter table of a variant, if present.
+    pub fn defaults(&self, version: &str) -> Option<&Table> {
+        self.defaults.get(&normalize_version(version))
+    }
+
+    /// Raw entry of a method and variant (without defaults merged, including
+    /// `doi`), if present.
+    pub fn raw_entry(&self, method: &str, version: &str) -> Option<&Table> {
+        self.method_entry(method)?.get(&normalize_version(version))
+    }
+
+    /// Reference DOI of a method and variant, if present.
+    pub fn doi(&self, method: &str, version: &str) -> Option<String> {
+        self.raw_entry(method, version).and_then(extract_doi)
+    }
+
+    /// Merged TOML table of a method and variant (method values override
+    /// defaults).
+    pub fn merged_table(&self, method: &str, version: &str) -> Result<Table, DFTD3Error> {
+        let version = normalize_version(version);
+        let entry = self
+            .method_entry(method)
+            .and_then(|e| e.get(&version))
+            .ok_or_else(|| self.lookup_error(Some(method), &version))?;
+        Ok(merge_tables(entry, self.defaults(&version).unwrap_or(&Table::new())))
+    }
+
+    /// Method keys nearest to `method` by edit distance of normalized names,
+    /// ranked (at most `limit`).
+    pub fn suggest_methods(&self, method: &str, limit: usize) -> Vec<String> {
+        let method = normalize_method(method);
+        let threshold = (method.chars().count() / 3).max(2);
+        let mut ranked: Vec<(usize, &String)> = self
+            .index
+            .iter()
+            .map(|(normalized, key)| (edit_distance(&method, normalized), key))
+            .filter(|(d, _)| *d <= threshold)
+            .collect();
+        ranked.sort();
+        ranked.into_iter().take(limit).map(|(_, key)| key.clone()).collect()
+    }
+
+    /// Diagnose the lookup of a method (optional) and variant, with ranked
+    /// suggestions; `None` if the lookup succeeds.
+    pub fn diagnose(&self, method: Option<&str>, version: &str) -> Option<ParameterLookupIssue> {
+        let version = normalize_version(version);
+        if !DATABASE_VARIANTS.contains(&version.as_str()) {
+            return Some(ParameterLookupIssue::UnknownVariant {
+                suggestions: suggest_variants(&version),
+                version,
+            });
+        }
+        let method = method?;
+        let Some(key) = self.resolve_method(method) else {
+            return Some(ParameterLookupIssue::UnknownMethod {
+                method: method.to_string(),
+                suggestions: self.suggest_methods(method, SUGGESTION_LIMIT),
+            });
+        };
+        let available = self.variants(key);
+        if available.contains(&version) {
+            return None;
+        }
+        Some(ParameterLookupIssue::MissingVariant {
+            method: key.to_string(),
+            version,
+            available: conventional_order(&available),
+        })
+    }
+
+    /// Lookup error with diagnosis of a method (optional) and variant.
+    fn lookup_error(&self, method: Option<&str>, version: &str) -> DFTD3Error {
+        let message = match self.diagnose(method, version) {
+            Some(issue) => issue.to_string(),
+            None => format!("Variant '{}' not found in defaults", normalize_version(version)),
+        };
+        DFTD3Error::ParametersError(message)
+    }
+
+    /// Get damping parameters for a method and variant.
+    pub fn get_damping_param(&self, method: &str, version: &str) -> DFTD3DampingParam {
+        self.get_damping_param_f(method, version).unwrap()
+    }
+
+    /// Get damping parameters of a method in its recommended variant
+    /// ([`ParameterDatabase::default_variant`]).
+    pub fn get_default_damping_param(&self, method: &str) -> DFTD3DampingParam {
+        self.get_default_damping_param_f(method).unwrap()
+    }
+
+    /// Get all damping parameters of a variant, by method key.
+    pub fn get_all_damping_params(&self, version: &str) -> HashMap<String, DFTD3DampingParam> {
+        self.get_all_damping_params_f(version).unwrap()
+    }
+
+    /// Get damping parameters for a method and variant (failable).
+    ///
+    /// # See also
+    ///
+    /// [`ParameterDatabase::get_damping_param`]
+    pub fn get_damping_param_f(
+        &self,
+        method: &str,
+        version: &str,
+    ) -> Result<DFTD3DampingParam, DFTD3Error> {
+        let merged = self.merged_table(method, version)?;
+        convert_to_damping_param(&merged, &normalize_version(version))
+    }
+
+    /// Get damping parameters of a method in its recommended variant
+    /// (failable).
+    ///
+    /// # See also
+    ///
+    /// [`ParameterDatabase::get_default_damping_param`]
+    pub fn get_default_damping_param_f(
+        &self,
+        method: &str,
+    ) -> Result<DFTD3DampingParam, DFTD3Error> {
+        let version = self.default_variant(method)?;
+        self.get_damping_param_f(method, &version)
+    }
+
+    /// Get all damping parameters of a variant, by method key (failable).
+    ///
+    /// Entries that cannot be converted (e.g. variants not enabled by crate
+    /// features) are skipped.
+    ///
+    /// # See also
+    ///
+    /// [`ParameterDatabase::get_all_damping_params`]
+    pub fn get_all_damping_params_f(
+        &self,
+        version: &str,
+    ) -> Result<HashMap<String, DFTD3DampingParam>, DFTD3Error> {
+        let version = normalize_version(version);
+        let defaults = self.defaults(&version).ok_or_else(|| self.lookup_error(None, &version))?;
+        let mut result = HashMap::new();
+        for (method, variants) in &self.entries {
+            if let Some(entry) = variants.get(&version) {
+                let merged = merge_tables(entry, defaults);
+                if let Ok(param) = convert_to_damping_param(&merged, &version) {
+                    result.insert(method.clone(), param);
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Source of an entry (`method = None` for variant defaults), if it was
+    /// merged from a parameter file; `None` for entries of the base database.
+    pub fn source(&self, method: Option<&str>, version: &str) -> Option<&str> {
+        let method = match method {
+            Some(method) => Some(self.resolve_method(method)?.to_string()),
+            None => None,
+        };
+        self.sources.get(&(method, normalize_version(version))).map(|s| s.as_str())
+    }
+
+    /// Merge a TOML string in the format of `parameters.toml` over this
+    /// database, returns conflicts with existing entries.
+    ///
+    /// All sections are optional. Entries of `[parameter.<method>]` replace the
+    /// existing entry of the same method (matched by normalized name) and
+    /// variant as a whole; tables of `[default.parameter]` replace the variant
+    /// defaults as a whole; `[default] d3` replaces the default variants.
+    ///
+    /// - `source` - label of the string in conflict reports (e.g. file path)
+    pub fn merge_toml_str(&mut self, toml_str: &str, source: &str) -> Vec<ParameterConflict> {
+        self.merge_toml_str_f(toml_str, source).unwrap()
+    }
+
+    /// Merge parameter files in order (later files take precedence), returns
+    /// conflicts with existing entries.
+    pub fn merge_files<P: AsRef<Path>>(&mut self, paths: &[P]) -> Vec<ParameterConflict> {
+        self.merge_files_f(paths).unwrap()
+    }
+
+    /// Merge a TOML string over this database (failable).
+    ///
+    /// The string is validated first
+    /// ([`ParameterDatabase::validate_toml_str`]); it is rejected if any
+    /// error is found, warnings are accepted. The database is left
+    /// unchanged on error.
+    ///
+    /// # See also
+    ///
+    /// [`ParameterDatabase::merge_toml_str`]
+    pub fn merge_toml_str_f(
+        &mut self,
+        toml_str: &str,
+        source: &str,
+    ) -> Result<Vec<ParameterConflict>, DFTD3Error> {
+        let report = self.validate_toml_str(toml_str, source);
+        if report.has_errors() {
+            return Err(DFTD3Error::ParametersError(report.to_string().trim_end().to_string()));
+        }
+        let layer: ParameterDataBaseLayer = toml::from_str(toml_str).map_err(|e| {
+            DFTD3Error::ParametersError(format!("TOML parsing error in {source}: {e}"))
+        })?;
+        let parameter: BTreeMap<String, D3Variants> = layer.parameter.into_iter().collect();
+
+        // merged into a copy, so that a failure leaves this database unchanged
+        let mut merged = self.clone();
+        let mut conflicts = vec![];
+        if let Some(default) = layer.default {
+            if let Some(d3) = default.d3 {
+                merged.default_variants = d3;
+            }
+            for (version, table) in
+                default.parameter.map(|p| p.d3.into_tables()).unwrap_or_default()
+            {
+                merged.insert_entry(None, version, table, source, &mut conflicts);
+            }
+        }
+        for (doi, entry) in layer.citation {
+            merged.insert_citation(&doi, entry);
+        }
+        for (method, variants) in parameter {
+            let key = merged.insert_method(method);
+            if let Some(meta) = variants.meta {
+                merged.insert_metadata(&key, meta)?;
+            }
+            for (version, table) in variants.d3.into_tables() {
+                merged.insert_entry(Some(&key), version, table, source, &mut conflicts);
+            }
+        }
+        *self = merged;
+        Ok(conflicts)
+    }
+
+    /// Register parameters of a method and variant, returns the conflict if
+    /// a different entry is replaced.
+    ///
+    /// - `method` - method name; replaces an alias of the same name
+    /// - `version` - DFT-D3 variant ("bj", "zero", "bjm", "zerom", "op", "cso")
+    /// - `param` - parameters of the variant, e.g.
+    ///   [`DFTD3RationalDampingParam`] for "bj"
+    /// - `doi` - optional, reference DOI
+    pub fn register_method<P: Serialize>(
+        &mut self,
+        method: &str,
+        version: &str,
+        param: P,
+        doi: Option<&str>,
+    ) -> Option<ParameterConflict> {
+        self.register_method_f(method, version, param, doi).unwrap()
+    }
+
+    /// Register an alias of a method, e.g. "pbeh" for "pbe0".
+    pub fn register_alias(&mut self, alias: &str, method: &str) {
+        self.register_alias_f(alias, method).unwrap()
+    }
+
+    /// Register parameters of a method and variant (failable).
+    ///
+    /// Returns an error if `param` does not match the variant.
+    ///
+    /// # See also
+    ///
+    /// [`ParameterDatabase::register_method`]
+    pub fn register_method_f<P: Serialize>(
+        &mut self,
+        method: &str,
+        version: &str,
+        param: P,
+        doi: Option<&str>,
+    ) -> Result<Option<ParameterConflict>, DFTD3Error> {
+        let version = normalize_version(version);
+        let mut table = Table::try_from(param).map_err(|e| {
+            DFTD3Error::ParametersError(format!("Failed to serialize parameters: {e}"))
+        })?;
+        convert_to_damping_param(&table, &version)?;
+        if let Some(doi) = doi {
+            table.insert("doi".into(), doi.into());
+        }
+        let key = self.insert_method(method.to_string());
+        let mut conflicts = vec![];
+        self.insert_entry(Some(&key), version, table, REGISTERED_SOURCE, &mut conflicts);
+        Ok(conflicts.pop())
+    }
+
+    /// Register an alias of a method (failable).
+    ///
+    /// Returns an error if the method is not found, or if the alias is the name
+    /// of a method of the database.
+    ///
+    /// # See also
+    ///
+    /// [`ParameterDatabase::register_alias`]
+    pub fn register_alias_f(&mut self, alias: &str, method: &str) -> Result<(), DFTD3Error> {
+        let key = self.resolve_method(method).ok_or_else(|| {
+            DFTD3Error::ParametersError(format!("Method '{}' not found in database", method))
+        })?;
+        let normalized = normalize_method(alias);
+        if self.index.contains_key(&normalized) {
+            return Err(DFTD3Error::ParametersError(format!(
+                "Alias '{alias}' is a method name of the database"
+            )));
+        }
+        self.aliases.insert(normalized, key.to_string());
+        Ok(())
+    }
+
+    /// Method key for a method name (not alias), inserted into the index if
+    /// new; an alias of the same name is removed.
+    fn insert_method(&mut self, method: String) -> String {
+        let normalized = normalize_method(&method);
+        self.aliases.remove(&normalized);
+        self.index.entry(normalized).or_insert(method).clone()
+    }
+
+    /// Insert a reference of the `[citation]` section; the citation key is
+    /// the last name of the first author and the year (with suffix `b`, `c`,
+    /// ... if taken).
+    fn insert_citation(&mut self, doi: &str, entry: CitationEntry) {
+        let doi_key = doi.to_lowercase();
+        let authors: Vec<String> = entry
+            .author
+            .iter()
+            .map(|name| match name.trim().rsplit_once(' ') {
+                Some((first, last)) => format!("{last}, {}", initials(first)),
+                None => name.trim().to_string(),
+            })
+            .collect();
+        let year = entry.year.as_deref().and_then(|y| y.trim().parse().ok());
+        let last_name = authors.first().and_then(|a| a.split(',').next()).unwrap_or_default();
+        let stem: String = last_name
+            .chars()
+            .filter(|c| c.is_ascii_alphanumeric())
+            .map(|c| c.to_ascii_lowercase())
+            .chain(year.map(|y: u32| y.to_string()).unwrap_or_default().chars())
+            .collect();
+        let taken =
+            |key: &str| self.citations.iter().any(|(doi, r)| *doi != doi_key && r.key == key);
+        let key = match stem.is_empty() {
+            true => DFTD3Reference::bare(doi).key,
+            false => std::iter::once(stem.clone())
+                .chain(('b'..='z').map(|c| format!("{stem}{c}")))
+                .find(|key| !taken(key))
+                .unwrap_or(stem),
+        };
+        let reference = DFTD3Reference {
+            key,
+            authors,
+            title: entry.title,
+            journal: entry.journal,
+            year,
+            volume: entry.volume,
+            issue: entry.issue,
+            pages: entry.pages.map(|p| p.replace('–', "-")),
+            doi: doi.to_string(),
+        };
+        self.citations.insert(doi_key, reference);
+    }
+
+    /// Set metadata of a method key, and register its display name and
+    /// aliases as aliases (except those equal to the method name).
+    fn insert_metadata(&mut self, key: &str, meta: MethodMetadata) -> Result<(), DFTD3Error> {
+        let own = normalize_method(key);
+        for name in meta.name.iter().chain(&meta.aliases) {
+            if normalize_method(name) != own {
+                self.register_alias_f(name, key)?;
+            }
+        }
+        self.metadata.insert(key.to_string(), meta);
+        Ok(())
+    }
+
+    /// Merge parameter files in order (failable).
+    ///
+    /// The database is left unchanged on error.
+    ///
+    /// # See also
+    ///
+    /// [`ParameterDatabase::merge_files`]
+    pub fn merge_files_f<P: AsRef<Path>>(
+        &mut self,
+        paths: &[P],
+    ) -> Result<Vec<ParameterConflict>, DFTD3Error> {
+        let mut merged = self.clone();
+        let mut conflicts = vec![];
+        for path in paths {
+            let path = path.as_ref();
+            let toml_str = std::fs::read_to_string(path).map_err(|e| {
+                DFTD3Error::ParametersError(format!(
+                    "Failed to read parameter file {}: {e}",
+                    path.display()
+                ))
+            })?;
+            conflicts.extend(merged.merge_toml_str_f(&toml_str, &path.display().to_string())?);
+        }
+        *self = merged;
+        Ok(conflicts)
+    }
+
+    /// Merge parameter files listed in environment variable
+    /// [`DFTD3_PARAM_PATH`] (separated as `PATH`), returns conflicts with
+    /// existing entries.
+    pub fn merge_env_f(&mut self) -> Result<Vec<ParameterConflict>, DFTD3Error> {
+        let Some(value) = std::env::var_os(DFTD3_PARAM_PATH) else { return Ok(vec![]) };
+        let paths: Vec<PathBuf> =
+            std::env::split_paths(&value).filter(|p| !p.as_os_str().is_empty()).collect();
+        self.merge_files_f(&paths)
+    }
+
+    fn insert_entry(
+        &mut self,
+        method: Option<&str>,
+        version: String,
+        table: Table,
+        source: &str,
+        conflicts: &mut Vec<ParameterConflict>,
+    ) {
+        let tables = match method {
+            Some(method) => self.entries.entry(method.to_string()).or_default(),
+            None => &mut self.defaults,
+        };
+        let key = (method.map(|m| m.to_string()), version.clone());
+        if let Some(previous) = tables.insert(version.clone(), table.clone()) {
+            if previous != table {
+                conflicts.push(ParameterConflict {
+                    method: key.0.clone(),
+                    version,
+                    previous_source: self.sources.get(&key).cloned(),
+                    source: source.to_string(),
+                    previous,
+                    current: table,
+                });
+            }
+        }
+        self.sources.insert(key, source.to_string());
+    }
+
+    fn method_entry(&self, method: &str) -> Option<&BTreeMap<String, Table>> {
+        self.entries.get(self.resolve_method(method)?)
+    }
+}
+
+/// Entry of a parameter file replacing a different entry of the database.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterConflict {
+    /// Method key, or `None` for variant defaults.
+    pub method: Option<String>,
+    /// Normalized variant.
+    pub version: String,
+    /// Source of the replaced entry, `None` for the base database.
+    pub previous_source: Option<String>,
+    /// Source of the new entry.
+    pub source: String,
+    /// Replaced entry.
+    pub previous: Table,
+    /// New entry.
+    pub current: Table,
+}
+
+impl std::fmt::Display for ParameterConflict {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match &self.method {
+            Some(method) => format!("parameter.{method}.d3.{}", self.version),
+            None => format!("default.parameter.d3.{}", self.version),
+        };
+        let previous = self.previous_source.as_deref().unwrap_or("embedded database");
+        let [previous_table, current_table] =
+            [&self.previous, &self.current].map(|t| toml::Value::Table(t.clone()));
+        write!(
+            f,
+            "{name} of {previous} ({previous_table}) overridden by {} ({current_table})",
+            self.source
+        )
+    }
+}
+
+/// Maximum number of suggestions of lookup errors.
+pub(crate) const SUGGESTION_LIMIT: usize = 5;
+
+/// Diagnosis of a failed parameter lookup, with ranked suggestions.
+///
+/// Its `Display` is the message of the [`DFTD3Error::ParametersError`]
+/// returned by lookups; use [`dftd3_diagnose_lookup`] to read it
+/// programmatically.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterLookupIssue {
+    /// Method (or alias) not found.
+    UnknownMethod {
+        /// Requested method.
+        method: String,
+        /// Nearest method keys by edit distance, ranked.
+        suggestions: Vec<String>,
+    },
+    /// Variant not recognized.
+    UnknownVariant {
+        /// Requested variant (normalized).
+        version: String,
+        /// Nearest variants by edit distance, ranked.
+        suggestions: Vec<String>,
+    },
+    /// Method found, but without the requested variant.
+    MissingVariant {
+        /// Method key.
+        method: String,
+        /// Requested variant (normalized).
+        version: String,
+        /// Variants available for the method.
+        available: Vec<String>,
+    },
+}
+
+impl std::fmt::Display for ParameterLookupIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParameterLookupIssue::UnknownMethod { method, suggestions } => {
+                write!(f, "Method '{method}' not found in database")?;
+                if !suggestions.is_empty() {
+                    write!(f, "; did you mean {}?", suggestions.join(", "))?;
+                }
+                Ok(())
+            },
+            ParameterLookupIssue::UnknownVariant { version, suggestions } => {
+                write!(f, "Unknown variant: {version}")?;
+                if suggestions.is_empty() {
+                    write!(f, "; expected one of {}", DATABASE_VARIANTS.join(", "))
+                } else {
+                    write!(f, "; did you mean {}?", suggestions.join(", "))
+                }
+            },
+            ParameterLookupIssue::MissingVariant { method, version, available } => write!(
+                f,
+                "Variant '{version}' not found for method '{method}'; {method} has {}",
+                available.join(", ")
+            ),
+        }
+    }
+}
+
+/// Environment variable listing parameter files merged over the embedded
+/// database (separated as `PATH`).
+pub const DFTD3_PARAM_PATH: &str = "DFTD3_PARAM_PATH";
+
+/// Source label of entries registered at runtime.
+const REGISTERED_SOURCE: &str = "runtime registration";
+
+/// Global database, with error of merging [`DFTD3_PARAM_PATH`] files.
+struct GlobalDatabase {
+    db: RwLock<ParameterDatabase>,
+    env_error: RwLock<Option<String>>,
+}
+
+fn global_database() -> &'static GlobalDatabase {
+    static GLOBAL: OnceLock<GlobalDatabase> = OnceLock::new();
+    GLOBAL.get_or_init(|| {
+        let (db, env_error) = embedded_with_env();
+        GlobalDatabase { db: RwLock::new(db), env_error: RwLock::new(env_error) }
+    })
+}
+
+/// Embedded database with [`DFTD3_PARAM_PATH`] files merged; the embedded
+/// database alone if merging fails.
+fn embedded_with_env() -> (ParameterDatabase, Option<String>) {
+    let mut db = ParameterDatabase::embedded().clone();
+    let env_error = db.merge_env_f().err().map(|e| e.get_message());
+    (db, env_error)
+}
+
+impl ParameterDatabase {
+    /// Global database used by all lookups of this crate: the embedded database
+    /// with files of [`DFTD3_PARAM_PATH`] and [`dftd3_load_param_files`]
+    /// merged.
+    ///
+    /// Do not hold the guard while calling other lookup functions of this
+    /// crate, or while loading parameter files.
+    pub fn global() -> RwLockReadGuard<'static, ParameterDatabase> {
+        Self::global_f().unwrap()
+    }
+
+    /// Global database used by all lookups of this crate (failable).
+    ///
+    /// Returns an error if files of [`DFTD3_PARAM_PATH`] could not be merged.
+    ///
+    /// # See also
+    ///
+    /// [`ParameterDatabase::global`]
+    pub fn global_f() -> Result<RwLockReadGuard<'static, ParameterDatabase>, DFTD3Error> {
+        let global = global_database();
+        if let Some(error) = global.env_error.read().map_err(|_| lock_error())?.as_ref() {
+            return Err(DFTD3Error::ParametersError(format!(
+                "Failed to merge {DFTD3_PARAM_PATH} parameter files: {error}"
+            )));
+        }
+        global.db.read().map_err(|_| lock_error())
+    }
+}
+
+fn lock_error() -> DFTD3Error {
+    DFTD3Error::ParametersError("Parameter database lock poisoned".into())
+}
+
+/* #endregion */
+
 /* #region Public API functions */
 
-/// Load the parameter database from embedded TOML.
-fn load_data_base() -> Result<ParameterDataBase, DFTD3Error> {
-    toml::from_str(PARAMETERS_TOML)
-        .map_err(|e| DFTD3Error::ParametersError(format!("TOML parsing error: {}", e)))
+/// Merge parameter files over the global database, in order (later files take
+/// precedence over earlier files, [`DFTD3_PARAM_PATH`] files and the embedded
+/// database).
+///
+/// Returns entries that replaced different existing entries.
+///
+/// # Example
+///
+/// ```no_run
+/// use dftd3::prelude::*;
+///
+/// let conflicts = dftd3_load_param_files(&["in-house.toml"]);
+/// conflicts.iter().for_each(|c| println!("{c}"));
+/// let param = dftd3_get_damping_param("my-functional", "bj");
+/// ```
+pub fn dftd3_load_param_files<P: AsRef<Path>>(paths: &[P]) -> Vec<ParameterConflict> {
+    dftd3_load_param_files_f(paths).unwrap()
+}
+
+/// Merge parameter files over the global database (failable).
+///
+/// The global database is left unchanged on error.
+///
+/// # See also
+///
+/// [`dftd3_load_param_files`]
+pub fn dftd3_load_param_files_f<P: AsRef<Path>>(
+    paths: &[P],
+) -> Result<Vec<ParameterConflict>, DFTD3Error> {
+    global_write_f()?.merge_files_f(paths)
+}
+
+/// Register parameters of a method and variant in the global database,
+/// returns the conflict if a different entry is replaced.
+///
+/// Registered methods are found by [`dftd3_get_damping_param`], parsing with
+/// `method`, and [`dftd3_load_param`] for names unknown to the s-dftd3 library.
+///
+/// # Example
+///
+/// ```no_run
+/// use dftd3::prelude::*;
+///
+/// let param =
+///     DFTD3RationalDampingParamBuilder::default().a1(0.42).s8(1.5).a2(4.2).build().unwrap();
+/// dftd3_register_method("myfunc", "bj", param, Some("10.0000/in-house"));
+/// dftd3_register_alias("pbeh", "pbe0");
+/// let param = dftd3_load_param("d3bj", "myfunc", true);
+/// ```
+pub fn dftd3_register_method<P: Serialize>(
+    method: &str,
+    version: &str,
+    param: P,
+    doi: Option<&str>,
+) -> Option<ParameterConflict> {
+    dftd3_register_method_f(method, version, param, doi).unwrap()
+}
+
+/// Register parameters of a method and variant in the global database
+/// (failable).
+///
+/// # See also
+///
+/// [`dftd3_register_method`]
+pub fn dftd3_register_method_f<P: Serialize>(
+    method: &str,
+    version: &str,
+    param: P,
+    doi: Option<&str>,
+) -> Result<Option<ParameterConflict>, DFTD3Error> {
+    global_write_f()?.register_method_f(method, version, param, doi)
+}
+
+/// Register an alias of a method in the global database, e.g. "pbeh" for
+/// "pbe0".
+pub fn dftd3_register_alias(alias: &str, method: &str) {
+    dftd3_register_alias_f(alias, method).unwrap()
+}
+
+/// Register an alias of a method in the global database (failable).
+///
+/// # See also
+///
+/// [`dftd3_register_alias`]
+pub fn dftd3_register_alias_f(alias: &str, method: &str) -> Result<(), DFTD3Error> {
+    global_write_f()?.register_alias_f(alias, method)
+}
+
+/// Write guard of the global database; fails if [`DFTD3_PARAM_PATH`] files
+/// could not be merged.
+fn global_write_f() -> Result<RwLockWriteGuard<'static, ParameterDatabase>, DFTD3Error> {
+    drop(ParameterDatabase::global_f()?);
+    global_database().db.write().map_err(|_| lock_error())
+}
+
+/// Reset the global database to the embedded database with
+/// [`DFTD3_PARAM_PATH`] files merged (the variable is read again).
+pub fn dftd3_reset_param_database() {
+    let (new_db, new_error) = embedded_with_env();
+    let global = global_database();
+    *global.db.write().unwrap_or_else(|e| e.into_inner()) = new_db;
+    *global.env_error.write().unwrap_or_else(|e| e.into_inner()) = new_error;
 }
 
 /// Get damping parameters for a specific method and variant.
@@ -235,23 +1447,37 @@ pub fn dftd3_get_damping_param_f(
     method: &str,
     version: &str,
 ) -> Result<DFTD3DampingParam, DFTD3Error> {
-    let db = load_data_base()?;
-    let method_lower = normalize_method(method);
-    let version_normalized = normalize_version(version);
+    ParameterDatabase::global_f()?.get_damping_param_f(method, version)
+}
 
-    // Get method entry
-    let method_entry = db.parameter.get(&method_lower).ok_or_else(|| {
-        DFTD3Error::ParametersError(format!("Method '{}' not found in database", method))
-    })?;
+/// Get the recommended variant of a method: the first variant of `[default]
+/// d3` (`bj`, then `zero`) with an entry for the method.
+///
+/// # Example
+///
+/// ```
+/// use dftd3::prelude::*;
+///
+/// assert_eq!(dftd3_default_variant("b3lyp"), "bj");
+/// assert_eq!(dftd3_default_variant("M06-2X"), "zero");
+/// ```
+pub fn dftd3_default_variant(method: &str) -> String {
+    dftd3_default_variant_f(method).unwrap()
+}
 
-    // Get variant entry
-    let (entry_raw, default_entry) = get_variant_entry(method_entry, &version_normalized, &db)?;
+pub fn dftd3_default_variant_f(method: &str) -> Result<String, DFTD3Error> {
+    ParameterDatabase::global_f()?.default_variant(method)
+}
 
-    // Merge with defaults
-    let merged = merge_tables(&entry_raw, &default_entry);
+/// Get damping parameters of a method in its recommended variant (see
+/// [`dftd3_default_variant`]); the chosen variant is given by
+/// [`DFTD3DampingParam::version`].
+pub fn dftd3_get_default_damping_param(method: &str) -> DFTD3DampingParam {
+    dftd3_get_default_damping_param_f(method).unwrap()
+}
 
-    // Convert to public struct
-    convert_to_damping_param(&merged, &version_normalized)
+pub fn dftd3_get_default_damping_param_f(method: &str) -> Result<DFTD3DampingParam, DFTD3Error> {
+    ParameterDatabase::global_f()?.get_default_damping_param_f(method)
 }
 
 /// Get the merged TOML table for a method and variant (method values override
@@ -260,16 +1486,7 @@ pub fn dftd3_get_damping_param_f(
 /// This is useful for programmatic parameter overrides before final
 /// deserialization.
 pub(crate) fn get_merged_param_table(method: &str, version: &str) -> Result<Table, DFTD3Error> {
-    let db = load_data_base()?;
-    let method_lower = normalize_method(method);
-    let version_normalized = normalize_version(version);
-
-    let method_entry = db.parameter.get(&method_lower).ok_or_else(|| {
-        DFTD3Error::ParametersError(format!("Method '{}' not found in database", method))
-    })?;
-
-    let (entry_raw, default_entry) = get_variant_entry(method_entry, &version_normalized, &db)?;
-    Ok(merge_tables(&entry_raw, &default_entry))
+    ParameterDatabase::global_f()?.merged_table(method, version)
 }
 
 /// Normalize method name: lowercase and remove separators (`-`, `_`, spaces).
@@ -279,10 +1496,8 @@ pub(crate) fn normalize_method(method: &str) -> String {
 
 /// Get the default parameter table for a variant.
 pub(crate) fn get_default_param_table(version: &str) -> Result<Table, DFTD3Error> {
-    let db = load_data_base()?;
-    let version_normalized = normalize_version(version);
-    let (_, default_entry) = get_variant_entry_for_defaults(&version_normalized, &db)?;
-    Ok(default_entry)
+    let db = ParameterDatabase::global_f()?;
+    db.defaults(version).cloned().ok_or_else(|| db.lookup_error(None, version))
 }
 
 /// Get all damping parameters for all methods for a given variant.
@@ -293,7 +1508,8 @@ pub(crate) fn get_default_param_table(version: &str) -> Result<Table, DFTD3Error
 ///
 /// # Returns
 ///
-/// A HashMap mapping method names to their damping parameters.
+/// A HashMap mapping method names to their damping parameters. For sorted
+/// tables see [`dftd3_parameter_table`](crate::export::dftd3_parameter_table).
 pub fn dftd3_get_all_damping_params(version: &str) -> HashMap<String, DFTD3DampingParam> {
     dftd3_get_all_damping_params_f(version).unwrap()
 }
@@ -301,50 +1517,69 @@ pub fn dftd3_get_all_damping_params(version: &str) -> HashMap<String, DFTD3Dampi
 pub fn dftd3_get_all_damping_params_f(
     version: &str,
 ) -> Result<HashMap<String, DFTD3DampingParam>, DFTD3Error> {
-    let db = load_data_base()?;
-    let version_normalized = normalize_version(version);
-    let (_, default_entry) = get_variant_entry_for_defaults(&version_normalized, &db)?;
-
-    let mut result = HashMap::new();
-    for (method, method_entry) in &db.parameter {
-        if let Ok((entry_raw, _)) = get_variant_entry(method_entry, &version_normalized, &db) {
-            let merged = merge_tables(&entry_raw, &default_entry);
-            if let Ok(param) = convert_to_damping_param(&merged, &version_normalized) {
-                result.insert(method.clone(), param);
-            }
-        }
-    }
-    Ok(result)
+    ParameterDatabase::global_f()?.get_all_damping_params_f(version)
 }
 
-/// List all available methods in the database.
+/// List all available methods in the database (sorted).
+///
+/// These are normalized keys like "m062x"; see [`dftd3_display_name`] for
+/// names to show to users.
 pub fn dftd3_list_methods() -> Vec<String> {
-    let db = load_data_base().unwrap_or_else(|_| {
-        // Return empty if parsing fails (shouldn't happen with embedded TOML)
-        ParameterDataBase {
-            default: DefaultSection {
-                d3: vec!["bj".to_string(), "zero".to_string()],
-                parameter: DefaultParameterSection {
-                    d3: D3DefaultParams {
-                        bj: Table::new(),
-                        zero: Table::new(),
-                        bjm: Table::new(),
-                        zerom: Table::new(),
-                        op: None,
-                        cso: None,
-                    },
-                },
-            },
-            parameter: HashMap::new(),
-        }
-    });
-    db.parameter.keys().cloned().collect()
+    ParameterDatabase::global().methods()
+}
+
+/// Metadata of a method (display name, aliases, family, ...), if present.
+pub fn dftd3_method_metadata(method: &str) -> Option<MethodMetadata> {
+    ParameterDatabase::global().metadata(method).cloned()
+}
+
+/// Display name of a method, e.g. "M06-2X" for "m062x"; the method key if the
+/// method has no display name, `None` if it is not found.
+pub fn dftd3_display_name(method: &str) -> Option<String> {
+    ParameterDatabase::global().display_name(method).map(str::to_string)
+}
+
+/// Methods of a functional family (optional) having an entry of a variant
+/// (optional), sorted; see [`ParameterDatabase::find_methods`].
+pub fn dftd3_find_methods(family: Option<FunctionalFamily>, version: Option<&str>) -> Vec<String> {
+    ParameterDatabase::global().find_methods(family, version)
+}
+
+/// Diagnose the lookup of a method (optional) and variant in the global
+/// database, with ranked suggestions; `None` if the lookup succeeds.
+///
+/// # Example
+///
+/// ```
+/// use dftd3::prelude::*;
+///
+/// let issue = dftd3_diagnose_lookup(Some("b3lpy"), "bj").unwrap();
+/// println!("{issue}"); // Method 'b3lpy' not found in database; did you mean b3lyp, ...?
+/// match issue {
+///     ParameterLookupIssue::UnknownMethod { suggestions, .. } => assert_eq!(suggestions[0], "b3lyp"),
+///     _ => unreachable!(),
+/// }
+/// ```
+pub fn dftd3_diagnose_lookup(method: Option<&str>, version: &str) -> Option<ParameterLookupIssue> {
+    ParameterDatabase::global().diagnose(method, version)
 }
 
 /* #endregion */
 
 /* #region Internal helper functions */
 
+/// Parse the parameter database from a TOML string.
+fn parse_data_base(toml_str: &str) -> Result<ParameterDataBase, DFTD3Error> {
+    toml::from_str(toml_str)
+        .map_err(|e| DFTD3Error::ParametersError(format!("TOML parsing error: {}", e)))
+}
+
+/// Load the parameter database from embedded TOML.
+#[cfg(test)]
+fn load_data_base() -> Result<ParameterDataBase, DFTD3Error> {
+    parse_data_base(PARAMETERS_TOML)
+}
+
 /// Normalize version string (handle aliases like "d3bj" -> "bj").
 pub(crate) fn normalize_version(version: &str) -> String {
     let version_lower = version.to_lowercase().replace(['-', '_', ' '], "");
@@ -361,59 +1596,65 @@ pub(crate) fn normalize_version(version: &str) -> String {
     .to_string()
 }
 
-/// Get variant entry from method and database.
-fn get_variant_entry(
-    method_entry: &D3Variants,
-    version: &str,
-    db: &ParameterDataBase,
-) -> Result<(Table, Table), DFTD3Error> {
-    let d3_params = &method_entry.d3;
-    let entry = match version {
-        "bj" => d3_params.bj.clone(),
-        "zero" => d3_params.zero.clone(),
-        "bjm" => d3_params.bjm.clone(),
-        "zerom" => d3_params.zerom.clone(),
-        "op" => d3_params.op.clone(),
-        "cso" => d3_params.cso.clone(),
+/// Damping parameter fields of a normalized variant, independent of crate
+/// features; `None` for unknown variants.
+pub(crate) fn variant_fields(version: &str) -> Option<&'static [&'static str]> {
+    match version {
+        "bj" | "bjm" => Some(&["s6", "s8", "s9", "a1", "a2", "alp"]),
+        "zero" => Some(&["s6", "s8", "s9", "rs6", "rs8", "alp"]),
+        "zerom" => Some(&["s6", "s8", "s9", "rs6", "rs8", "alp", "bet"]),
+        "op" => Some(&["s6", "s8", "s9", "a1", "a2", "alp", "bet"]),
+        "cso" => Some(&["s6", "s9", "a1", "a2", "a3", "a4", "alp"]),
         _ => None,
-    };
+    }
+}
 
-    let entry = entry.ok_or_else(|| {
-        DFTD3Error::ParametersError(format!("Variant '{}' not found for this method", version))
-    })?;
-
-    let default_entry = match version {
-        "bj" => db.default.parameter.d3.bj.clone(),
-        "zero" => db.default.parameter.d3.zero.clone(),
-        "bjm" => db.default.parameter.d3.bjm.clone(),
-        "zerom" => db.default.parameter.d3.zerom.clone(),
-        "op" => db.default.parameter.d3.op.clone().unwrap_or_default(),
-        "cso" => db.default.parameter.d3.cso.clone().unwrap_or_default(),
-        _ => Table::new(),
-    };
+/// Variants of the database in the conventional order ([`DATABASE_VARIANTS`]).
+fn conventional_order(variants: &[String]) -> Vec<String> {
+    DATABASE_VARIANTS
+        .iter()
+        .filter(|v| variants.iter().any(|a| a == *v))
+        .map(|v| v.to_string())
+        .collect()
+}
 
-    Ok((entry, default_entry))
+/// Error of an unknown variant, with suggestions.
+pub(crate) fn unknown_variant_error(version: &str) -> DFTD3Error {
+    let version = normalize_version(version);
+    let suggestions = suggest_variants(&version);
+    DFTD3Error::ParametersError(
+        ParameterLookupIssue::UnknownVariant { version, suggestions }.to_string(),
+    )
 }
 
-/// Get default entry for a variant.
-fn get_variant_entry_for_defaults(
-    version: &str,
-    db: &ParameterDataBase,
-) -> Result<(Option<Table>, Table), DFTD3Error> {
-    let default_entry = match version {
-        "bj" => db.default.parameter.d3.bj.clone(),
-        "zero" => db.default.parameter.d3.zero.clone(),
-        "bjm" => db.default.parameter.d3.bjm.clone(),
-        "zerom" => db.default.parameter.d3.zerom.clone(),
-        "op" => db.default.parameter.d3.op.clone().unwrap_or_default(),
-        "cso" => db.default.parameter.d3.cso.clone().unwrap_or_default(),
-        _ => {
-            return Err(DFTD3Error::ParametersError(format!(
-                "Variant '{version}' not found in defaults",
-            )))
-        },
-    };
-    Ok((None, default_entry))
+/// Variants nearest to a normalized variant by edit distance, ranked.
+///
+/// A leading `d3` (as in `d3bj`) is not counted.
+fn suggest_variants(version: &str) -> Vec<String> {
+    let version = version.strip_prefix("d3").unwrap_or(version);
+    let mut ranked: Vec<(usize, &str)> = DATABASE_VARIANTS
+        .iter()
+        .map(|v| (edit_distance(version, v), *v))
+        .filter(|(d, _)| *d <= 2)
+        .collect();
+    ranked.sort();
+    ranked.into_iter().map(|(_, v)| v.to_string()).collect()
+}
+
+/// Levenshtein distance of two strings (by characters).
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == *cb { prev } else { prev + 1 };
+            prev = row[j + 1];
+            row[j + 1] = cost.min(row[j] + 1).min(prev + 1);
+        }
+    }
+    row[b.len()]
 }
 
 /// Merge method-specific entry table with defaults table.
@@ -460,7 +1701,7 @@ pub(crate) fn convert_to_damping_param(
                 "Variant '{version}' requires api-v1_3 feature",
             )))
         },
-        _ => return Err(DFTD3Error::ParametersError(format!("Unknown variant: {version}"))),
+        _ => return Err(unknown_variant_error(version)),
     };
 
     Ok(DFTD3DampingParam { param, doi })
@@ -491,6 +1732,17 @@ pub(crate) fn convert_to_damping_param(
     )))
 }
 
+/// Initials of given names, e.g. "Jan M. L." to "J. M. L." and "Chin-Wei" to
+/// "C.-W.".
+fn initials(given: &str) -> String {
+    let initial = |part: &str| part.chars().next().map(|c| format!("{c}.")).unwrap_or_default();
+    given
+        .split_whitespace()
+        .map(|name| name.split('-').map(initial).collect::<Vec<_>>().join("-"))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
 /* #endregion */
 
 #[cfg(test)]
@@ -513,6 +1765,147 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_parameter_database() {
+        let db = ParameterDatabase::embedded();
+        assert!(std::ptr::eq(db, ParameterDatabase::embedded()));
+        assert_eq!(db.default_variants(), ["bj", "zero"]);
+        assert_eq!(db.display_name("m062x"), Some("M06-2X"));
+        assert_eq!(db.resolve_method("ωB97X"), Some("wb97x"));
+        assert_eq!(db.resolve_method("B3LYP/6-31G*"), Some("b3lyp_631gd"));
+        assert!(db.metadata("hse06").unwrap().range_separated);
+        assert!(db.metadata.len() == db.entries.len());
+        let double_hybrids = db.find_methods(Some(FunctionalFamily::DoubleHybrid), Some("bj"));
+        assert!(double_hybrids.contains(&"b2plyp".to_string()));
+        assert!(!double_hybrids.contains(&"b3lyp".to_string()));
+        assert_eq!(db.default_variant("B3-LYP").unwrap(), "bj");
+        assert_eq!(db.default_variant("m05").unwrap(), "zero");
+        let message = db.default_variant("ms2").unwrap_err().get_message();
+        assert_eq!(message, "No default variant (bj, zero) found for method 'ms2'; ms2 has op");
+        assert_eq!(db.resolve_method("B97-1"), Some("b97_1"));
+        assert_eq!(db.variants("bp"), ["bj", "bjm", "cso", "zero", "zerom"]);
+        assert!(db.methods_for_variant("d3zero").contains(&"slaterdirac".to_string()));
+        assert!(!db.methods_for_variant("bj").contains(&"slaterdirac".to_string()));
+        assert_eq!(db.doi("bp", "d3bj").as_deref(), Some("10.1002/jcc.21759"));
+        assert!(db.raw_entry("bp", "bj").unwrap().get("s6").is_none());
+        assert!(db.merged_table("bp", "bj").unwrap().get("s6").is_some());
+        assert_eq!(db.defaults("zero").unwrap().get("rs8").unwrap().as_float(), Some(1.0));
+        assert!(db.merged_table("unknown", "bj").is_err());
+    }
+
+    #[test]
+    fn test_merge_parameter_files() {
+        let mut db = ParameterDatabase::embedded().clone();
+        let layer = r#"
+            [default.parameter]
+            d3.bj = {s6=1.0, s9=1.0, alp=16.0, damping="rational"}
+            [parameter.B3LYP]
+            d3.bj = {a1=0.4, s8=2.0, a2=4.4}
+            d3.zero = {rs6=1.261, s8=1.703, doi="10.1063/1.3382344"}
+            [parameter.my-functional]
+            d3.bj = {a1=0.5, s8=1.0, a2=5.0}
+        "#;
+        let conflicts = db.merge_toml_str(layer, "in-house.toml");
+        // identical b3lyp zero entry is not a conflict
+        assert_eq!(conflicts.len(), 2);
+        assert_eq!(conflicts[0].method, None);
+        assert_eq!(conflicts[1].method.as_deref(), Some("b3lyp"));
+        assert_eq!(conflicts[1].previous_source, None);
+        assert!(conflicts[1].to_string().starts_with("parameter.b3lyp.d3.bj of embedded"));
+        assert_eq!(db.source(Some("b3lyp"), "d3bj"), Some("in-house.toml"));
+        assert_eq!(db.source(Some("b3lyp"), "bjm"), None);
+        assert_eq!(db.merged_table("MyFunctional", "bj").unwrap()["alp"].as_float(), Some(16.0));
+
+        let update = "[parameter.my_functional]\nd3.bj = {a1=0.6, s8=1.0, a2=5.0}";
+        let conflicts = db.merge_toml_str(update, "b");
+        assert_eq!(conflicts[0].previous_source.as_deref(), Some("in-house.toml"));
+        // metadata only, entries are kept
+        let meta = "[parameter.my-functional]\nmeta = {name=\"My Func\", family=\"gga\"}";
+        assert!(db.merge_toml_str(meta, "d").is_empty());
+        assert_eq!(db.display_name("myfunc"), Some("My Func"));
+        assert_eq!(db.variants("my-functional"), ["bj"]);
+        assert!(db
+            .find_methods(Some(FunctionalFamily::Gga), None)
+            .contains(&"my-functional".into()));
+        // alias taken by a method of the database: rejected, nothing merged
+        let taken =
+            "[parameter.new-func]\nd3.bj = {a1=0.6, s8=1.0, a2=5.0}\nmeta = {aliases=[\"pbe\"]}";
+        let error = db.merge_toml_str_f(taken, "e").unwrap_err().get_message();
+        assert!(error.contains("alias 'pbe' is the name of method 'pbe'"), "{error}");
+        assert!(db.resolve_method("newfunc").is_none());
+        let clash = "[parameter.x-1]\nd3.bj = {a1=0.6}\n[parameter.x_1]\nd3.bj = {a1=0.6}";
+        assert!(db.merge_toml_str_f(clash, "c").is_err());
+        // rejected by validation: unknown field
+        let invalid = "[parameter.x-2]\nd3.bj = {a1=0.6, s8=1.0, a2=5.0, rs6=1.2}";
+        let error = db.merge_toml_str_f(invalid, "f").unwrap_err().get_message();
+        assert!(error.contains("error: parameter.x-2.d3.bj: unknown field 'rs6'"), "{error}");
+        assert!(db.resolve_method("x1").is_none());
+    }
+
+    #[cfg(feature = "api-v0_4")]
+    #[test]
+    fn test_damping_param_serde() {
+        let param = dftd3_get_damping_param("b3lyp", "zero");
+        let text = toml::to_string(&param).unwrap();
+        assert_eq!(
+            text,
+            "version = \"zero\"\nalp = 14.0\nrs6 = 1.261\nrs8 = 1.0\ns6 = 1.0\ns8 = 1.703\n\
+             s9 = 1.0\ndoi = \"10.1063/1.3382344\"\n"
+        );
+        let parsed: DFTD3DampingParam = toml::from_str(&text).unwrap();
+        assert_eq!(toml::to_string(&parsed).unwrap(), text);
+        let parsed: DFTD3DampingParamEnum = toml::from_str("method = \"b3lyp\"").unwrap();
+        assert_eq!(parsed.version(), "bj");
+        assert!(!toml::to_string(&parsed).unwrap().contains("doi"));
+    }
+
+    #[cfg(feature = "api-v0_4")]
+    #[test]
+    fn test_register_method_alias() {
+        let mut db = ParameterDatabase::embedded().clone();
+        let param =
+            DFTD3RationalDampingParamBuilder::default().a1(0.42).s8(1.5).a2(4.2).build().unwrap();
+        assert!(db.register_method("My-Func", "d3bj", param.clone(), Some("10.0/x")).is_none());
+        assert_eq!(db.doi("myfunc", "bj").as_deref(), Some("10.0/x"));
+        assert!(db.register_method("myfunc", "bj", param, None).is_some());
+        let zero = DFTD3ZeroDampingParamBuilder::default().s8(1.0).rs6(1.2).build().unwrap();
+        assert!(db.register_method_f("myfunc", "bj", zero, None).is_err());
+
+        db.register_alias("pbeh", "PBE0");
+        db.register_alias("myfunc-alias", "my_func");
+        assert_eq!(db.resolve_alias("PBEh"), Some("pbe0"));
+        assert_eq!(db.resolve_method("myfuncalias"), Some("My-Func"));
+        assert_eq!(db.get_damping_param("pbeh", "bj").param.get_field("a1"), Some(0.4145));
+        assert!(db.register_alias_f("b3lyp", "pbe0").is_err());
+        assert!(db.register_alias_f("x", "unknown").is_err());
+    }
+
+    #[test]
+    fn test_lookup_suggestions() {
+        assert_eq!(edit_distance("b3lpy", "b3lyp"), 2);
+        assert_eq!(edit_distance("", "bj"), 2);
+        assert_eq!(edit_distance("pbe0", "pbe0"), 0);
+
+        let db = ParameterDatabase::embedded();
+        assert_eq!(db.suggest_methods("B3-LPY", 5)[0], "b3lyp");
+        assert!(db.suggest_methods("completely-unrelated-name", 5).is_empty());
+        assert_eq!(db.diagnose(Some("b3lyp"), "bj"), None);
+        assert_eq!(
+            db.diagnose(Some("b3lyp"), "bjx"),
+            Some(ParameterLookupIssue::UnknownVariant {
+                version: "bjx".into(),
+                suggestions: vec!["bj".into(), "bjm".into()]
+            })
+        );
+        let issue = db.diagnose(Some("slaterdirac"), "d3bj").unwrap();
+        assert_eq!(
+            issue.to_string(),
+            "Variant 'bj' not found for method 'slaterdirac'; slaterdirac has zero"
+        );
+        let message = db.merged_table("b3lpy", "bj").unwrap_err().get_message();
+        assert!(message.starts_with("Method 'b3lpy' not found in database; did you mean b3lyp"));
+    }
+
     #[test]
     fn test_normalize_version() {
         assert_eq!(normalize_version("d3bj"), "bj");
@@ -8,6 +8,10 @@
 //!
 //! - **Usual case with method**: `{version = "d3bj", method = "b3lyp"}` Lookup
 //!   B3LYP-D3(BJ) parameters from the database.
+//! - **Method without version**: `{method = "b3lyp"}` The recommended variant
+//!   of the method is used, see
+//!   [`dftd3_default_variant`](crate::parameters::dftd3_default_variant); it is
+//!   reported by [`DFTD3DampingParam::version`].
 //! - **Version without d3 prefix**: `{version = "bj", method = "b3lyp"}` The
 //!   `d3` prefix is optional. The `version` field is case-insensitive, so `BJ`
 //!   works too.
@@ -23,10 +27,24 @@
 //! - **Method name normalization**: `{version = "zero", method = "m06-2x"}`
 //!   Separators like `-`, `_` are removed automatically (normalized to `m062x`
 //!   for lookup).
+//! - **Method label**: `{method = "B3LYP-D3(BJ)-ATM"}` The variant is taken
+//!   from the label, see [`dftd3_parse_method_label`]; `version`, if given,
+//!   must agree. Unless `atm` is given, the ATM term is only included for
+//!   `-ATM`, as in [`dftd3_get_damping_param_from_label`]; `atm = false`
+//!   conflicts with `-ATM` and is an error.
+//! - **Reference DOI**: `{version = "d3bj", a1 = 0.3981, s8 = 1.9889, a2 =
+//!   4.4211, doi = "10.1002/jcc.21759"}` Sets the DOI of the parameters; with
+//!   `method`, replaces the DOI of the database entry.
 //! - **Invalid field error**: `{version = "d3bj", method = "b3lyp", rs6 = 0.5}`
 //!   Returns an error because `rs6` is not a valid parameter for the `bj`
 //!   variant.
 //!
+//! # Provenance
+//!
+//! [`dftd3_explain_damping_param_f`] parses the same input and reports, for
+//! every field, the final value and its origin (variant default, database
+//! entry, user override or `atm` flag), e.g. for audit logs of calculations.
+//!
 //! # Example
 //!
 //! ```
@@ -56,25 +74,19 @@
 use crate::interface::DFTD3Error;
 use crate::parameters::{
     convert_to_damping_param, get_default_param_table, get_merged_param_table, normalize_version,
-    DFTD3DampingParam,
+    unknown_variant_error, variant_fields, DFTD3DampingParam, ParameterDatabase, DATABASE_VARIANTS,
+    SUGGESTION_LIMIT,
 };
+use serde::Serialize;
 use toml::Table;
 
 /// Meta-fields that control parsing but are not damping parameters themselves.
-const META_FIELDS: &[&str] = &["version", "method", "atm"];
+const META_FIELDS: &[&str] = &["version", "method", "atm", "doi"];
 
 /// Valid damping parameter fields for each version.
 #[cfg(feature = "api-v0_4")]
 fn valid_fields_for_version(version: &str) -> Result<&[&str], DFTD3Error> {
     match version {
-        "bj" => Ok(&["s6", "s8", "s9", "a1", "a2", "alp"]),
-        "zero" => Ok(&["s6", "s8", "s9", "rs6", "rs8", "alp"]),
-        "bjm" => Ok(&["s6", "s8", "s9", "a1", "a2", "alp"]),
-        "zerom" => Ok(&["s6", "s8", "s9", "rs6", "rs8", "alp", "bet"]),
-        #[cfg(feature = "api-v0_5")]
-        "op" => Ok(&["s6", "s8", "s9", "a1", "a2", "alp", "bet"]),
-        #[cfg(feature = "api-v1_3")]
-        "cso" => Ok(&["s6", "s9", "a1", "a2", "a3", "a4", "alp"]),
         #[cfg(not(feature = "api-v0_5"))]
         "op" => Err(DFTD3Error::ParametersError(format!(
             "Variant '{version}' requires api-v0_5 feature"
@@ -83,21 +95,25 @@ fn valid_fields_for_version(version: &str) -> Result<&[&str], DFTD3Error> {
         "cso" => Err(DFTD3Error::ParametersError(format!(
             "Variant '{version}' requires api-v1_3 feature"
         ))),
-        _ => Err(DFTD3Error::ParametersError(format!("Unknown variant: {version}"))),
+        _ => variant_fields(version).ok_or_else(|| unknown_variant_error(version)),
     }
 }
 
 /// Parse damping parameters from a TOML table.
 ///
-/// The table must contain a `version` field specifying the DFT-D3 variant.
-/// Optional `method` field triggers a database lookup, and `atm` controls
-/// the three-body dispersion term (s9). Remaining fields are treated as
-/// damping parameters or overrides.
+/// The `version` field specifies the DFT-D3 variant; it may be omitted if a
+/// `method` is given, which then uses the recommended variant of the method
+/// ([`dftd3_default_variant`](crate::parameters::dftd3_default_variant)), or
+/// the variant of a method label. Optional `method` field triggers a database
+/// lookup, `atm` controls the three-body dispersion term (s9), and `doi` sets
+/// the reference DOI. Remaining fields are treated as damping parameters or
+/// overrides.
 ///
 /// # Errors
 ///
 /// Returns an error if:
-/// - `version` is missing or unrecognized
+/// - `version` is unrecognized, or missing without `method`
+/// - `method` has none of the default variants if `version` is missing
 /// - `method` is specified but not found in the database
 /// - A field not valid for the given variant is present
 /// - Required damping parameters are missing
@@ -106,18 +122,138 @@ pub fn dftd3_parse_damping_param(input: &Table) -> DFTD3DampingParam {
 }
 
 pub fn dftd3_parse_damping_param_f(input: &Table) -> Result<DFTD3DampingParam, DFTD3Error> {
-    // 1. Extract version (required)
-    let version_raw = input
-        .get("version")
-        .and_then(|v| v.as_str())
-        .ok_or_else(|| DFTD3Error::ParametersError("Missing required field 'version'".into()))?;
-    let version = normalize_version(version_raw);
+    dftd3_explain_damping_param_f(input).map(|explanation| explanation.param)
+}
 
-    // 2. Extract method (optional)
-    let method = input.get("method").and_then(|v| v.as_str());
+/* #region Explain mode */
 
-    // 3. Extract atm flag (optional, default true)
-    let atm = input.get("atm").and_then(|v| v.as_bool()).unwrap_or(true);
+/// Origin of the value of a damping parameter field.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DFTD3ParamSource {
+    /// Variant default (`[default.parameter]`); `source` is the file it was
+    /// merged from, `None` for the embedded database.
+    VariantDefault {
+        /// Parameter file, `None` for the embedded database.
+        source: Option<String>,
+    },
+    /// Entry of the method in the database.
+    Database {
+        /// Parameter file, `None` for the embedded database.
+        source: Option<String>,
+    },
+    /// Given in the input.
+    UserOverride,
+    /// `s9 = 0` set by `atm = false`.
+    Atm,
+    /// Default of the parameter struct, absent in the database.
+    Builtin,
+}
+
+impl std::fmt::Display for DFTD3ParamSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use DFTD3ParamSource::*;
+        let (kind, source) = match self {
+            VariantDefault { source } => ("variant default", source),
+            Database { source } => ("database", source),
+            UserOverride => return write!(f, "user override"),
+            Atm => return write!(f, "atm = false"),
+            Builtin => return write!(f, "built-in default"),
+        };
+        write!(f, "{kind}, {}", source.as_deref().unwrap_or("embedded"))
+    }
+}
+
+/// Final value of a damping parameter field and its origin.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DFTD3FieldExplanation {
+    /// Parameter field, e.g. `a1`.
+    pub field: String,
+    /// Final value.
+    pub value: f64,
+    /// Origin of the value.
+    pub source: DFTD3ParamSource,
+}
+
+/// Damping parameters with the origin of every field, see
+/// [`dftd3_explain_damping_param_f`].
+#[derive(Debug, Clone, Serialize)]
+pub struct DFTD3ParamExplanation {
+    /// Resolved damping parameters (with DOI).
+    pub param: DFTD3DampingParam,
+    /// Method key of the database, if a method was given.
+    pub method: Option<String>,
+    /// Normalized variant.
+    pub version: String,
+    /// Fields of the variant, sorted by name.
+    pub fields: Vec<DFTD3FieldExplanation>,
+}
+
+impl std::fmt::Display for DFTD3ParamExplanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let method = self.method.as_deref().unwrap_or("(none)");
+        let doi = self.param.doi.as_deref().unwrap_or("(none)");
+        writeln!(
+            f,
+            "DFT-D3 damping parameters: method {method}, variant {}, doi {doi}",
+            self.version
+        )?;
+        for DFTD3FieldExplanation { field, value, source } in &self.fields {
+            writeln!(f, "  {field} = {value:?} ({source})")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse damping parameters from a TOML table, with the origin of every field
+/// (panics on error).
+///
+/// See [`dftd3_explain_damping_param_f`].
+pub fn dftd3_explain_damping_param(input: &Table) -> DFTD3ParamExplanation {
+    dftd3_explain_damping_param_f(input).unwrap()
+}
+
+/// Parse damping parameters from a TOML table, with the origin of every field
+/// (fallible version).
+///
+/// Parsing is the same as [`dftd3_parse_damping_param_f`]; each field of the
+/// result is attributed to, in order of precedence, the `atm` flag, a user
+/// override, the database entry of the method, the variant default, or the
+/// default of the parameter struct.
+///
+/// # Example
+///
+/// ```
+/// # #[cfg(feature = "api-v0_4")]
+/// # {
+/// use dftd3::prelude::*;
+///
+/// let explanation = dftd3_explain_damping_param_from_toml(r#"{method = "b3lyp", a1 = 0.5}"#);
+/// let a1 = explanation.fields.iter().find(|f| f.field == "a1").unwrap();
+/// assert_eq!(a1.source, DFTD3ParamSource::UserOverride);
+/// println!("{explanation}");
+/// # }
+/// ```
+pub fn dftd3_explain_damping_param_f(input: &Table) -> Result<DFTD3ParamExplanation, DFTD3Error> {
+    // 1. Extract version, method (optional) and atm flag (optional, default true);
+    //    a method label like "B3LYP-D3(BJ)-ATM" may provide version and atm
+    let version_raw = input.get("version").and_then(|v| v.as_str());
+    let method_raw = input.get("method").and_then(|v| v.as_str());
+    let atm = input.get("atm").and_then(|v| v.as_bool());
+    let (method, version, atm) = match method_raw {
+        Some(method) => {
+            let (method, version, atm) = resolve_method_label(method, version_raw, atm)?;
+            (Some(method), version, atm)
+        },
+        None => (None, version_raw.map(normalize_version), atm.unwrap_or(true)),
+    };
+    let version = match (version, &method) {
+        (Some(version), _) => version,
+        (None, Some(method)) => ParameterDatabase::global_f()?.default_variant(method)?,
+        (None, None) => {
+            return Err(DFTD3Error::ParametersError("Missing required field 'version'".into()))
+        },
+    };
 
     // 4. Check whether s9 is explicitly provided by user
     let s9_explicit = input.contains_key("s9");
@@ -134,7 +270,7 @@ pub fn dftd3_parse_damping_param_f(input: &Table) -> Result<DFTD3DampingParam, D
                     "Variant '{version}' requires api-v0_4 feature or higher"
                 )))
             },
-            _ => return Err(DFTD3Error::ParametersError(format!("Unknown variant: {version}"))),
+            _ => return Err(unknown_variant_error(version)),
         }
     }
 
@@ -152,7 +288,7 @@ pub fn dftd3_parse_damping_param_f(input: &Table) -> Result<DFTD3DampingParam, D
     }
 
     // 6. Build the merged parameter table
-    let mut merged = if let Some(method) = method {
+    let mut merged = if let Some(method) = &method {
         // Method lookup: get base table from database
         get_merged_param_table(method, &version)?
     } else {
@@ -167,6 +303,14 @@ pub fn dftd3_parse_damping_param_f(input: &Table) -> Result<DFTD3DampingParam, D
         }
     }
 
+    // DOI of the user replaces the DOI of the database entry
+    if let Some(doi) = input.get("doi") {
+        if !doi.is_str() {
+            return Err(DFTD3Error::ParametersError("Field 'doi' must be a string".into()));
+        }
+        merged.insert("doi".to_string(), doi.clone());
+    }
+
     // 8. Handle atm -> s9 relationship If s9 is explicitly provided, it takes
     //    precedence over atm. If s9 is not explicit and atm is false, set s9 = 0.0.
     if !atm && !s9_explicit {
@@ -177,9 +321,227 @@ pub fn dftd3_parse_damping_param_f(input: &Table) -> Result<DFTD3DampingParam, D
     merged.remove("damping");
 
     // 10. Convert to DFTD3DampingParam
-    convert_to_damping_param(&merged, &version)
+    let param = convert_to_damping_param(&merged, &version)?;
+
+    // 11. Attribute fields of the result to their origin
+    let db = ParameterDatabase::global_f()?;
+    let method = method.map(|m| db.resolve_method(&m).map(str::to_string).unwrap_or(m));
+    let entry = method.as_deref().and_then(|m| db.raw_entry(m, &version));
+    let defaults = db.defaults(&version);
+    let fields = param
+        .param
+        .to_table()
+        .into_iter()
+        .map(|(field, value)| {
+            let source = if field == "s9" && !atm && !s9_explicit {
+                DFTD3ParamSource::Atm
+            } else if user_param_keys.contains(&field.as_str()) {
+                DFTD3ParamSource::UserOverride
+            } else if entry.is_some_and(|e| e.contains_key(&field)) {
+                let source = db.source(method.as_deref(), &version).map(str::to_string);
+                DFTD3ParamSource::Database { source }
+            } else if defaults.is_some_and(|d| d.contains_key(&field)) {
+                let source = db.source(None, &version).map(str::to_string);
+                DFTD3ParamSource::VariantDefault { source }
+            } else {
+                DFTD3ParamSource::Builtin
+            };
+            // plain structs of floats
+            let value = value.as_float().unwrap_or_default();
+            DFTD3FieldExplanation { field, value, source }
+        })
+        .collect();
+    Ok(DFTD3ParamExplanation { param, method, version, fields })
+}
+
+/// Parse damping parameters from a TOML string, with the origin of every
+/// field (panics on error).
+///
+/// See [`dftd3_explain_damping_param_f`].
+pub fn dftd3_explain_damping_param_from_toml(input: &str) -> DFTD3ParamExplanation {
+    dftd3_explain_damping_param_from_toml_f(input).unwrap()
+}
+
+/// Parse damping parameters from a TOML string, with the origin of every
+/// field (fallible version).
+///
+/// # See also
+///
+/// [`dftd3_explain_damping_param_from_toml`]
+pub fn dftd3_explain_damping_param_from_toml_f(
+    input: &str,
+) -> Result<DFTD3ParamExplanation, DFTD3Error> {
+    dftd3_explain_damping_param_f(&parse_toml_table(input)?)
+}
+
+/* #endregion */
+
+/* #region Method labels */
+
+/// Method label split into method, damping variant and ATM flag.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DFTD3MethodLabel {
+    /// Method key of the parameter database.
+    pub method: String,
+    /// Normalized damping variant (`bj`, `zero`, `bjm`, `zerom`, `op`, `cso`).
+    pub version: String,
+    /// Whether the label requests the ATM three-body term (`-ATM` suffix).
+    pub atm: bool,
 }
 
+/// Parse a method label like "B3LYP-D3(BJ)-ATM" (panics on error).
+///
+/// The label is `<method>-D3<variant>[-ATM]`, case-insensitive, where `-` may
+/// also be `_`, space or `+`:
+///
+/// - `<variant>` is optional and may be parenthesized: `D3` and `D3(0)` are
+///   zero damping, `D3(BJ)` and `D3BJ` rational damping, `D3M(BJ)` and
+///   `D3(BJM)` modified rational damping, `D3M(0)` modified zero damping,
+///   `D3(op)` and `D3(CSO)` the respective variants;
+/// - `<method>` is normalized as in database lookups; `B97-D3` resolves to the
+///   `b97d` entry, as the D3 parameters of B97-D are listed there.
+///
+/// # Example
+///
+/// ```
+/// use dftd3::prelude::*;
+///
+/// let label = dftd3_parse_method_label("B2PLYP-D3BJ-ATM");
+/// assert_eq!((label.method.as_str(), label.version.as_str(), label.atm), ("b2plyp", "bj", true));
+/// let label = dftd3_parse_method_label("wB97X-D3");
+/// assert_eq!((label.method.as_str(), label.version.as_str(), label.atm), ("wb97x", "zero", false));
+/// ```
+pub fn dftd3_parse_method_label(label: &str) -> DFTD3MethodLabel {
+    dftd3_parse_method_label_f(label).unwrap()
+}
+
+/// Parse a method label like "B3LYP-D3(BJ)-ATM" (fallible version).
+///
+/// # Errors
+///
+/// Returns an error naming the part of the label that did not resolve: a
+/// missing `-D3` part, an unknown damping variant, or a method not found in
+/// the database.
+///
+/// # See also
+///
+/// [`dftd3_parse_method_label`]
+pub fn dftd3_parse_method_label_f(label: &str) -> Result<DFTD3MethodLabel, DFTD3Error> {
+    let (method, variant, atm) = split_method_label(label).ok_or_else(|| {
+        DFTD3Error::ParametersError(format!(
+            "Label '{label}' has no dispersion part, expected e.g. 'B3LYP-D3(BJ)'"
+        ))
+    })?;
+    let version = label_variant(variant).ok_or_else(|| {
+        DFTD3Error::ParametersError(format!(
+            "Unknown damping variant 'D3{variant}' in label '{label}'"
+        ))
+    })?;
+    let db = ParameterDatabase::global_f()?;
+    let resolved = db.resolve_method(method).or_else(|| db.resolve_method(&format!("{method}d")));
+    let method = resolved.ok_or_else(|| {
+        let mut message = format!("Method '{method}' of label '{label}' not found in database");
+        let suggestions = db.suggest_methods(method, SUGGESTION_LIMIT);
+        if !suggestions.is_empty() {
+            message += &format!("; did you mean {}?", suggestions.join(", "));
+        }
+        DFTD3Error::ParametersError(message)
+    })?;
+    Ok(DFTD3MethodLabel { method: method.to_string(), version, atm })
+}
+
+/// Get damping parameters for a method label like "B3LYP-D3(BJ)-ATM" (panics
+/// on error).
+///
+/// Following literature convention, the ATM term is only included (`s9` of
+/// the database) if the label has the `-ATM` suffix, otherwise `s9 = 0`.
+pub fn dftd3_get_damping_param_from_label(label: &str) -> DFTD3DampingParam {
+    dftd3_get_damping_param_from_label_f(label).unwrap()
+}
+
+/// Get damping parameters for a method label (fallible version).
+///
+/// # See also
+///
+/// [`dftd3_get_damping_param_from_label`]
+pub fn dftd3_get_damping_param_from_label_f(label: &str) -> Result<DFTD3DampingParam, DFTD3Error> {
+    let label = dftd3_parse_method_label_f(label)?;
+    let mut input = Table::new();
+    input.insert("version".into(), label.version.into());
+    input.insert("method".into(), label.method.into());
+    input.insert("atm".into(), label.atm.into());
+    dftd3_parse_damping_param_f(&input)
+}
+
+/// Resolve `method` of a lookup, which may be a method label; returns method,
+/// normalized version and atm flag.
+///
+/// Method names of the database are never treated as labels. The variant of a
+/// label must agree with `version` if both are given, and `-ATM` must not be
+/// combined with `atm = false`. If `atm` is not given, it defaults to `true`
+/// for method names and to the `-ATM` suffix for labels.
+pub(crate) fn resolve_method_label(
+    method: &str,
+    version: Option<&str>,
+    atm: Option<bool>,
+) -> Result<(String, Option<String>, bool), DFTD3Error> {
+    let version = version.map(normalize_version);
+    let is_method = ParameterDatabase::global_f()
+        .map(|db| db.resolve_method(method).is_some())
+        .unwrap_or(false);
+    if is_method || split_method_label(method).is_none() {
+        return Ok((method.to_string(), version, atm.unwrap_or(true)));
+    }
+    let label = dftd3_parse_method_label_f(method)?;
+    if let Some(version) = version.filter(|v| *v != label.version) {
+        return Err(DFTD3Error::ParametersError(format!(
+            "Variant '{version}' conflicts with variant '{}' of label '{method}'",
+            label.version
+        )));
+    }
+    if label.atm && atm == Some(false) {
+        return Err(DFTD3Error::ParametersError(format!(
+            "atm = false conflicts with the -ATM suffix of label '{method}'"
+        )));
+    }
+    Ok((label.method, Some(label.version), atm.unwrap_or(label.atm)))
+}
+
+/// Split label into method, variant (after `D3`) and ATM suffix.
+fn split_method_label(label: &str) -> Option<(&str, &str, bool)> {
+    let is_sep = |b: u8| matches!(b, b'-' | b'_' | b' ' | b'+');
+    let mut rest = label.trim();
+    let lower = rest.to_ascii_lowercase();
+    let n = lower.len();
+    let atm = n > 4 && lower.ends_with("atm") && is_sep(lower.as_bytes()[n - 4]);
+    if atm {
+        rest = rest[..n - 4].trim_end_matches(|c: char| c.is_ascii() && is_sep(c as u8));
+    }
+    let lower = rest.to_ascii_lowercase();
+    let i = lower
+        .rmatch_indices("d3")
+        .map(|(i, _)| i)
+        .find(|&i| i > 1 && is_sep(lower.as_bytes()[i - 1]))?;
+    Some((rest[..i - 1].trim(), &rest[i + 2..], atm))
+}
+
+/// Normalized variant of the part of a label after `D3` (e.g. `(BJ)`, `M(0)`).
+fn label_variant(variant: &str) -> Option<String> {
+    let variant: String = variant
+        .to_ascii_lowercase()
+        .chars()
+        .filter(|c| !matches!(c, '(' | ')' | '-' | '_' | ' '))
+        .collect();
+    let version = match variant.as_str() {
+        "" | "0" => "zero".to_string(),
+        "m0" | "0m" => "zerom".to_string(),
+        v => normalize_version(v),
+    };
+    DATABASE_VARIANTS.contains(&version.as_str()).then_some(version)
+}
+
+/* #endregion */
+
 /// Parse a TOML string to a table. Supports both standard TOML documents
 /// and inline table syntax like `{version = "bj", method = "b3lyp"}`.
 fn parse_toml_table(input: &str) -> Result<Table, DFTD3Error> {
@@ -231,7 +593,7 @@ pub fn dftd3_parse_damping_param_from_toml(input: &str) -> DFTD3DampingParam {
 ///
 /// Returns an error if:
 /// - TOML parsing fails
-/// - Required field `version` is missing
+/// - Required field `version` is missing (without `method`)
 /// - Method not found in database
 /// - Unknown parameter field for the given variant
 /// - Parameter deserialization fails
@@ -335,6 +697,54 @@ fn json_value_to_toml(value: &serde_json::Value) -> Result<toml::Value, DFTD3Err
     }
 }
 
+#[test]
+fn test_split_method_label() {
+    assert_eq!(split_method_label("B3LYP-D3(BJ)"), Some(("B3LYP", "(BJ)", false)));
+    assert_eq!(split_method_label("B2PLYP-D3BJ-ATM"), Some(("B2PLYP", "BJ", true)));
+    assert_eq!(split_method_label("revPBE-D3(op)"), Some(("revPBE", "(op)", false)));
+    assert_eq!(split_method_label("M06-2X_D3 + ATM"), Some(("M06-2X", "", true)));
+    assert_eq!(split_method_label("b3lyp"), None);
+    assert_eq!(split_method_label("d3"), None);
+    assert_eq!(label_variant("(0)").as_deref(), Some("zero"));
+    assert_eq!(label_variant("M(BJ)").as_deref(), Some("bjm"));
+    assert_eq!(label_variant("M(0)").as_deref(), Some("zerom"));
+    assert_eq!(label_variant("(CSO)").as_deref(), Some("cso"));
+    assert_eq!(label_variant("(XYZ)"), None);
+}
+
+#[cfg(feature = "api-v0_4")]
+#[test]
+fn test_explain_damping_param() {
+    use DFTD3ParamSource::*;
+    let explanation =
+        dftd3_explain_damping_param_from_toml(r#"{method = "B3-LYP", a1 = 0.5, atm = false}"#);
+    assert_eq!(explanation.method.as_deref(), Some("b3lyp"));
+    assert_eq!(explanation.version, "bj");
+    let sources: Vec<(&str, &DFTD3ParamSource)> =
+        explanation.fields.iter().map(|f| (f.field.as_str(), &f.source)).collect();
+    let (embedded_default, embedded) = (VariantDefault { source: None }, Database { source: None });
+    assert_eq!(sources, [
+        ("a1", &UserOverride),
+        ("a2", &embedded),
+        ("alp", &embedded_default),
+        ("s6", &embedded_default),
+        ("s8", &embedded),
+        ("s9", &Atm),
+    ]);
+    assert!(explanation.to_string().starts_with(
+        "DFT-D3 damping parameters: method b3lyp, variant bj, doi 10.1002/jcc.21759\n  a1 = 0.5 \
+         (user override)\n  a2 = 4.4211 (database, embedded)\n"
+    ));
+
+    // direct parameters; s9 takes precedence over atm
+    let explanation = dftd3_explain_damping_param_from_toml(
+        r#"{version = "zerom", rs6 = 1.2, s8 = 1.0, bet = 0.1, s9 = 1.0, atm = false}"#,
+    );
+    assert_eq!(explanation.method, None);
+    let s9 = explanation.fields.iter().find(|f| f.field == "s9").unwrap();
+    assert_eq!((s9.value, &s9.source), (1.0, &UserOverride));
+}
+
 #[test]
 fn test_dftd3_parse_damping_param_from_toml_doc() {
     use crate::prelude::*;
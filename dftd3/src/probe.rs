@@ -0,0 +1,338 @@
+//! Dispersion probe grid maps, written as Gaussian cube files.
+//!
+//! A probe atom (for example C or Ar) is placed on a 3D grid around a host
+//! molecule, or inside the unit cell of a periodic host. At each grid point the
+//! D3 interaction energy of the probe with the host is evaluated as
+//!
+//! ```text
+//! E_int(r) = E(host + probe at r) - E(host) - E(probe)
+//! ```
+//!
+//! where `E(probe)` is the self-interaction of the probe with its own periodic
+//! images (zero for molecular hosts). Points closer than `min_distance` to a
+//! host atom are not evaluated and stored as `NaN`, also in cube files.
+//!
+//! Grid points are evaluated in parallel. Each worker thread reuses its own
+//! [`DFTD3Model`] of host and probe, and only updates the probe position.
+//! Damping parameters are given as a parameter struct, from which every
+//! worker thread builds its own [`DFTD3Param`], as library handles are not
+//! shared between threads.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use dftd3::prelude::*;
+//!
+//! # let numbers: Vec<usize> = vec![];
+//! # let positions: Vec<f64> = vec![];
+//! let param = dftd3_get_damping_param("pbe0", "bj");
+//! let options = DFTD3ProbeGridOptionsBuilder::default().probe(18).spacing(0.4).build().unwrap();
+//! let grid = dftd3_probe_grid(&numbers, &positions, None, None, &param, &options);
+//! grid.write_cube_file("probe_ar.cube").unwrap();
+//! ```
+
+use crate::interface::*;
+use crate::utils::*;
+use derive_builder::Builder;
+use rayon::prelude::*;
+use std::io::Write;
+
+/* #region Probe grid options */
+
+/// Options for probe grid maps (quantities in Bohr).
+#[derive(Builder, Debug, Clone)]
+#[builder(pattern = "owned", build_fn(error = "DFTD3Error"))]
+pub struct DFTD3ProbeGridOptions {
+    #[builder(default = 6)]
+    #[doc = r"optional, default 6 (carbon); element index of probe atom"]
+    pub probe: usize,
+    #[builder(default = 0.5)]
+    #[doc = r"optional, default 0.5; grid spacing"]
+    pub spacing: f64,
+    #[builder(default = 6.0)]
+    #[doc = r"optional, default 6.0; margin around molecular hosts, not used for periodic hosts"]
+    pub margin: f64,
+    #[builder(default = 1.0)]
+    #[doc = r"optional, default 1.0; points closer to any host atom are not evaluated and set to NaN"]
+    pub min_distance: f64,
+}
+
+impl Default for DFTD3ProbeGridOptions {
+    fn default() -> Self {
+        DFTD3ProbeGridOptionsBuilder::default().build().unwrap()
+    }
+}
+
+/* #endregion */
+
+/* #region Probe grid */
+
+/// Probe interaction energies on a regular grid.
+///
+/// Grid point `(i, j, k)` is located at `origin + i * axes[0] + j * axes[1] +
+/// k * axes[2]`, and its value is stored at `values[(i * shape[1] + j) *
+/// shape[2] + k]` (the cube file ordering).
+#[derive(Debug, Clone)]
+pub struct DFTD3ProbeGrid {
+    /// Origin of the grid in Bohr.
+    pub origin: [f64; 3],
+    /// Voxel vectors in Bohr.
+    pub axes: [[f64; 3]; 3],
+    /// Number of points along each voxel vector.
+    pub shape: [usize; 3],
+    /// Probe interaction energies in Hartree; `NaN` for points excluded by
+    /// [`DFTD3ProbeGridOptions::min_distance`].
+    pub values: Vec<f64>,
+    /// Element index of probe atom.
+    pub probe: usize,
+    /// Element index of host atoms.
+    pub numbers: Vec<usize>,
+    /// Host atomic positions in Bohr (natom * 3).
+    pub positions: Vec<f64>,
+}
+
+impl DFTD3ProbeGrid {
+    /// Cartesian position of grid point `(i, j, k)` in Bohr.
+    pub fn point(&self, i: usize, j: usize, k: usize) -> [f64; 3] {
+        let [a, b, c] = self.axes;
+        std::array::from_fn(|x| {
+            self.origin[x] + i as f64 * a[x] + j as f64 * b[x] + k as f64 * c[x]
+        })
+    }
+
+    /// Write the grid in Gaussian cube format.
+    ///
+    /// Excluded points are written as `NaN`, as noted in the second comment
+    /// line; viewers that do not accept `NaN` need the values masked first.
+    pub fn write_cube<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writeln!(writer, "DFT-D3 probe interaction energy")?;
+        writeln!(
+            writer,
+            "probe element {}, energies in Hartree, NaN for points too close to host atoms",
+            self.probe
+        )?;
+        let [ox, oy, oz] = self.origin;
+        writeln!(writer, "{:5} {:12.6} {:12.6} {:12.6}", self.numbers.len(), ox, oy, oz)?;
+        for (n, [x, y, z]) in self.shape.iter().zip(self.axes) {
+            writeln!(writer, "{n:5} {x:12.6} {y:12.6} {z:12.6}")?;
+        }
+        for (&z, r) in self.numbers.iter().zip(self.positions.chunks(3)) {
+            let (x, y, w) = (r[0], r[1], r[2]);
+            writeln!(writer, "{z:5} {:12.6} {x:12.6} {y:12.6} {w:12.6}", z as f64)?;
+        }
+        for row in self.values.chunks(self.shape[2]) {
+            for line in row.chunks(6) {
+                let line: String = line.iter().map(|v| format!(" {v:12.5E}")).collect();
+                writeln!(writer, "{line}")?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Write the grid to a Gaussian cube file.
+    pub fn write_cube_file(&self, path: impl AsRef<std::path::Path>) -> std::io::Result<()> {
+        let mut writer = std::io::BufWriter::new(std::fs::File::create(path)?);
+        self.write_cube(&mut writer)?;
+        writer.flush()
+    }
+}
+
+/* #endregion */
+
+/* #region Probe grid driver */
+
+/// Compute the probe interaction energy map around a host.
+///
+/// - `numbers` - element index (6 for O, 7 for N) of the host
+/// - `positions` - host atomic positions in Bohr (natom * 3)
+/// - `lattice` - optional, lattice parameters (3 * 3); if given, the grid spans
+///   the unit cell
+/// - `periodic` - optional, periodicity (3)
+/// - `param` - damping parameters
+/// - `options` - probe element, grid spacing and margin
+pub fn dftd3_probe_grid<P: DFTD3ParamAPI + Clone + Sync>(
+    numbers: &[usize],
+    positions: &[f64],
+    lattice: Option<&[f64]>,
+    periodic: Option<&[bool]>,
+    param: &P,
+    options: &DFTD3ProbeGridOptions,
+) -> DFTD3ProbeGrid {
+    dftd3_probe_grid_f(numbers, positions, lattice, periodic, param, options).unwrap()
+}
+
+/// Compute the probe interaction energy map around a host (failable).
+///
+/// # See also
+///
+/// [`dftd3_probe_grid`]
+pub fn dftd3_probe_grid_f<P: DFTD3ParamAPI + Clone + Sync>(
+    numbers: &[usize],
+    positions: &[f64],
+    lattice: Option<&[f64]>,
+    periodic: Option<&[bool]>,
+    param: &P,
+    options: &DFTD3ProbeGridOptions,
+) -> Result<DFTD3ProbeGrid, DFTD3Error> {
+    check_structure_dims(numbers, positions, lattice, periodic)?;
+    if options.spacing <= 0.0 {
+        return Err(DFTD3Error::Rust(format!(
+            "Grid spacing should be positive, got {}",
+            options.spacing
+        )));
+    }
+
+    // grid layout
+    let (origin, axes, shape) = match lattice {
+        Some(lattice) => {
+            let vecs: [[f64; 3]; 3] =
+                std::array::from_fn(|i| [lattice[3 * i], lattice[3 * i + 1], lattice[3 * i + 2]]);
+            let shape = vecs.map(|v| {
+                let len = v.iter().map(|x| x * x).sum::<f64>().sqrt();
+                ((len / options.spacing).ceil() as usize).max(1)
+            });
+            let axes = std::array::from_fn(|i| vecs[i].map(|x| x / shape[i] as f64));
+            ([0.0; 3], axes, shape)
+        },
+        None => {
+            let lo: [f64; 3] = std::array::from_fn(|x| {
+                positions.chunks(3).map(|r| r[x]).fold(f64::INFINITY, f64::min) - options.margin
+            });
+            let hi: [f64; 3] = std::array::from_fn(|x| {
+                positions.chunks(3).map(|r| r[x]).fold(f64::NEG_INFINITY, f64::max) + options.margin
+            });
+            let shape = std::array::from_fn(|x| ((hi[x] - lo[x]) / options.spacing) as usize + 1);
+            let mut axes = [[0.0; 3]; 3];
+            (0..3).for_each(|x| axes[x][x] = options.spacing);
+            (lo, axes, shape)
+        },
+    };
+
+    // neighbouring cell translations, for distance checks of periodic hosts
+    let mut images = vec![[0.0; 3]];
+    if let Some(lattice) = lattice {
+        let periodic = periodic.map_or([true; 3], |p| [p[0], p[1], p[2]]);
+        let range = |k: usize| if periodic[k] { -1..=1 } else { 0..=0 };
+        images.clear();
+        for n0 in range(0) {
+            for n1 in range(1) {
+                for n2 in range(2) {
+                    let n = [n0 as f64, n1 as f64, n2 as f64];
+                    images.push(std::array::from_fn(|x| {
+                        (0..3).map(|i| n[i] * lattice[3 * i + x]).sum::<f64>()
+                    }));
+                }
+            }
+        }
+    }
+
+    // reference energies of host and probe
+    let host_param = param.clone().new_param_f()?;
+    let energy_host = DFTD3Model::new_f(numbers, positions, lattice, periodic)?
+        .get_dispersion_f(&host_param, false)?
+        .energy;
+    let energy_probe = match lattice {
+        Some(_) => {
+            DFTD3Model::new_f(&[options.probe], &[0.0; 3], lattice, periodic)?
+                .get_dispersion_f(&host_param, false)?
+                .energy
+        },
+        None => 0.0,
+    };
+
+    let mut grid = DFTD3ProbeGrid {
+        origin,
+        axes,
+        shape,
+        values: vec![],
+        probe: options.probe,
+        numbers: numbers.to_vec(),
+        positions: positions.to_vec(),
+    };
+
+    let mut full_numbers = numbers.to_vec();
+    full_numbers.push(options.probe);
+    let min_dist2 = options.min_distance.powi(2);
+    let npoints = shape.iter().product::<usize>();
+
+    // interaction energy at a grid point, reusing the model of a worker
+    let point_energy = |model: &mut Option<DFTD3Model>,
+                        param: &DFTD3Param,
+                        idx: usize|
+     -> Result<f64, DFTD3Error> {
+        let (i, j, k) = (idx / (shape[1] * shape[2]), idx / shape[2] % shape[1], idx % shape[2]);
+        let p = grid.point(i, j, k);
+        let too_close = positions.chunks(3).any(|r| {
+            images
+                .iter()
+                .any(|t| (0..3).map(|x| (r[x] + t[x] - p[x]).powi(2)).sum::<f64>() < min_dist2)
+        });
+        if too_close {
+            return Ok(f64::NAN);
+        }
+        let mut full_positions = positions.to_vec();
+        full_positions.extend(p);
+        let model = match model {
+            Some(model) => {
+                model.update_f(&full_positions, lattice)?;
+                model
+            },
+            None => {
+                model.insert(DFTD3Model::new_f(&full_numbers, &full_positions, lattice, periodic)?)
+            },
+        };
+        let energy = model.get_dispersion_f(param, false)?.energy;
+        Ok(energy - energy_host - energy_probe)
+    };
+
+    // one model and library parameter handle per worker thread; errors are
+    // passed on as messages, since error handles are not sent between threads
+    let values = (0..npoints)
+        .into_par_iter()
+        .map_init(
+            || (None, param.clone().new_param_f()),
+            |(model, worker_param), idx| {
+                match worker_param {
+                    Ok(worker_param) => point_energy(model, worker_param, idx),
+                    Err(e) => Err(DFTD3Error::Rust(e.to_string())),
+                }
+                .map_err(|e| e.to_string())
+            },
+        )
+        .collect::<Result<Vec<f64>, String>>()
+        .map_err(DFTD3Error::Rust)?;
+    grid.values = values;
+    Ok(grid)
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_cube() {
+        let grid = DFTD3ProbeGrid {
+            origin: [-1.0, 0.0, 0.0],
+            axes: [[0.5, 0.0, 0.0], [0.0, 0.5, 0.0], [0.0, 0.0, 0.5]],
+            shape: [2, 2, 7],
+            values: (0..28).map(|x| if x == 0 { f64::NAN } else { -1e-4 * x as f64 }).collect(),
+            probe: 18,
+            numbers: vec![8],
+            positions: vec![0.0, 0.0, 0.0],
+        };
+        assert_eq!(grid.point(1, 1, 2), [-0.5, 0.5, 1.0]);
+
+        let mut buffer = vec![];
+        grid.write_cube(&mut buffer).unwrap();
+        let text = String::from_utf8(buffer).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        // 2 comments, origin, 3 axes, 1 atom, 4 rows of 7 values (6 + 1 per row)
+        assert_eq!(lines.len(), 2 + 1 + 3 + 1 + 4 * 2);
+        assert_eq!(lines[2].split_whitespace().next(), Some("1"));
+        assert_eq!(lines[3].split_whitespace().next(), Some("2"));
+        assert_eq!(lines[8].split_whitespace().count(), 1);
+        assert_eq!(lines[7].split_whitespace().next(), Some("NaN"));
+    }
+}
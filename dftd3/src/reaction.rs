@@ -0,0 +1,267 @@
+//! Reaction and conformer dispersion energies over multiple structures.
+//!
+//! A reaction is a set of [`DFTD3Model`]s with stoichiometric coefficients
+//! (negative for reactants, positive for products), so that
+//!
+//! ```text
+//! dE = sum_i nu_i E_i
+//! ```
+//!
+//! Models are constructed once and reused for every damping parameter set, so
+//! the same reaction can be evaluated cheaply over the whole parameter
+//! database.
+//!
+//! # Example
+//!
+//! ```no_run
+//! use dftd3::prelude::*;
+//!
+//! # let (numbers_a, positions_a) = (vec![1_usize], vec![0.0; 3]);
+//! # let (numbers_b, positions_b) = (vec![1_usize], vec![0.0; 3]);
+//! # let (numbers_c, positions_c) = (vec![1_usize], vec![0.0; 3]);
+//! // A + B -> C
+//! let reaction = DFTD3Reaction::new()
+//!     .species("A", DFTD3Model::new(&numbers_a, &positions_a, None, None), -1.0)
+//!     .species("B", DFTD3Model::new(&numbers_b, &positions_b, None, None), -1.0)
+//!     .species("C", DFTD3Model::new(&numbers_c, &positions_c, None, None), 1.0);
+//!
+//! let param = dftd3_load_param("d3bj", "b3lyp", true);
+//! let res = reaction.compute(&param);
+//! println!("reaction dispersion energy: {}", res.energy);
+//!
+//! // every method and variant in the parameter database
+//! let database = reaction.compute_database(true);
+//! for entry in &database.entries {
+//!     println!("{:>12} {:>6} {:16.10}", entry.method, entry.version, entry.output.energy);
+//! }
+//! for failure in &database.failures {
+//!     println!("{:>12} {:>6} failed: {}", failure.method, failure.version, failure.error);
+//! }
+//! ```
+
+use crate::interface::*;
+use crate::parameters::*;
+use crate::parsing::*;
+use toml::Table;
+
+/* #region Reaction output */
+
+/// Reaction dispersion energy result.
+#[derive(Debug, Clone)]
+pub struct DFTD3ReactionOutput {
+    /// Reaction dispersion energy `sum_i nu_i E_i`.
+    pub energy: f64,
+    /// Dispersion energy of each species.
+    pub species_energies: Vec<f64>,
+    /// Contribution `nu_i E_i` of each species.
+    pub contributions: Vec<f64>,
+}
+
+/// Reaction result for one method and variant of the parameter database.
+#[derive(Debug, Clone)]
+pub struct DFTD3ReactionDatabaseEntry {
+    /// Method name (normalized database key).
+    pub method: String,
+    /// DFT-D3 variant (normalized, e.g. `bj`).
+    pub version: String,
+    /// Reaction result.
+    pub output: DFTD3ReactionOutput,
+}
+
+/// Method and variant of the parameter database that could not be evaluated.
+#[derive(Debug, Clone)]
+pub struct DFTD3DatabaseFailure {
+    /// Method name (normalized database key).
+    pub method: String,
+    /// DFT-D3 variant (normalized, e.g. `bj`).
+    pub version: String,
+    /// Error message.
+    pub error: String,
+}
+
+/// Reaction results over the parameter database.
+#[derive(Debug, Clone)]
+pub struct DFTD3ReactionDatabaseOutput {
+    /// Results, sorted by variant and method.
+    pub entries: Vec<DFTD3ReactionDatabaseEntry>,
+    /// Methods and variants skipped because parameters could not be built or
+    /// evaluated.
+    pub failures: Vec<DFTD3DatabaseFailure>,
+}
+
+/* #endregion */
+
+/* #region Reaction */
+
+/// A species of a reaction.
+pub struct DFTD3ReactionSpecies {
+    /// Name of species.
+    pub name: String,
+    /// Dispersion model of species.
+    pub model: DFTD3Model,
+    /// Stoichiometric coefficient (negative for reactants).
+    pub coefficient: f64,
+}
+
+/// Reaction or energy difference defined over multiple structures.
+#[derive(Default)]
+pub struct DFTD3Reaction {
+    species: Vec<DFTD3ReactionSpecies>,
+}
+
+impl DFTD3Reaction {
+    /// Create new empty reaction.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a species with its stoichiometric coefficient (negative for
+    /// reactants, positive for products).
+    pub fn species(mut self, name: &str, model: DFTD3Model, coefficient: f64) -> Self {
+        self.push(name, model, coefficient);
+        self
+    }
+
+    /// Add a species with its stoichiometric coefficient (negative for
+    /// reactants, positive for products).
+    pub fn push(&mut self, name: &str, model: DFTD3Model, coefficient: f64) {
+        self.species.push(DFTD3ReactionSpecies { name: name.to_string(), model, coefficient });
+    }
+
+    /// Species of this reaction.
+    pub fn get_species(&self) -> &[DFTD3ReactionSpecies] {
+        &self.species
+    }
+
+    /// Evaluate the reaction dispersion energy with one damping parameter set.
+    pub fn compute(&self, param: &DFTD3Param) -> DFTD3ReactionOutput {
+        self.compute_f(param).unwrap()
+    }
+
+    /// Evaluate the reaction for every method and variant in the parameter
+    /// database, sorted by variant and method.
+    ///
+    /// Methods that fail are skipped and listed in
+    /// [`DFTD3ReactionDatabaseOutput::failures`].
+    ///
+    /// - `atm` - use three-body correction (true) or two-body correction
+    ///   (false)
+    pub fn compute_database(&self, atm: bool) -> DFTD3ReactionDatabaseOutput {
+        self.compute_database_f(atm).unwrap()
+    }
+
+    /// Evaluate the reaction dispersion energy (failable).
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3Reaction::compute`]
+    pub fn compute_f(&self, param: &DFTD3Param) -> Result<DFTD3ReactionOutput, DFTD3Error> {
+        let species_energies = self
+            .species
+            .iter()
+            .map(|s| Ok(s.model.get_dispersion_f(param, false)?.energy))
+            .collect::<Result<Vec<f64>, DFTD3Error>>()?;
+        let contributions: Vec<f64> =
+            self.species.iter().zip(&species_energies).map(|(s, e)| s.coefficient * e).collect();
+        let energy = contributions.iter().sum();
+        Ok(DFTD3ReactionOutput { energy, species_energies, contributions })
+    }
+
+    /// Evaluate the reaction for every method and variant in the parameter
+    /// database (failable).
+    ///
+    /// Variants not supported by the enabled API features are skipped.
+    ///
+    /// # See also
+    ///
+    /// [`DFTD3Reaction::compute_database`]
+    pub fn compute_database_f(&self, atm: bool) -> Result<DFTD3ReactionDatabaseOutput, DFTD3Error> {
+        let (params, mut failures) = database_params_f(atm)?;
+        let mut entries = vec![];
+        for (method, version, param) in params {
+            match self.compute_f(&param) {
+                Ok(output) => entries.push(DFTD3ReactionDatabaseEntry { method, version, output }),
+                Err(e) => {
+                    failures.push(DFTD3DatabaseFailure { method, version, error: e.to_string() })
+                },
+            }
+        }
+        Ok(DFTD3ReactionDatabaseOutput { entries, failures })
+    }
+}
+
+/// `(method, version, param)` of database entries.
+type DatabaseParams = Vec<(String, String, DFTD3Param)>;
+
+/// Damping parameters of every method and variant in the parameter database,
+/// as `(method, version, param)` sorted by variant and method, and the
+/// methods whose parameters could not be built.
+///
+/// Variants not supported by the enabled API features are skipped.
+pub(crate) fn database_params_f(
+    atm: bool,
+) -> Result<(DatabaseParams, Vec<DFTD3DatabaseFailure>), DFTD3Error> {
+    let mut params = vec![];
+    let mut failures = vec![];
+    for &version in DATABASE_VARIANTS {
+        let mut methods: Vec<String> =
+            dftd3_get_all_damping_params_f(version)?.into_keys().collect();
+        methods.sort();
+        for method in methods {
+            let mut input = Table::new();
+            input.insert("version".into(), version.into());
+            input.insert("method".into(), method.as_str().into());
+            input.insert("atm".into(), atm.into());
+            match dftd3_parse_damping_param_f(&input).and_then(|p| p.new_param_f()) {
+                Ok(param) => params.push((method, version.to_string(), param)),
+                Err(e) => failures.push(DFTD3DatabaseFailure {
+                    method,
+                    version: version.to_string(),
+                    error: e.to_string(),
+                }),
+            }
+        }
+    }
+    Ok((params, failures))
+}
+
+/* #endregion */
+
+/* #region Conformers */
+
+/// Dispersion energies of conformers relative to the lowest one.
+pub fn dftd3_conformer_energies(models: &[DFTD3Model], param: &DFTD3Param) -> Vec<f64> {
+    dftd3_conformer_energies_f(models, param).unwrap()
+}
+
+/// Dispersion energies of conformers relative to the lowest one (failable).
+///
+/// # See also
+///
+/// [`dftd3_conformer_energies`]
+pub fn dftd3_conformer_energies_f(
+    models: &[DFTD3Model],
+    param: &DFTD3Param,
+) -> Result<Vec<f64>, DFTD3Error> {
+    let energies = models
+        .iter()
+        .map(|model| Ok(model.get_dispersion_f(param, false)?.energy))
+        .collect::<Result<Vec<f64>, DFTD3Error>>()?;
+    let lowest = energies.iter().copied().fold(f64::INFINITY, f64::min);
+    Ok(energies.iter().map(|e| e - lowest).collect())
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_database_variants() {
+        // every scanned variant must have defaults in the parameter database
+        for version in DATABASE_VARIANTS {
+            assert!(dftd3_get_all_damping_params_f(version).is_ok(), "{version}");
+        }
+    }
+}
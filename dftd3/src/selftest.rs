@@ -0,0 +1,433 @@
+//! Self-test of the loaded s-dftd3 library against reference values.
+//!
+//! The s-dftd3 library may be loaded dynamically from conda, pip or system
+//! paths, and a wrong or mis-built library can silently give different
+//! numbers. [`selftest`] runs a small embedded suite and returns a structured
+//! report, so that services can check the library at startup:
+//!
+//! - energies of a 16-atom molecule with database parameters of several damping
+//!   variants, with and without the ATM term;
+//! - energies of an 18-atom molecule with rational damping (database) and zero
+//!   damping (custom parameters);
+//! - gradient of a 17-atom molecule;
+//! - the 16- and 17-atom molecules isolated in a 100 Bohr box, checking that
+//!   the periodic code path reproduces the molecular energy and gradient, and
+//!   that `sigma` equals the virial of the molecular gradient;
+//! - a periodic cell (16-18 Bohr), checking strain derivatives `sigma` against
+//!   central differences of the energy, and the net force of the gradient.
+//!
+//! Reference values are those of the crate's own interface tests (obtained
+//! with s-dftd3 and its PySCF interface). In the 100 Bohr box all images lie
+//! beyond the real-space cutoffs, so these cases reuse the molecular
+//! references; the virial is `sum_i g_i r_i^T` of the reference gradient. No
+//! lattice sum is checked against a reference: the cell with interacting
+//! images is only checked for internal consistency. Failures to load the
+//! library, or
+//! panics of the FFI layer, are reported as failed cases rather than
+//! propagated.
+//!
+//! # Example
+//!
+//! ```no_run
+//! let report = dftd3::selftest();
+//! println!("{report}");
+//! assert!(report.passed(), "s-dftd3 library failed self-test");
+//! ```
+
+use crate::elements::*;
+use crate::interface::*;
+use std::panic::{catch_unwind, AssertUnwindSafe};
+
+/* #region Reference data */
+
+/// 16-atom molecule (Bohr).
+pub(crate) const NUMBERS_MB16: [usize; 16] = [1, 1, 6, 5, 1, 15, 8, 17, 13, 15, 5, 1, 9, 15, 1, 15];
+#[rustfmt::skip]
+pub(crate) const POSITIONS_MB16: [f64; 48] = [
+     2.79274810283778,  3.82998228828316, -2.79287054959216,
+    -1.43447454186833,  0.43418729987882,  5.53854345129809,
+    -3.26268343665218, -2.50644032426151, -1.56631149351046,
+     2.14548759959147, -0.88798018953965, -2.24592534506187,
+    -4.30233097423181, -3.93631518670031, -0.48930754109119,
+     0.06107643564880, -3.82467931731366, -2.22333344469482,
+     0.41168550401858,  0.58105573172764,  5.56854609916143,
+     4.41363836635653,  3.92515871809283,  2.57961724984000,
+     1.33707758998700,  1.40194471661647,  1.97530004949523,
+     3.08342709834868,  1.72520024666801, -4.42666116106828,
+    -3.02346932078505,  0.04438199934191, -0.27636197425010,
+     1.11508390868455, -0.97617412809198,  6.25462847718180,
+     0.61938955433011,  2.17903547389232, -6.21279842416963,
+    -2.67491681346835,  3.00175899761859,  1.05038813614845,
+    -4.13181080289514, -2.34226739863660, -3.44356159392859,
+     2.85007173009739, -2.64884892757600,  0.71010806424206,
+];
+
+/// Iodobenzene and methanethiol (Angstrom).
+const NUMBERS_IODO18: [usize; 18] = [6, 6, 6, 6, 6, 6, 53, 1, 1, 1, 1, 1, 16, 1, 6, 1, 1, 1];
+#[rustfmt::skip]
+const POSITIONS_IODO18: [f64; 54] = [
+    -0.755422531,  -0.796459123,  -1.023590391,
+     0.634274834,  -0.880017014,  -1.075233285,
+     1.406955202,   0.199695367,  -0.653144334,
+     0.798863737,   1.361204515,  -0.180597909,
+    -0.593166787,   1.434312023,  -0.133597923,
+    -1.376239198,   0.359205222,  -0.553258516,
+    -1.514344238,   3.173268101,   0.573601106,
+     1.110906949,  -1.778801728,  -1.440619836,
+     1.399172302,   2.197767355,   0.147412751,
+     2.486417780,   0.142466525,  -0.689380574,
+    -2.454252250,   0.422581120,  -0.512807958,
+    -1.362353593,  -1.630564523,  -1.348743149,
+    -3.112683203,   6.289227834,   1.226984439,
+    -4.328789697,   5.797771251,   0.973373089,
+    -2.689135032,   6.703163830,  -0.489062886,
+    -1.684433029,   7.115457372,  -0.460265708,
+    -2.683867206,   5.816530502,  -1.115183775,
+    -3.365330613,   7.451201412,  -0.890098894,
+];
+
+/// Benzene and bromotrifluoromethane (Angstrom).
+const NUMBERS_BROMO17: [usize; 17] = [1, 6, 6, 6, 6, 6, 6, 1, 1, 1, 1, 1, 35, 6, 9, 9, 9];
+#[rustfmt::skip]
+const POSITIONS_BROMO17: [f64; 51] = [
+     0.002144194,   0.361043475,   0.029799709,
+     0.015020592,   0.274789738,   1.107648016,
+     1.227632658,   0.296655040,   1.794629427,
+     1.243958826,   0.183702791,   3.183703934,
+     0.047958213,   0.048915002,   3.886484583,
+    -1.165135654,   0.026954348,   3.200213281,
+    -1.181832083,   0.139828643,   1.810376587,
+     2.155807907,   0.399177037,   1.249441585,
+     2.184979344,   0.198598553,   3.716170761,
+     0.060934662,  -0.040672756,   4.964014252,
+    -2.093220602,  -0.078628959,   3.745125056,
+    -2.122845437,   0.123257119,   1.277645797,
+    -0.268325907,  -3.194209024,   1.994458950,
+     0.049999933,  -5.089197474,   1.929391171,
+     0.078949601,  -5.512441335,   0.671851563,
+     1.211983937,  -5.383996300,   2.498664481,
+    -0.909987405,  -5.743747328,   2.570721738,
+];
+
+/// r2SCAN-D3(BJ) gradient of [`POSITIONS_BROMO17`], without ATM.
+#[rustfmt::skip]
+const GRADIENT_BROMO17: [f64; 51] = [
+     7.13721248e-07,  2.19571763e-05, -3.77372946e-05,
+     9.19838860e-07,  3.53459763e-05, -1.43306994e-06,
+     7.43860881e-06,  3.78237447e-05,  8.46031238e-07,
+     8.06120927e-06,  3.79834948e-05,  8.58427570e-06,
+     1.16592466e-06,  3.62585085e-05,  1.16326308e-05,
+    -3.69381337e-06,  3.39047971e-05,  6.92483428e-06,
+    -3.05404225e-06,  3.29484247e-05,  1.80766271e-06,
+     3.51228183e-05,  2.08136972e-05, -1.76546837e-05,
+     3.49762054e-05,  1.66544908e-05,  2.14435772e-05,
+     1.57516340e-06,  1.41373959e-05,  4.21574793e-05,
+    -3.35392428e-05,  1.49030766e-05,  2.29976305e-05,
+    -3.38817253e-05,  1.82002569e-05, -1.72487448e-05,
+    -2.15610724e-05, -1.87935101e-04, -3.02815495e-05,
+     1.27580963e-06, -5.96841724e-05, -5.99713166e-06,
+     9.01173808e-07, -2.23010304e-05, -7.96228701e-06,
+     7.42062176e-06, -2.79631452e-05,  7.03703317e-07,
+    -3.84119900e-06, -2.30475903e-05,  1.21693625e-06,
+];
+
+/// Cubic box (Bohr) isolating a molecule; images are farther apart than the
+/// default real-space cutoffs of s-dftd3 (60 Bohr for two-body terms).
+const LATTICE_ISOLATED: [f64; 9] = [100.0, 0.0, 0.0, 0.0, 100.0, 0.0, 0.0, 0.0, 100.0];
+
+/// Virial `sum_i g_i r_i^T` of [`GRADIENT_BROMO17`], the strain derivatives
+/// of the isolated molecule in [`LATTICE_ISOLATED`].
+#[rustfmt::skip]
+const VIRIAL_BROMO17: [f64; 9] = [
+    6.4235680001e-04, 1.1831980391e-04, 1.6535041699e-05,
+    1.1831980391e-04, 2.5797787943e-03, 2.6595897674e-04,
+    1.6535041699e-05, 2.6595897674e-04, 6.7149889994e-04,
+];
+
+/// Database parameter energies of [`POSITIONS_MB16`]: (version, method, atm,
+/// energy).
+#[rustfmt::skip]
+const ENERGIES_MB16: &[(&str, &str, bool, f64)] = &[
+    ("d3bj", "pbe0", true, -0.029489232932494884),
+    ("d3bj", "pbe0", false, -0.029589132634178342),
+    ("d3zero", "b3lyp", true, -0.022714272555175656),
+    ("d3zero", "b3lyp", false, -0.022814172019166058),
+    ("d3bjm", "pbe", true, -0.06327406660942464),
+    ("d3bjm", "pbe", false, -0.06337396631110809),
+    ("d3zerom", "bp", true, -0.026013316869036292),
+    ("d3zerom", "bp", false, -0.026113216333026695),
+    #[cfg(feature = "api-v0_5")]
+    ("d3op", "b97d", true, -0.07681029606751344),
+    #[cfg(feature = "api-v0_5")]
+    ("d3op", "b97d", false, -0.07691018779028679),
+];
+
+/// Tolerance of energies (Hartree).
+const TOL_ENERGY: f64 = 1.0e-8;
+/// Tolerance of gradient L2 norm difference.
+const TOL_GRADIENT: f64 = 1.0e-8;
+/// Tolerance of strain derivatives against central differences.
+const TOL_SIGMA: f64 = 1.0e-6;
+/// Strain step of central differences.
+const STRAIN_STEP: f64 = 1.0e-4;
+
+/* #endregion */
+
+/* #region Self-test report */
+
+/// Result of one self-test case.
+#[derive(Debug, Clone)]
+pub struct DFTD3SelfTestCase {
+    /// Name of case.
+    pub name: String,
+    /// Deviation from reference (absolute, or L2 norm for arrays); `NaN` if
+    /// the case could not be evaluated.
+    pub deviation: f64,
+    /// Tolerance of deviation.
+    pub tolerance: f64,
+    /// Whether the case passed.
+    pub passed: bool,
+    /// Error message, if the case could not be evaluated.
+    pub error: Option<String>,
+}
+
+/// Self-test report of the s-dftd3 library.
+#[derive(Debug, Clone)]
+pub struct DFTD3SelfTestReport {
+    /// Version of the s-dftd3 library, if it could be loaded.
+    pub version: Option<String>,
+    /// Paths of dynamically loaded libraries (empty for static linking).
+    pub library_paths: Vec<String>,
+    /// Results of all cases.
+    pub cases: Vec<DFTD3SelfTestCase>,
+}
+
+impl DFTD3SelfTestReport {
+    /// Whether the library could be loaded and all cases passed.
+    pub fn passed(&self) -> bool {
+        self.version.is_some() && self.cases.iter().all(|c| c.passed)
+    }
+
+    /// Failed cases.
+    pub fn failures(&self) -> Vec<&DFTD3SelfTestCase> {
+        self.cases.iter().filter(|c| !c.passed).collect()
+    }
+}
+
+impl std::fmt::Display for DFTD3SelfTestReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let status = if self.passed() { "PASSED" } else { "FAILED" };
+        writeln!(f, "s-dftd3 self-test: {status}")?;
+        writeln!(f, "library version: {}", self.version.as_deref().unwrap_or("(not loaded)"))?;
+        if self.library_paths.is_empty() {
+            writeln!(f, "library paths: (statically linked or not loaded)")?;
+        }
+        for path in &self.library_paths {
+            writeln!(f, "library path: {path}")?;
+        }
+        for case in &self.cases {
+            let mark = if case.passed { "ok" } else { "FAILED" };
+            write!(
+                f,
+                "{mark:>6}  {:<40} {:10.3e} (tol {:.1e})",
+                case.name, case.deviation, case.tolerance
+            )?;
+            if let Some(error) = &case.error {
+                write!(
+                    f,
+                    "  error: {}",
+                    error.lines().find(|l| !l.trim().is_empty()).unwrap_or("")
+                )?;
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+/* #endregion */
+
+/* #region Self-test driver */
+
+/// Run the embedded self-test suite against the loaded s-dftd3 library.
+///
+/// This function does not panic on library loading failures or numerical
+/// mismatches; check [`DFTD3SelfTestReport::passed`].
+pub fn selftest() -> DFTD3SelfTestReport {
+    let version = guarded(|| Ok(dftd3_get_api_version())).ok();
+    #[cfg(feature = "dynamic_loading")]
+    let library_paths =
+        guarded(|| Ok(unsafe { crate::ffi::dyload_lib().__libraries_path.clone() }))
+            .unwrap_or_default();
+    #[cfg(not(feature = "dynamic_loading"))]
+    let library_paths = vec![];
+
+    let mut cases = vec![];
+    let mut run = |name: String, tolerance: f64, case: &dyn Fn() -> Result<f64, DFTD3Error>| {
+        let (deviation, error) = match guarded(case) {
+            Ok(deviation) => (deviation, None),
+            Err(error) => (f64::NAN, Some(error)),
+        };
+        let passed = deviation.abs() <= tolerance;
+        cases.push(DFTD3SelfTestCase { name, deviation, tolerance, passed, error });
+    };
+
+    // database parameters, several damping variants
+    for &(version, method, atm, expected) in ENERGIES_MB16 {
+        run(format!("mb16 {method}-{version} atm={atm} energy"), TOL_ENERGY, &|| {
+            let model = DFTD3Model::new_f(&NUMBERS_MB16, &POSITIONS_MB16, None, None)?;
+            let param = dftd3_load_param_c_f(version, method, atm)?;
+            Ok(model.get_dispersion_f(&param, false)?.energy - expected)
+        });
+    }
+
+    // database and custom parameters, Angstrom input
+    let iodo18 = || to_bohr(&POSITIONS_IODO18);
+    run("iodo18 r2scan-d3bj atm=false energy".into(), TOL_ENERGY, &|| {
+        let model = DFTD3Model::new_f(&NUMBERS_IODO18, &iodo18(), None, None)?;
+        let param = dftd3_load_param_c_f("d3bj", "r2scan", false)?;
+        Ok(model.get_dispersion_f(&param, false)?.energy - (-0.00578401192369041))
+    });
+    for (atm, expected) in [(true, -0.01410721853585842), (false, -0.014100267345314462)] {
+        run(format!("iodo18 custom d3zero atm={atm} energy"), TOL_ENERGY, &|| {
+            let model = DFTD3Model::new_f(&NUMBERS_IODO18, &iodo18(), None, None)?;
+            let s9 = if atm { 1.0 } else { 0.0 };
+            let param = DFTD3Param::new_zero_damping_f(1.0, 1.683, s9, 1.139, 1.0, 14.0)?;
+            Ok(model.get_dispersion_f(&param, false)?.energy - expected)
+        });
+    }
+
+    // gradient
+    run("bromo17 r2scan-d3bj atm=false gradient".into(), TOL_GRADIENT, &|| {
+        let model = DFTD3Model::new_f(&NUMBERS_BROMO17, &to_bohr(&POSITIONS_BROMO17), None, None)?;
+        let param = dftd3_load_param_c_f("d3bj", "r2scan", false)?;
+        let grad = model.get_dispersion_f(&param, true)?.grad.unwrap_or_default();
+        deviation(&grad, &GRADIENT_BROMO17, "Gradient")
+    });
+
+    // molecules isolated in a box: periodic code path, molecular references
+    run("mb16 isolated pbe0-d3bj atm=true energy".into(), TOL_ENERGY, &|| {
+        let model =
+            DFTD3Model::new_f(&NUMBERS_MB16, &POSITIONS_MB16, Some(&LATTICE_ISOLATED), None)?;
+        let param = dftd3_load_param_c_f("d3bj", "pbe0", true)?;
+        Ok(model.get_dispersion_f(&param, false)?.energy - (-0.029489232932494884))
+    });
+    let bromo17_isolated = || -> Result<DFTD3Output, DFTD3Error> {
+        let positions = to_bohr(&POSITIONS_BROMO17);
+        let model = DFTD3Model::new_f(&NUMBERS_BROMO17, &positions, Some(&LATTICE_ISOLATED), None)?;
+        let param = dftd3_load_param_c_f("d3bj", "r2scan", false)?;
+        model.get_dispersion_f(&param, true)
+    };
+    run("bromo17 isolated r2scan-d3bj atm=false gradient".into(), TOL_GRADIENT, &|| {
+        deviation(&bromo17_isolated()?.grad.unwrap_or_default(), &GRADIENT_BROMO17, "Gradient")
+    });
+    run("bromo17 isolated r2scan-d3bj atm=false virial".into(), TOL_GRADIENT, &|| {
+        deviation(
+            &bromo17_isolated()?.sigma.unwrap_or_default(),
+            &VIRIAL_BROMO17,
+            "Strain derivatives",
+        )
+    });
+
+    // periodic cell with interacting images, internal consistency of gradient
+    // and sigma only (no reference values)
+    let lattice = [16.0, 0.0, 0.0, 1.0, 17.0, 0.0, 0.0, -1.0, 18.0];
+    run("mb16 cell pbe0-d3bj net force".into(), TOL_SIGMA, &|| {
+        let model = DFTD3Model::new_f(&NUMBERS_MB16, &POSITIONS_MB16, Some(&lattice), None)?;
+        let param = dftd3_load_param_c_f("d3bj", "pbe0", true)?;
+        let grad = model.get_dispersion_f(&param, true)?.grad.unwrap_or_default();
+        let force: Vec<f64> = (0..3).map(|x| grad.chunks(3).map(|g| g[x]).sum()).collect();
+        Ok(force.iter().map(|f| f * f).sum::<f64>().sqrt())
+    });
+    run("mb16 cell pbe0-d3bj sigma".into(), TOL_SIGMA, &|| {
+        let param = dftd3_load_param_c_f("d3bj", "pbe0", true)?;
+        let mut model = DFTD3Model::new_f(&NUMBERS_MB16, &POSITIONS_MB16, Some(&lattice), None)?;
+        let sigma = model.get_dispersion_f(&param, true)?.sigma.unwrap_or_default();
+        if sigma.len() != 9 {
+            return Err(DFTD3Error::Rust("Strain derivatives of unexpected dimension".into()));
+        }
+        let mut deviation = 0.0_f64;
+        for i in 0..3 {
+            for j in i..3 {
+                let mut energies = [0.0; 2];
+                for (k, sign) in [1.0, -1.0].into_iter().enumerate() {
+                    // symmetric strain with eps_ij = eps_ji = sign * h / 2
+                    let mut deform = [1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0];
+                    deform[3 * i + j] += 0.5 * sign * STRAIN_STEP;
+                    deform[3 * j + i] += 0.5 * sign * STRAIN_STEP;
+                    let apply = |v: &[f64]| -> Vec<f64> {
+                        v.chunks(3)
+                            .flat_map(|r| {
+                                (0..3).map(move |a| (0..3).map(|b| deform[3 * a + b] * r[b]).sum())
+                            })
+                            .collect()
+                    };
+                    model.update_f(&apply(&POSITIONS_MB16), Some(&apply(&lattice)))?;
+                    energies[k] = model.get_dispersion_f(&param, false)?.energy;
+                }
+                let numerical = (energies[0] - energies[1]) / (2.0 * STRAIN_STEP);
+                let analytical = 0.5 * (sigma[3 * i + j] + sigma[3 * j + i]);
+                deviation = deviation.max((numerical - analytical).abs());
+            }
+        }
+        Ok(deviation)
+    });
+
+    DFTD3SelfTestReport { version, library_paths, cases }
+}
+
+/// Run `f`, turning errors and panics (e.g. library loading failure) into
+/// error messages.
+fn guarded<T>(f: impl FnOnce() -> Result<T, DFTD3Error>) -> Result<T, String> {
+    match catch_unwind(AssertUnwindSafe(f)) {
+        Ok(Ok(value)) => Ok(value),
+        Ok(Err(error)) => Err(error.to_string()),
+        Err(panic) => Err(panic
+            .downcast_ref::<String>()
+            .cloned()
+            .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+            .unwrap_or_else(|| "unknown panic".into())),
+    }
+}
+
+/// L2 norm of the difference of an array to its reference.
+fn deviation(value: &[f64], reference: &[f64], what: &str) -> Result<f64, DFTD3Error> {
+    if value.len() != reference.len() {
+        return Err(DFTD3Error::Rust(format!("{what} of unexpected dimension")));
+    }
+    Ok(value.iter().zip(reference).map(|(x, y)| (x - y).powi(2)).sum::<f64>().sqrt())
+}
+
+fn to_bohr(positions: &[f64]) -> Vec<f64> {
+    positions.iter().map(|x| x / BOHR_TO_ANGSTROM).collect()
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selftest_report() {
+        let error = guarded::<()>(|| panic!("library not found")).unwrap_err();
+        assert_eq!(error, "library not found");
+        let case = |passed| DFTD3SelfTestCase {
+            name: "case".into(),
+            deviation: 0.0,
+            tolerance: 1e-8,
+            passed,
+            error: None,
+        };
+        let mut report = DFTD3SelfTestReport {
+            version: Some("1.0.0".into()),
+            library_paths: vec![],
+            cases: vec![case(true), case(true)],
+        };
+        assert!(report.passed());
+        report.cases.push(case(false));
+        assert!(!report.passed());
+        assert_eq!(report.failures().len(), 1);
+        assert!(report.to_string().contains("FAILED"));
+    }
+}
@@ -0,0 +1,166 @@
+//! Sensitivity of dispersion energies with respect to damping parameters.
+//!
+//! The Jacobian `dE_i/dp_j` of the dispersion energies of one or many
+//! structures with respect to the damping parameter fields of a
+//! [`DFTD3DampingParamEnum`] is evaluated by central differences
+//!
+//! ```text
+//! dE/dp = (E(p + h) - E(p - h)) / 2h,    h = step * max(|p|, 0.01)
+//! ```
+//!
+//! Models are constructed once and reused; only the [`DFTD3Param`] is rebuilt
+//! for each displacement, so the cost is `2 * nfields` energy evaluations per
+//! structure. Analytic derivatives are not available yet.
+//!
+//! Requested fields that do not exist for the variant (for example `s8` of
+//! CSO damping) are not differentiated, and are reported in
+//! [`DFTD3ParamJacobian::skipped`].
+//!
+//! # Example
+//!
+//! ```no_run
+//! use dftd3::prelude::*;
+//!
+//! # let (numbers, positions) = (vec![1_usize], vec![0.0; 3]);
+//! let models = vec![DFTD3Model::new(&numbers, &positions, None, None)];
+//! let param = dftd3_get_damping_param("b3lyp", "bj").param;
+//! let jac = dftd3_param_jacobian(&models, &param, Some(&["s8", "a1", "a2", "rs6"]));
+//! assert_eq!(jac.skipped, vec!["rs6"]);
+//! println!("dE/da1 = {}", jac.get(0, "a1").unwrap());
+//! ```
+
+use crate::interface::*;
+use crate::parameters::*;
+
+/// Default relative step of central differences.
+pub const DFTD3_JACOBIAN_STEP: f64 = 1.0e-4;
+
+/* #region Jacobian output */
+
+/// Jacobian of dispersion energies with respect to damping parameters.
+#[derive(Debug, Clone)]
+pub struct DFTD3ParamJacobian {
+    /// Differentiated parameter fields (columns).
+    pub fields: Vec<String>,
+    /// Requested fields that do not exist for this variant.
+    pub skipped: Vec<String>,
+    /// Dispersion energies of structures at the given parameters.
+    pub energies: Vec<f64>,
+    /// Jacobian `dE_i/dp_j` (nstructure * nfield, row-major).
+    pub jacobian: Vec<f64>,
+}
+
+impl DFTD3ParamJacobian {
+    /// Derivative of the dispersion energy of structure `index` with respect to
+    /// `field`, if that field is differentiated.
+    pub fn get(&self, index: usize, field: &str) -> Option<f64> {
+        let j = self.fields.iter().position(|f| f == field)?;
+        self.jacobian.get(index * self.fields.len() + j).copied()
+    }
+}
+
+/* #endregion */
+
+/* #region Jacobian driver */
+
+/// Jacobian of dispersion energies of structures with respect to damping
+/// parameters.
+///
+/// - `models` - structures
+/// - `param` - damping parameters at which the Jacobian is evaluated
+/// - `fields` - optional, parameter fields to be differentiated; all fields of
+///   the variant if not given
+pub fn dftd3_param_jacobian(
+    models: &[DFTD3Model],
+    param: &DFTD3DampingParamEnum,
+    fields: Option<&[&str]>,
+) -> DFTD3ParamJacobian {
+    dftd3_param_jacobian_f(models, param, fields).unwrap()
+}
+
+/// Jacobian of dispersion energies of structures with respect to damping
+/// parameters (failable).
+///
+/// # See also
+///
+/// [`dftd3_param_jacobian`]
+pub fn dftd3_param_jacobian_f(
+    models: &[DFTD3Model],
+    param: &DFTD3DampingParamEnum,
+    fields: Option<&[&str]>,
+) -> Result<DFTD3ParamJacobian, DFTD3Error> {
+    let available: Vec<String> = param.to_table().keys().cloned().collect();
+    let requested: Vec<String> = match fields {
+        Some(fields) => fields.iter().map(|f| f.to_string()).collect(),
+        None => available.clone(),
+    };
+    let (fields, skipped): (Vec<String>, Vec<String>) =
+        requested.into_iter().partition(|f| available.contains(f));
+
+    let energies_of = |param: DFTD3DampingParamEnum| -> Result<Vec<f64>, DFTD3Error> {
+        let param = param.new_param_f()?;
+        models.iter().map(|m| Ok(m.get_dispersion_f(&param, false)?.energy)).collect()
+    };
+    let energies = energies_of(param.clone())?;
+    let jacobian = param_jacobian_with(param, &fields, DFTD3_JACOBIAN_STEP, energies_of)?;
+    Ok(DFTD3ParamJacobian { fields, skipped, energies, jacobian })
+}
+
+/// Central-difference Jacobian (nvalue * nfield, row-major) of values computed
+/// by `evaluate` with respect to parameter `fields`, which must exist for the
+/// variant of `param`.
+pub(crate) fn param_jacobian_with<F>(
+    param: &DFTD3DampingParamEnum,
+    fields: &[String],
+    step: f64,
+    mut evaluate: F,
+) -> Result<Vec<f64>, DFTD3Error>
+where
+    F: FnMut(DFTD3DampingParamEnum) -> Result<Vec<f64>, DFTD3Error>,
+{
+    let nfield = fields.len();
+    let mut jacobian = vec![];
+    for (j, field) in fields.iter().enumerate() {
+        let value = param.get_field(field).ok_or_else(|| {
+            DFTD3Error::ParametersError(format!(
+                "Unknown parameter '{field}' for variant '{}'",
+                param.version()
+            ))
+        })?;
+        let h = step * value.abs().max(1.0e-2);
+        let (mut plus, mut minus) = (param.clone(), param.clone());
+        plus.set_field(field, value + h)?;
+        minus.set_field(field, value - h)?;
+        let (plus, minus) = (evaluate(plus)?, evaluate(minus)?);
+        if jacobian.is_empty() {
+            jacobian = vec![0.0; plus.len() * nfield];
+        }
+        for (i, (p, m)) in plus.iter().zip(&minus).enumerate() {
+            jacobian[i * nfield + j] = (p - m) / (2.0 * h);
+        }
+    }
+    Ok(jacobian)
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_param_jacobian_with() {
+        // E_0 = a1^2 + s8, E_1 = a1 * a2; central differences are exact for
+        // quadratics
+        let param = dftd3_get_damping_param("b3lyp", "bj").param;
+        let fields = vec!["a1".to_string(), "a2".to_string(), "s8".to_string()];
+        let jac = param_jacobian_with(&param, &fields, DFTD3_JACOBIAN_STEP, |p| {
+            let [a1, a2, s8] = ["a1", "a2", "s8"].map(|f| p.get_field(f).unwrap());
+            Ok(vec![a1 * a1 + s8, a1 * a2])
+        })
+        .unwrap();
+        let [a1, a2] = ["a1", "a2"].map(|f| param.get_field(f).unwrap());
+        let expected = [2.0 * a1, 0.0, 1.0, a2, a1, 0.0];
+        jac.iter().zip(expected).for_each(|(x, y)| assert!((x - y).abs() < 1e-8));
+    }
+}
@@ -0,0 +1,172 @@
+//! Crate-internal helpers shared by the high-level drivers.
+
+use crate::interface::*;
+
+/// Extract atomic numbers and positions (natom * 3) of a subset of atoms.
+pub(crate) fn take_atoms(
+    numbers: &[usize],
+    positions: &[f64],
+    atoms: &[usize],
+) -> (Vec<usize>, Vec<f64>) {
+    let sub_numbers = atoms.iter().map(|&i| numbers[i]).collect();
+    let sub_positions = atoms.iter().flat_map(|&i| positions[3 * i..3 * i + 3].to_vec()).collect();
+    (sub_numbers, sub_positions)
+}
+
+/// Check that atom indices are in range, returning a descriptive error
+/// otherwise.
+pub(crate) fn check_atom_indices(
+    atoms: &[usize],
+    natoms: usize,
+    what: &str,
+) -> Result<(), DFTD3Error> {
+    match atoms.iter().find(|&&i| i >= natoms) {
+        Some(i) => Err(DFTD3Error::Rust(format!(
+            "Atom index {i} in {what} is out of range (number of atoms {natoms})"
+        ))),
+        None => Ok(()),
+    }
+}
+
+/// Check that fragments partition all atoms: every fragment is non-empty, and
+/// every atom belongs to exactly one fragment.
+pub(crate) fn check_partition(
+    fragments: &[Vec<usize>],
+    natoms: usize,
+    what: &str,
+) -> Result<(), DFTD3Error> {
+    let mut owner = vec![None; natoms];
+    for (ifrag, frag) in fragments.iter().enumerate() {
+        if frag.is_empty() {
+            return Err(DFTD3Error::Rust(format!("Fragment {ifrag} of {what} is empty")));
+        }
+        check_atom_indices(frag, natoms, &format!("fragment {ifrag} of {what}"))?;
+        for &iatom in frag {
+            if let Some(jfrag) = owner[iatom] {
+                return Err(DFTD3Error::Rust(format!(
+                    "Atom {iatom} of {what} is assigned to both fragment {jfrag} and {ifrag}"
+                )));
+            }
+            owner[iatom] = Some(ifrag);
+        }
+    }
+    if let Some(iatom) = owner.iter().position(|o| o.is_none()) {
+        return Err(DFTD3Error::Rust(format!(
+            "Atom {iatom} of {what} is not assigned to any fragment"
+        )));
+    }
+    Ok(())
+}
+
+/// Check dimensions of structure arrays, the same way as
+/// [`DFTD3Structure::new_f`].
+pub(crate) fn check_structure_dims(
+    numbers: &[usize],
+    positions: &[f64],
+    lattice: Option<&[f64]>,
+    periodic: Option<&[bool]>,
+) -> Result<(), DFTD3Error> {
+    let natoms = numbers.len();
+    if positions.len() != 3 * natoms {
+        return Err(DFTD3Error::Rust(format!(
+            "Invalid dimension for positions, expected {}, got {}",
+            3 * natoms,
+            positions.len()
+        )));
+    }
+    if let Some(lattice) = lattice.filter(|lattice| lattice.len() != 9) {
+        return Err(DFTD3Error::Rust(format!(
+            "Invalid dimension for lattice, expected 9, got {}",
+            lattice.len()
+        )));
+    }
+    if let Some(periodic) = periodic.filter(|periodic| periodic.len() != 3) {
+        return Err(DFTD3Error::Rust(format!(
+            "Invalid dimension for periodic, expected 3, got {}",
+            periodic.len()
+        )));
+    }
+    Ok(())
+}
+
+/// Cell translations (in units of lattice vectors) reaching every periodic
+/// image within `cutoff` of a point, for positions wrapped into the unit cell.
+///
+/// The range along each periodic direction follows from the cell height
+/// `h_k = 1 / |b_k|` (reciprocal vector `b_k`) as `|n_k| <= floor(cutoff / h_k)
+/// + 1`, so skewed cells are handled without reduction.
+#[cfg(feature = "api-v0_5")]
+pub(crate) fn lattice_images(
+    lattice: &[f64],
+    periodic: [bool; 3],
+    cutoff: f64,
+) -> Result<Vec<[i64; 3]>, DFTD3Error> {
+    let inv = inverse3(lattice)?;
+    let nmax: [i64; 3] = std::array::from_fn(|k| match periodic[k] {
+        true => {
+            let recip = (0..3).map(|i| inv[3 * i + k].powi(2)).sum::<f64>().sqrt();
+            (cutoff * recip).floor() as i64 + 1
+        },
+        false => 0,
+    });
+    let mut images = vec![];
+    for n0 in -nmax[0]..=nmax[0] {
+        for n1 in -nmax[1]..=nmax[1] {
+            for n2 in -nmax[2]..=nmax[2] {
+                images.push([n0, n1, n2]);
+            }
+        }
+    }
+    Ok(images)
+}
+
+/// Inverse of a 3 x 3 row-major matrix.
+pub(crate) fn inverse3(mat: &[f64]) -> Result<[f64; 9], DFTD3Error> {
+    let m = |i: usize, j: usize| mat[3 * i + j];
+    let cof = [
+        m(1, 1) * m(2, 2) - m(1, 2) * m(2, 1),
+        m(0, 2) * m(2, 1) - m(0, 1) * m(2, 2),
+        m(0, 1) * m(1, 2) - m(0, 2) * m(1, 1),
+        m(1, 2) * m(2, 0) - m(1, 0) * m(2, 2),
+        m(0, 0) * m(2, 2) - m(0, 2) * m(2, 0),
+        m(0, 2) * m(1, 0) - m(0, 0) * m(1, 2),
+        m(1, 0) * m(2, 1) - m(1, 1) * m(2, 0),
+        m(0, 1) * m(2, 0) - m(0, 0) * m(2, 1),
+        m(0, 0) * m(1, 1) - m(0, 1) * m(1, 0),
+    ];
+    let det = m(0, 0) * cof[0] + m(0, 1) * cof[3] + m(0, 2) * cof[6];
+    if det.abs() < 1e-12 {
+        return Err(DFTD3Error::Rust("Singular 3 x 3 matrix (lattice or strain)".into()));
+    }
+    Ok(cof.map(|x| x / det))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inverse3() {
+        let mat = [2.0, 0.3, 0.0, 0.1, 3.0, 0.5, 0.0, 0.2, 4.0];
+        let inv = inverse3(&mat).unwrap();
+        for i in 0..3 {
+            for j in 0..3 {
+                let x: f64 = (0..3).map(|k| mat[3 * i + k] * inv[3 * k + j]).sum();
+                assert!((x - if i == j { 1.0 } else { 0.0 }).abs() < 1e-12);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "api-v0_5")]
+    fn test_lattice_images() {
+        // cubic cell, images of the neighbouring cells only
+        let lattice = [6.0, 0.0, 0.0, 0.0, 6.0, 0.0, 0.0, 0.0, 6.0];
+        assert_eq!(lattice_images(&lattice, [true; 3], 3.0).unwrap().len(), 27);
+        // skewed cell (b = 10 a + 6 y), height 0.597 bohr along a
+        let lattice = [6.0, 0.0, 0.0, 60.0, 6.0, 0.0, 0.0, 0.0, 6.0];
+        let images = lattice_images(&lattice, [true, true, false], 3.0).unwrap();
+        assert_eq!(images.len(), 13 * 3);
+        assert!(images.contains(&[-6, 1, 0]));
+    }
+}
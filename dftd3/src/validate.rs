@@ -0,0 +1,595 @@
+//! Validation (linting) of parameter database files.
+//!
+//! Any file in the format of `parameters.toml`, complete databases as well as
+//! user files layered over a database, can be checked for
+//!
+//! - entries that fail to deserialize into damping parameters of their variant
+//!   (with variant defaults merged), see [`ParameterLintIssue::Deserialize`];
+//! - method keys colliding after normalization (`b97-1` and `b97_1`), and
+//!   aliases of `meta` colliding with method names of the file or database;
+//! - malformed DOIs (expected `10.<registrant>/<suffix>`), of entries and of
+//!   the `[citation]` section;
+//! - unknown sections, variants and fields, e.g. `rs6` in a `d3.bj` entry;
+//! - values outside physically plausible ranges (negative `a2`, `s8 > 5`),
+//!   reported as warnings.
+//!
+//! User files are validated before they are merged
+//! ([`ParameterDatabase::merge_toml_str_f`]); files with errors are rejected.
+//!
+//! # Example
+//!
+//! ```
+//! use dftd3::prelude::*;
+//!
+//! let report = dftd3_validate_param_toml("[parameter.my-func]\nd3.bj = {a1=0.4, s8=1.0, rs6=1.2}");
+//! assert!(report.has_errors());
+//! println!("{report}");
+//! ```
+
+use crate::interface::DFTD3Error;
+use crate::parameters::*;
+use serde::Deserialize;
+use std::path::Path;
+use toml::{Table, Value};
+
+/// Fields of a `meta` table.
+const META_FIELDS: &[&str] = &["name", "aliases", "family", "range_separated", "notes"];
+
+/// Fields of a `[citation."<doi>"]` table.
+const CITATION_FIELDS: &[&str] =
+    &["title", "author", "journal", "volume", "issue", "pages", "year", "published"];
+
+/// Plausible ranges `(field, min, max)` of parameter values; values outside
+/// are reported as warnings.
+///
+/// `a1` is not bounded: a few published fits (e.g. b97m, ssb) have `a1 < 0`.
+const PLAUSIBLE_RANGES: &[(&str, f64, f64)] =
+    &[("a2", 0.0, f64::INFINITY), ("s8", f64::NEG_INFINITY, 5.0)];
+
+/* #region Lint report */
+
+/// Issue found by the validator.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParameterLintIssue {
+    /// File is not valid TOML, or a section has the wrong type.
+    Syntax {
+        /// Parser error or description.
+        error: String,
+    },
+    /// Entry fails to deserialize into damping parameters of its variant.
+    Deserialize {
+        /// Deserialization error.
+        error: String,
+    },
+    /// Method key collides with another key after normalization.
+    NameCollision {
+        /// Other method key.
+        other: String,
+    },
+    /// Alias (or display name) of `meta` is a method name of the file or of
+    /// the database the file is merged over.
+    AliasCollision {
+        /// The alias.
+        alias: String,
+        /// Method key of that name.
+        method: String,
+    },
+    /// DOI is not of the form `10.<registrant>/<suffix>`.
+    MalformedDoi {
+        /// The DOI.
+        doi: String,
+    },
+    /// Unknown section, variant or field.
+    UnknownField {
+        /// Name of the field.
+        field: String,
+    },
+    /// Field has the wrong type (e.g. a string for a parameter value).
+    InvalidValue {
+        /// Name of the field.
+        field: String,
+        /// Expected type.
+        expected: &'static str,
+    },
+    /// Value outside the physically plausible range (warning).
+    ImplausibleValue {
+        /// Name of the field.
+        field: String,
+        /// The value.
+        value: f64,
+        /// Plausible range (inclusive).
+        range: (f64, f64),
+    },
+}
+
+impl ParameterLintIssue {
+    /// Whether the issue is an error (otherwise a warning).
+    pub fn is_error(&self) -> bool {
+        !matches!(self, ParameterLintIssue::ImplausibleValue { .. })
+    }
+}
+
+impl std::fmt::Display for ParameterLintIssue {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use ParameterLintIssue::*;
+        match self {
+            Syntax { error } => write!(f, "{error}"),
+            Deserialize { error } => write!(f, "{error}"),
+            NameCollision { other } => write!(f, "same normalized name as '{other}'"),
+            AliasCollision { alias, method } => {
+                write!(f, "alias '{alias}' is the name of method '{method}'")
+            },
+            MalformedDoi { doi } => write!(f, "malformed DOI '{doi}'"),
+            UnknownField { field } => write!(f, "unknown field '{field}'"),
+            InvalidValue { field, expected } => write!(f, "field '{field}' should be {expected}"),
+            ImplausibleValue { field, value, range: (min, max) } => {
+                write!(f, "{field} = {value} outside plausible range")?;
+                match (min.is_finite(), max.is_finite()) {
+                    (true, true) => write!(f, " [{min}, {max}]"),
+                    (true, false) => write!(f, " (>= {min})"),
+                    _ => write!(f, " (<= {max})"),
+                }
+            },
+        }
+    }
+}
+
+/// Issue with its location, e.g. `parameter.b3lyp.d3.bj`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParameterLint {
+    /// Dotted path of the section or entry.
+    pub location: String,
+    /// Issue found.
+    pub issue: ParameterLintIssue,
+}
+
+/// Validation report of a parameter file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ParameterLintReport {
+    /// Source of the file (path or label).
+    pub source: String,
+    /// Issues found, in file order of sections.
+    pub lints: Vec<ParameterLint>,
+}
+
+impl ParameterLintReport {
+    /// Whether any error (not only warnings) was found.
+    pub fn has_errors(&self) -> bool {
+        self.lints.iter().any(|l| l.issue.is_error())
+    }
+
+    /// Errors found.
+    pub fn errors(&self) -> Vec<&ParameterLint> {
+        self.lints.iter().filter(|l| l.issue.is_error()).collect()
+    }
+
+    /// Warnings found.
+    pub fn warnings(&self) -> Vec<&ParameterLint> {
+        self.lints.iter().filter(|l| !l.issue.is_error()).collect()
+    }
+
+    fn push(&mut self, location: &str, issue: ParameterLintIssue) {
+        self.lints.push(ParameterLint { location: location.to_string(), issue });
+    }
+}
+
+impl std::fmt::Display for ParameterLintReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(
+            f,
+            "Parameter file {}: {} errors, {} warnings",
+            self.source,
+            self.errors().len(),
+            self.warnings().len()
+        )?;
+        for lint in &self.lints {
+            let severity = if lint.issue.is_error() { "error" } else { "warning" };
+            writeln!(f, "{severity}: {}: {}", lint.location, lint.issue)?;
+        }
+        Ok(())
+    }
+}
+
+/* #endregion */
+
+/* #region Validator */
+
+impl ParameterDatabase {
+    /// Validate a parameter file to be merged over this database; variant
+    /// defaults missing in the file are taken from this database.
+    pub fn validate_toml_str(&self, toml_str: &str, source: &str) -> ParameterLintReport {
+        validate_toml_str(toml_str, source, self)
+    }
+}
+
+/// Validate a parameter file, standalone or layered over the embedded
+/// database (variant defaults missing in the file are taken from it).
+pub fn dftd3_validate_param_toml(toml_str: &str) -> ParameterLintReport {
+    validate_toml_str(toml_str, "<string>", ParameterDatabase::embedded())
+}
+
+/// Validate a parameter file on disk (panics if it cannot be read).
+///
+/// See [`dftd3_validate_param_toml`].
+pub fn dftd3_validate_param_file<P: AsRef<Path>>(path: P) -> ParameterLintReport {
+    dftd3_validate_param_file_f(path).unwrap()
+}
+
+/// Validate a parameter file on disk (failable).
+///
+/// # See also
+///
+/// [`dftd3_validate_param_file`]
+pub fn dftd3_validate_param_file_f<P: AsRef<Path>>(
+    path: P,
+) -> Result<ParameterLintReport, DFTD3Error> {
+    let path = path.as_ref();
+    let toml_str = std::fs::read_to_string(path).map_err(|e| {
+        DFTD3Error::ParametersError(format!(
+            "Failed to read parameter file {}: {e}",
+            path.display()
+        ))
+    })?;
+    Ok(validate_toml_str(&toml_str, &path.display().to_string(), ParameterDatabase::embedded()))
+}
+
+fn validate_toml_str(
+    toml_str: &str,
+    source: &str,
+    base: &ParameterDatabase,
+) -> ParameterLintReport {
+    let mut report = ParameterLintReport { source: source.to_string(), lints: vec![] };
+    let root: Table = match toml::from_str(toml_str) {
+        Ok(root) => root,
+        Err(e) => {
+            report.push("", ParameterLintIssue::Syntax { error: e.to_string() });
+            return report;
+        },
+    };
+    for key in root.keys().filter(|k| !["default", "parameter", "citation"].contains(&k.as_str())) {
+        report.push(key, ParameterLintIssue::UnknownField { field: key.clone() });
+    }
+    if let Some(citation) = table_at(&root, "citation", "citation", &mut report) {
+        validate_citations(citation, &mut report);
+    }
+
+    // [default]: variant order and variant defaults
+    let mut defaults = Table::new();
+    if let Some(default) = table_at(&root, "default", "default", &mut report) {
+        for (key, value) in default {
+            match key.as_str() {
+                "d3" => validate_default_variants(value, &mut report),
+                "parameter" => {
+                    let d3 = value.as_table().and_then(|p| p.get("d3"));
+                    match d3.and_then(|d3| d3.as_table()) {
+                        Some(d3) => defaults = d3.clone(),
+                        None => report.push("default.parameter", ParameterLintIssue::Syntax {
+                            error: "expected table 'd3'".into(),
+                        }),
+                    }
+                },
+                _ => {
+                    report.push("default", ParameterLintIssue::UnknownField { field: key.clone() })
+                },
+            }
+        }
+    }
+    for (version, entry) in &defaults {
+        let location = format!("default.parameter.d3.{version}");
+        if let Some(entry) = variant_entry(version, entry, &location, &mut report) {
+            validate_fields(version, entry, &["damping"], &location, &mut report);
+        }
+    }
+
+    // [parameter.<method>]
+    let Some(parameter) = table_at(&root, "parameter", "parameter", &mut report) else {
+        return report;
+    };
+    let mut normalized: Vec<(String, &String)> = vec![];
+    let method_name = |alias: &str| {
+        let alias = normalize_method(alias);
+        match parameter.keys().find(|m| normalize_method(m) == alias) {
+            Some(method) => Some(method.clone()),
+            None if base.resolve_alias(&alias).is_none() => {
+                base.resolve_method(&alias).map(|m| m.to_string())
+            },
+            None => None,
+        }
+    };
+    for (method, variants) in parameter {
+        let location = format!("parameter.{method}");
+        let name = normalize_method(method);
+        if let Some((_, other)) = normalized.iter().find(|(n, _)| *n == name) {
+            report.push(&location, ParameterLintIssue::NameCollision { other: (*other).clone() });
+        }
+        normalized.push((name, method));
+
+        let Some(variants) = variants.as_table() else {
+            report.push(&location, ParameterLintIssue::Syntax { error: "expected table".into() });
+            continue;
+        };
+        for (key, value) in variants {
+            match key.as_str() {
+                "meta" => {
+                    let location = format!("{location}.meta");
+                    validate_meta(value, &location, &mut report);
+                    let meta = MethodMetadata::deserialize(value.clone()).unwrap_or_default();
+                    for alias in meta.name.iter().chain(&meta.aliases) {
+                        if normalize_method(alias) == normalize_method(method) {
+                            continue;
+                        }
+                        if let Some(method) = method_name(alias) {
+                            let alias = alias.clone();
+                            report.push(&location, ParameterLintIssue::AliasCollision {
+                                alias,
+                                method,
+                            });
+                        }
+                    }
+                },
+                "d3" => {
+                    let Some(d3) = table_at(variants, "d3", &format!("{location}.d3"), &mut report)
+                    else {
+                        continue;
+                    };
+                    for (version, entry) in d3 {
+                        let location = format!("{location}.d3.{version}");
+                        let Some(entry) = variant_entry(version, entry, &location, &mut report)
+                        else {
+                            continue;
+                        };
+                        validate_fields(version, entry, &["doi"], &location, &mut report);
+                        let defaults = defaults
+                            .get(version)
+                            .and_then(|d| d.as_table())
+                            .or_else(|| base.defaults(version));
+                        validate_deserialize(version, entry, defaults, &location, &mut report);
+                    }
+                },
+                _ => {
+                    report.push(&location, ParameterLintIssue::UnknownField { field: key.clone() })
+                },
+            }
+        }
+    }
+    report
+}
+
+/// Sub-table of a table, if present; reports a syntax issue if not a table.
+fn table_at<'a>(
+    table: &'a Table,
+    key: &str,
+    location: &str,
+    report: &mut ParameterLintReport,
+) -> Option<&'a Table> {
+    let value = table.get(key)?;
+    let table = value.as_table();
+    if table.is_none() {
+        report.push(location, ParameterLintIssue::Syntax { error: "expected table".into() });
+    }
+    table
+}
+
+/// Entry table of a known variant.
+fn variant_entry<'a>(
+    version: &str,
+    entry: &'a Value,
+    location: &str,
+    report: &mut ParameterLintReport,
+) -> Option<&'a Table> {
+    if variant_fields(version).is_none() {
+        report.push(location, ParameterLintIssue::UnknownField { field: version.to_string() });
+        return None;
+    }
+    let entry = entry.as_table();
+    if entry.is_none() {
+        report.push(location, ParameterLintIssue::Syntax { error: "expected table".into() });
+    }
+    entry
+}
+
+fn validate_default_variants(value: &Value, report: &mut ParameterLintReport) {
+    let variants = value.as_array().map(|a| a.iter().map(|v| v.as_str()).collect::<Vec<_>>());
+    match variants {
+        Some(variants) if variants.iter().all(|v| v.is_some()) => {
+            for version in variants.into_iter().flatten() {
+                if variant_fields(version).is_none() {
+                    let field = version.to_string();
+                    report.push("default.d3", ParameterLintIssue::UnknownField { field });
+                }
+            }
+        },
+        _ => report.push("default", ParameterLintIssue::InvalidValue {
+            field: "d3".into(),
+            expected: "an array of variants",
+        }),
+    }
+}
+
+/// Check fields of an entry: known, numeric (`extra` fields are strings),
+/// plausible values and DOI.
+fn validate_fields(
+    version: &str,
+    entry: &Table,
+    extra: &[&str],
+    location: &str,
+    report: &mut ParameterLintReport,
+) {
+    let fields = variant_fields(version).unwrap_or_default();
+    for (field, value) in entry {
+        if extra.contains(&field.as_str()) {
+            match value.as_str() {
+                Some(doi) if field == "doi" && !is_valid_doi(doi) => {
+                    report.push(location, ParameterLintIssue::MalformedDoi { doi: doi.to_string() })
+                },
+                Some(_) => (),
+                None => report.push(location, ParameterLintIssue::InvalidValue {
+                    field: field.clone(),
+                    expected: "a string",
+                }),
+            }
+        } else if !fields.contains(&field.as_str()) {
+            report.push(location, ParameterLintIssue::UnknownField { field: field.clone() });
+        } else if let Some(value) =
+            value.as_float().or_else(|| value.as_integer().map(|i| i as f64))
+        {
+            let range = PLAUSIBLE_RANGES.iter().find(|(f, ..)| f == field);
+            if let Some(&(_, min, max)) =
+                range.filter(|(_, min, max)| !(*min..=*max).contains(&value))
+            {
+                report.push(location, ParameterLintIssue::ImplausibleValue {
+                    field: field.clone(),
+                    value,
+                    range: (min, max),
+                });
+            }
+        } else {
+            report.push(location, ParameterLintIssue::InvalidValue {
+                field: field.clone(),
+                expected: "a number",
+            });
+        }
+    }
+}
+
+fn validate_meta(meta: &Value, location: &str, report: &mut ParameterLintReport) {
+    let Some(table) = meta.as_table() else {
+        report.push(location, ParameterLintIssue::Syntax { error: "expected table".into() });
+        return;
+    };
+    for key in table.keys().filter(|k| !META_FIELDS.contains(&k.as_str())) {
+        report.push(location, ParameterLintIssue::UnknownField { field: key.clone() });
+    }
+    if let Err(e) = MethodMetadata::deserialize(meta.clone()) {
+        report.push(location, ParameterLintIssue::Deserialize { error: e.message().to_string() });
+    }
+}
+
+fn validate_citations(citation: &Table, report: &mut ParameterLintReport) {
+    for (doi, entry) in citation {
+        let location = format!("citation.\"{doi}\"");
+        if !is_valid_doi(doi) {
+            report.push(&location, ParameterLintIssue::MalformedDoi { doi: doi.clone() });
+        }
+        let Some(entry) = entry.as_table() else {
+            report.push(&location, ParameterLintIssue::Syntax { error: "expected table".into() });
+            continue;
+        };
+        for (field, value) in entry {
+            let expected = match field.as_str() {
+                "author" => {
+                    let authors = value.as_array();
+                    match authors.is_some_and(|a| a.iter().all(|v| v.is_str())) {
+                        true => continue,
+                        false => "an array of strings",
+                    }
+                },
+                "published" => continue,
+                _ if !CITATION_FIELDS.contains(&field.as_str()) => {
+                    let field = field.clone();
+                    report.push(&location, ParameterLintIssue::UnknownField { field });
+                    continue;
+                },
+                _ if value.is_str() => continue,
+                _ => "a string",
+            };
+            report.push(&location, ParameterLintIssue::InvalidValue {
+                field: field.clone(),
+                expected,
+            });
+        }
+    }
+}
+
+/// Deserialize an entry with variant defaults merged (variants enabled by
+/// crate features only).
+fn validate_deserialize(
+    version: &str,
+    entry: &Table,
+    defaults: Option<&Table>,
+    location: &str,
+    report: &mut ParameterLintReport,
+) {
+    let enabled = cfg!(feature = "api-v0_4")
+        && (version != "op" || cfg!(feature = "api-v0_5"))
+        && (version != "cso" || cfg!(feature = "api-v1_3"));
+    if !enabled {
+        return;
+    }
+    let Some(defaults) = defaults else {
+        let error = format!("no variant defaults for '{version}'");
+        report.push(location, ParameterLintIssue::Deserialize { error });
+        return;
+    };
+    let mut merged = merge_tables(entry, defaults);
+    merged.remove("damping");
+    if let Err(e) = convert_to_damping_param(&merged, version) {
+        report.push(location, ParameterLintIssue::Deserialize {
+            error: e.get_message().trim_end().to_string(),
+        });
+    }
+}
+
+/// Whether a DOI is of the form `10.<registrant>/<suffix>`.
+fn is_valid_doi(doi: &str) -> bool {
+    let Some((prefix, suffix)) = doi.split_once('/') else { return false };
+    let registrant = prefix.strip_prefix("10.").unwrap_or_default();
+    !registrant.is_empty()
+        && registrant.split('.').all(|p| !p.is_empty() && p.chars().all(|c| c.is_ascii_digit()))
+        && !suffix.is_empty()
+        && !suffix.chars().any(char::is_whitespace)
+}
+
+/* #endregion */
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_embedded() {
+        // known issue of the embedded database: CF22D D3(0) without s8 (fails
+        // to load)
+        let report = dftd3_validate_param_toml(include_str!("parameters.toml"));
+        let lints: Vec<String> =
+            report.lints.iter().map(|l| format!("{}: {}", l.location, l.issue)).collect();
+        assert_eq!(lints, ["parameter.cf22d.d3.zero: Deserialization error: missing field `s8`"]);
+
+        let report = dftd3_validate_param_toml(
+            r#"
+            [parameter.x-1]
+            d3.bj = {a1=0.4, s8=6.0, a2=-1.0, rs6=1.2, doi="doi:10.1/x"}
+            [parameter.x_1]
+            d3.zero = {rs6=1.2, s8="1.0"}
+            [citation."10.5555/x"]
+            author = "A B"
+            "#,
+        );
+        use ParameterLintIssue::*;
+        let issues: Vec<&ParameterLintIssue> = report.lints.iter().map(|l| &l.issue).collect();
+        assert_eq!(report.warnings().len(), 2);
+        assert!(issues.contains(&&UnknownField { field: "rs6".into() }));
+        assert!(issues.contains(&&MalformedDoi { doi: "doi:10.1/x".into() }));
+        assert!(issues.contains(&&NameCollision { other: "x-1".into() }));
+        assert!(issues.contains(&&InvalidValue { field: "s8".into(), expected: "a number" }));
+        assert!(issues
+            .contains(&&InvalidValue { field: "author".into(), expected: "an array of strings" }));
+        assert!(report.to_string().contains("warning: parameter.x-1.d3.bj: s8 = 6 outside"));
+
+        // aliases naming a method of the file or of the database
+        let report = dftd3_validate_param_toml(
+            "[parameter.x]\nmeta = {name=\"X\", aliases=[\"PBE\", \"y\", \"pbeh\"]}\n[parameter.y]",
+        );
+        let issues: Vec<&ParameterLintIssue> = report.lints.iter().map(|l| &l.issue).collect();
+        assert_eq!(issues, [
+            &AliasCollision { alias: "PBE".into(), method: "pbe".into() },
+            &AliasCollision { alias: "y".into(), method: "y".into() }
+        ]);
+
+        assert!(is_valid_doi("10.1021/acs.jctc.5b00400"));
+        assert!(is_valid_doi("10.1000.10/abc"));
+        assert!(!is_valid_doi("doi:10.1021/x"));
+        assert!(!is_valid_doi("10.1021/"));
+        assert!(!is_valid_doi("10.10a1/x"));
+    }
+}